@@ -5,7 +5,6 @@ use ads_proto::proto::ams_header::{AmsHeader, AmsTcpHeader};
 use ads_proto::proto::proto_traits::*;
 use ads_proto::proto::request::*;
 use ads_proto::proto::state_flags::StateFlags;
-use anyhow;
 use std::result::Result;
 use std::str::FromStr;
 
@@ -50,7 +49,7 @@ fn main() -> Result<(), anyhow::Error> {
 
     //If we want to send over TCP we need to put this AMS header into a AmsTcpHeader
     let mut tcp_ams_header = AmsTcpHeader::from(ams_header);
-    print!("Our AmsTcpHeader struct:\n{:?}\n", tcp_ams_header);
+    println!("Our AmsTcpHeader struct:\n{:?}", tcp_ams_header);
 
     //Finally create the byte buffer.
     let mut buffer: Vec<u8> = Vec::new();
@@ -74,7 +73,7 @@ fn main() -> Result<(), anyhow::Error> {
         "Byte buffer (AmsTcpHeader) with read state request\n{:?}\n",
         new_buffer
     );
-    print!("==========================================================================\n");
+    println!("==========================================================================");
     //=========================================================
 
     //Reading a request (server/router)
@@ -87,49 +86,40 @@ fn main() -> Result<(), anyhow::Error> {
     match request {
         Request::Invalid(_) => panic!(),
         Request::Read(r) => {
-            let r: ReadRequest = r.try_into()?;
             print!("var handle is {:?}", r.index_offset);
         }
-        Request::ReadDeviceInfo(r) => {
-            let _r: ReadDeviceInfoRequest = r.try_into()?;
+        Request::ReadDeviceInfo(_) => {
             print!("recieved a read device info request");
         }
-        Request::ReadState(r) => {
-            let _r: ReadStateRequest = r.try_into()?;
+        Request::ReadState(_) => {
             print!("recieved a read state request");
         }
         Request::ReadWrite(r) => {
-            let r: ReadWriteRequest = r.try_into()?;
-            print!("received a read write request...\n");
-            print!("Command id  :{:?} \n", r.command_id);
-            print!("Data        :{:?} \n", r.data);
-            print!("Index group :{:?} \n", r.index_group);
-            print!("Index offset:{:?} \n", r.index_offset);
-            print!("Read length :{:?} \n", r.read_length);
-            print!("Write length:{:?} \n", r.write_length);
+            println!("received a read write request...");
+            println!("Command id  :{:?}", r.command_id);
+            println!("Data        :{:?}", r.data);
+            println!("Index group :{:?}", r.index_group);
+            println!("Index offset:{:?}", r.index_offset);
+            println!("Read length :{:?}", r.read_length);
+            println!("Write length:{:?}", r.write_length);
         }
         Request::Write(r) => {
-            let r: WriteRequest = r.try_into()?;
             print!("recieved a write request");
             print!("data to write -> {:?}", r.data);
         }
         Request::WriteControl(r) => {
-            let r: WriteControlRequest = r.try_into()?;
             print!("recieved a write control request:");
             print!("requested ads state is: {:?}", r.ads_state);
         }
         Request::AddDeviceNotification(r) => {
-            let r: AddDeviceNotificationRequest = r.try_into()?;
             print!("recieved an add device notification request");
             print!("transition mode is -> {:?}", r.transmission_mode);
         }
         Request::DeleteDeviceNotification(r) => {
-            let r: DeleteDeviceNotificationRequest = r.try_into()?;
             print!("recieved a delete device notification request");
             print!("delete notification for handle -> {:?}", r.handle);
         }
-        Request::DeviceNotification(r) => {
-            let _r: DeviceNotificationRequest = r.try_into()?;
+        Request::DeviceNotification(_) => {
             print!("recieved a device notification (?)");
         }
     }
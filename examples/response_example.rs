@@ -5,7 +5,6 @@ use ads_proto::proto::ams_header::{AmsHeader, AmsTcpHeader};
 use ads_proto::proto::proto_traits::*;
 use ads_proto::proto::response::*;
 use ads_proto::proto::state_flags::StateFlags;
-use anyhow;
 use std::result::Result;
 use std::str::FromStr;
 
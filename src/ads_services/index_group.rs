@@ -0,0 +1,225 @@
+use std::fmt;
+
+macro_rules! index_groups {
+    ($($(#[$doc:meta])* $variant:ident = $value:expr, $name:expr;)*) => {
+        ///Well known index groups of the ADS services.
+        ///Index groups which are not in the catalog are kept as `Unknown`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum IndexGroup {
+            $($(#[$doc])* $variant,)*
+            Unknown(u32),
+        }
+
+        impl IndexGroup {
+            pub const fn as_u32(&self) -> u32 {
+                match self {
+                    $(IndexGroup::$variant => $value,)*
+                    IndexGroup::Unknown(value) => *value,
+                }
+            }
+
+            ///Name of the index group as in the TwinCAT headers, None for unknown index groups
+            pub fn name(&self) -> Option<&'static str> {
+                match self {
+                    $(IndexGroup::$variant => Some($name),)*
+                    IndexGroup::Unknown(_) => None,
+                }
+            }
+        }
+
+        impl From<u32> for IndexGroup {
+            fn from(value: u32) -> Self {
+                match value {
+                    $($value => IndexGroup::$variant,)*
+                    value => IndexGroup::Unknown(value),
+                }
+            }
+        }
+    };
+}
+
+index_groups! {
+    ///PLC memory (%M) byte access
+    PlcRwMb = 0x4020, "ADSIGRP_PLC_RWMB";
+    ///PLC memory (%M) bit access
+    PlcRwMx = 0x4021, "ADSIGRP_PLC_RWMX";
+
+    ///Symbol table
+    SymTab = 0xF000, "ADSIGRP_SYMTAB";
+    ///Symbol name
+    SymName = 0xF001, "ADSIGRP_SYMNAME";
+    ///Symbol value
+    SymVal = 0xF002, "ADSIGRP_SYMVAL";
+    ///Get a symbol handle by name
+    SymHndByName = 0xF003, "ADSIGRP_SYM_HNDBYNAME";
+    ///Read or write a symbol value by name
+    SymValByName = 0xF004, "ADSIGRP_SYM_VALBYNAME";
+    ///Read or write a symbol value by handle
+    SymValByHnd = 0xF005, "ADSIGRP_SYM_VALBYHND";
+    ///Release a symbol handle
+    SymReleaseHnd = 0xF006, "ADSIGRP_SYM_RELEASEHND";
+    ///Symbol info by name
+    SymInfoByName = 0xF007, "ADSIGRP_SYM_INFOBYNAME";
+    ///Version of the symbol table
+    SymVersion = 0xF008, "ADSIGRP_SYM_VERSION";
+    ///Extended symbol info by name
+    SymInfoByNameEx = 0xF009, "ADSIGRP_SYM_INFOBYNAMEEX";
+    ///Symbol download
+    SymDownload = 0xF00A, "ADSIGRP_SYM_DOWNLOAD";
+    ///Symbol upload
+    SymUpload = 0xF00B, "ADSIGRP_SYM_UPLOAD";
+    ///Symbol upload info (count and length)
+    SymUploadInfo = 0xF00C, "ADSIGRP_SYM_UPLOADINFO";
+    ///Symbol download with a version
+    SymDownload2 = 0xF00D, "ADSIGRP_SYM_DOWNLOAD2";
+    ///Data type upload
+    SymDtUpload = 0xF00E, "ADSIGRP_SYM_DT_UPLOAD";
+    ///Symbol and data type upload info
+    SymUploadInfo2 = 0xF00F, "ADSIGRP_SYM_UPLOADINFO2";
+    ///Notification of named handle
+    SymNote = 0xF010, "ADSIGRP_SYMNOTE";
+
+    ///Input image (%I) byte access
+    IoImageRwIb = 0xF020, "ADSIGRP_IOIMAGE_RWIB";
+    ///Input image (%I) bit access
+    IoImageRwIx = 0xF021, "ADSIGRP_IOIMAGE_RWIX";
+    ///Size of the input image
+    IoImageRiSize = 0xF025, "ADSIGRP_IOIMAGE_RISIZE";
+    ///Output image (%Q) byte access
+    IoImageRwOb = 0xF030, "ADSIGRP_IOIMAGE_RWOB";
+    ///Output image (%Q) bit access
+    IoImageRwOx = 0xF031, "ADSIGRP_IOIMAGE_RWOX";
+    ///Size of the output image
+    IoImageRoSize = 0xF035, "ADSIGRP_IOIMAGE_ROSIZE";
+    ///Clear the input image
+    IoImageClearI = 0xF040, "ADSIGRP_IOIMAGE_CLEARI";
+    ///Clear the output image
+    IoImageClearO = 0xF050, "ADSIGRP_IOIMAGE_CLEARO";
+    ///Write the inputs and read the outputs
+    IoImageRwIob = 0xF060, "ADSIGRP_IOIMAGE_RWIOB";
+
+    ///Sum up read
+    SumUpRead = 0xF080, "ADSIGRP_SUMUP_READ";
+    ///Sum up write
+    SumUpWrite = 0xF081, "ADSIGRP_SUMUP_WRITE";
+    ///Sum up read write
+    SumUpReadWrite = 0xF082, "ADSIGRP_SUMUP_READWRITE";
+    ///Sum up read with the length of the read data
+    SumUpReadEx = 0xF083, "ADSIGRP_SUMUP_READEX";
+    ///Sum up read with the result and length of each read
+    SumUpReadEx2 = 0xF084, "ADSIGRP_SUMUP_READEX2";
+    ///Sum up add device notification
+    SumUpAddDevNote = 0xF085, "ADSIGRP_SUMUP_ADDDEVNOTE";
+    ///Sum up delete device notification
+    SumUpDelDevNote = 0xF086, "ADSIGRP_SUMUP_DELDEVNOTE";
+
+    ///Device data (ADS state, device name, ...)
+    DeviceData = 0xF100, "ADSIGRP_DEVICE_DATA";
+
+    ///CoE SDO access of an EtherCAT slave
+    CanOpenSdo = 0xF302, "ADSIGRP_CANOPEN_SDO";
+
+    ///System service: open a file
+    SystemServiceFOpen = 0x78, "SYSTEMSERVICE_FOPEN";
+    ///System service: close a file
+    SystemServiceFClose = 0x79, "SYSTEMSERVICE_FCLOSE";
+    ///System service: read a file
+    SystemServiceFRead = 0x7A, "SYSTEMSERVICE_FREAD";
+    ///System service: write a file
+    SystemServiceFWrite = 0x7B, "SYSTEMSERVICE_FWRITE";
+    ///System service: seek in a file
+    SystemServiceFSeek = 0x7C, "SYSTEMSERVICE_FSEEK";
+    ///System service: position in a file
+    SystemServiceFTell = 0x7D, "SYSTEMSERVICE_FTELL";
+    ///System service: read a line of a file
+    SystemServiceFGets = 0x7E, "SYSTEMSERVICE_FGETS";
+    ///System service: write a line to a file
+    SystemServiceFPuts = 0x7F, "SYSTEMSERVICE_FPUTS";
+    ///System service: formatted read of a file
+    SystemServiceFScanf = 0x80, "SYSTEMSERVICE_FSCANF";
+    ///System service: formatted write to a file
+    SystemServiceFPrintf = 0x81, "SYSTEMSERVICE_FPRINTF";
+    ///System service: end of file
+    SystemServiceFEof = 0x82, "SYSTEMSERVICE_FEOF";
+    ///System service: delete a file
+    SystemServiceFDelete = 0x83, "SYSTEMSERVICE_FDELETE";
+    ///System service: rename a file
+    SystemServiceFRename = 0x84, "SYSTEMSERVICE_FRENAME";
+    ///System service: find first/next file
+    SystemServiceFFileFind = 0x85, "SYSTEMSERVICE_FFILEFIND";
+    ///System service: create a directory
+    SystemServiceMkDir = 0x8A, "SYSTEMSERVICE_MKDIR";
+    ///System service: remove a directory
+    SystemServiceRmDir = 0x8B, "SYSTEMSERVICE_RMDIR";
+    ///System service: registry of HKEY_LOCAL_MACHINE
+    SystemServiceRegHKeyLocalMachine = 0xC8, "SYSTEMSERVICE_REGHKEYLOCALMACHINE";
+    ///System service: send an email
+    SystemServiceSendEmail = 0x12C, "SYSTEMSERVICE_SENDEMAIL";
+    ///System service: time services
+    SystemServiceTimeServices = 0x190, "SYSTEMSERVICE_TIMESERVICES";
+    ///System service: start a process
+    SystemServiceStartProcess = 0x1F4, "SYSTEMSERVICE_STARTPROCESS";
+    ///System service: change the AmsNetId
+    SystemServiceChangeNetId = 0x258, "SYSTEMSERVICE_CHANGENETID";
+    ///System service: add a remote route
+    SystemServiceAddRemoteRoute = 0x321, "SYSTEMSERVICE_ADDREMOTEROUTE";
+    ///System service: delete a remote route
+    SystemServiceDelRemoteRoute = 0x322, "SYSTEMSERVICE_DELREMOTEROUTE";
+    ///System service: enumerate the remote routes
+    SystemServiceEnumRemoteRoute = 0x323, "SYSTEMSERVICE_ENUMREMOTEROUTE";
+}
+
+impl From<IndexGroup> for u32 {
+    fn from(index_group: IndexGroup) -> Self {
+        index_group.as_u32()
+    }
+}
+
+///Formats as the name of the index group, unknown index groups as hex number
+impl fmt::Display for IndexGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:#06X}", self.as_u32()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ads_services::system_services::{
+        ADSIGRP_SUMUP_READWRITE, GET_SYMHANDLE_BY_NAME, SYSTEMSERVICE_FOPEN,
+    };
+
+    #[test]
+    fn index_group_from_u32_test() {
+        assert_eq!(IndexGroup::from(0x4020), IndexGroup::PlcRwMb);
+        assert_eq!(
+            IndexGroup::from(GET_SYMHANDLE_BY_NAME.index_group),
+            IndexGroup::SymHndByName
+        );
+        assert_eq!(
+            IndexGroup::from(ADSIGRP_SUMUP_READWRITE.index_group),
+            IndexGroup::SumUpReadWrite
+        );
+        assert_eq!(
+            IndexGroup::from(SYSTEMSERVICE_FOPEN.index_group),
+            IndexGroup::SystemServiceFOpen
+        );
+        assert_eq!(IndexGroup::from(0x1234), IndexGroup::Unknown(0x1234));
+
+        for value in [0x4021, 0xF00F, 0xF031, 0xF086, 0xF100, 0xF302, 0x85, 0x1234] {
+            assert_eq!(u32::from(IndexGroup::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn index_group_display_test() {
+        assert_eq!(IndexGroup::SymValByHnd.to_string(), "ADSIGRP_SYM_VALBYHND");
+        assert_eq!(IndexGroup::IoImageRwOb.to_string(), "ADSIGRP_IOIMAGE_RWOB");
+        assert_eq!(IndexGroup::DeviceData.to_string(), "ADSIGRP_DEVICE_DATA");
+        assert_eq!(IndexGroup::Unknown(0x1234).to_string(), "0x1234");
+        assert_eq!(IndexGroup::Unknown(0x1234).name(), None);
+    }
+}
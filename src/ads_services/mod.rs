@@ -1 +1,3 @@
+///Typed catalog of the well known index groups with their names.
+pub mod index_group;
 pub mod system_services;
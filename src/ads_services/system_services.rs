@@ -28,6 +28,15 @@ pub const RELEASE_SYMHANDLE: AdsService = AdsService {
     index_offset_end: 0xFFFFFFFF,
 };
 
+/// Index offset = Number of internal sub-commands.
+/// Max commands = 500
+/// Response: the results of all sub-commands followed by the data (read length of each sub-command)
+pub const ADSIGRP_SUMUP_READ: AdsService = AdsService {
+    index_group: 0x0000F080,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
 /// Index offset = Number of internal sub-commands.
 /// Max commands = 500
 pub const ADSIGRP_SUMUP_WRITE: AdsService = AdsService {
@@ -44,6 +53,15 @@ pub const ADSIGRP_SUMUP_READEX: AdsService = AdsService {
     index_offset_end: 0xFFFFFFFF,
 };
 
+/// Index offset = Number of internal sub-commands.
+/// Max commands = 500
+/// Same as ADSIGRP_SUMUP_READEX, the data of each sub-command is returned with its actual length
+pub const ADSIGRP_SUMUP_READEX2: AdsService = AdsService {
+    index_group: 0x0000F084,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
 /// Index offset = Number of internal sub-commands.
 /// Max commands = 500
 pub const ADSIGRP_SUMUP_READWRITE: AdsService = AdsService {
@@ -51,3 +69,181 @@ pub const ADSIGRP_SUMUP_READWRITE: AdsService = AdsService {
     index_offset_start: 0x00000000,
     index_offset_end: 0xFFFFFFFF,
 };
+
+///PLC memory range (%M field).
+///Index offset = byte offset
+pub const ADSIGRP_PLC_RWMB: AdsService = AdsService {
+    index_group: 0x00004020,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///PLC memory range (%MX field).
+///Index offset = bit offset (byte offset * 8 + bit)
+pub const ADSIGRP_PLC_RWMX: AdsService = AdsService {
+    index_group: 0x00004021,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Process image of the inputs (%I field).
+///Index offset = byte offset
+pub const ADSIGRP_IOIMAGE_RWIB: AdsService = AdsService {
+    index_group: 0x0000F020,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Process image of the inputs (%IX field).
+///Index offset = bit offset (byte offset * 8 + bit)
+pub const ADSIGRP_IOIMAGE_RWIX: AdsService = AdsService {
+    index_group: 0x0000F021,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Process image of the outputs (%Q field).
+///Index offset = byte offset
+pub const ADSIGRP_IOIMAGE_RWOB: AdsService = AdsService {
+    index_group: 0x0000F030,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Process image of the outputs (%QX field).
+///Index offset = bit offset (byte offset * 8 + bit)
+pub const ADSIGRP_IOIMAGE_RWOX: AdsService = AdsService {
+    index_group: 0x0000F031,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Read the symbol entries of all symbols (symbol upload).
+///Length from ADSIGRP_SYM_UPLOADINFO
+pub const ADSIGRP_SYM_UPLOAD: AdsService = AdsService {
+    index_group: 0x0000F00B,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///Read symbol count and length of the symbol upload (8 bytes)
+pub const ADSIGRP_SYM_UPLOADINFO: AdsService = AdsService {
+    index_group: 0x0000F00C,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///Read the data type entries of all data types (data type upload).
+///Length from ADSIGRP_SYM_UPLOADINFO2
+pub const ADSIGRP_SYM_DT_UPLOAD: AdsService = AdsService {
+    index_group: 0x0000F00E,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///Read count and length of the symbol and data type upload (24 bytes)
+pub const ADSIGRP_SYM_UPLOADINFO2: AdsService = AdsService {
+    index_group: 0x0000F00F,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///AMS port of the TwinCAT system service (file access, routes, ...)
+pub const SYSTEM_SERVICE_PORT: u16 = 10000;
+
+///Open a file. ReadWrite, write data = null terminated path, read data = file handle (4 bytes).
+///Index offset = open mode flags | base path << 16
+pub const SYSTEMSERVICE_FOPEN: AdsService = AdsService {
+    index_group: 0x00000078,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Close a file or find handle.
+///Index offset = handle
+pub const SYSTEMSERVICE_FCLOSE: AdsService = AdsService {
+    index_group: 0x00000079,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Read from a file. ReadWrite, read length = max bytes to read, less are returned at the end of the file.
+///Index offset = file handle
+pub const SYSTEMSERVICE_FREAD: AdsService = AdsService {
+    index_group: 0x0000007A,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Write to a file. ReadWrite, write data = bytes to write.
+///Index offset = file handle
+pub const SYSTEMSERVICE_FWRITE: AdsService = AdsService {
+    index_group: 0x0000007B,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Delete a file. ReadWrite, write data = null terminated path.
+///Index offset = base path << 16
+pub const SYSTEMSERVICE_FDELETE: AdsService = AdsService {
+    index_group: 0x00000083,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Find first (write data = null terminated path pattern, index offset = base path << 16)
+///or find next (no write data, index offset = find handle). Read data = find entry (324 bytes)
+pub const SYSTEMSERVICE_FFILEFIND: AdsService = AdsService {
+    index_group: 0x00000085,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Create a directory. ReadWrite, write data = null terminated path.
+///Index offset = base path << 16
+pub const SYSTEMSERVICE_MKDIR: AdsService = AdsService {
+    index_group: 0x0000008A,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///Remove an empty directory. ReadWrite, write data = null terminated path.
+///Index offset = base path << 16
+pub const SYSTEMSERVICE_RMDIR: AdsService = AdsService {
+    index_group: 0x0000008B,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///CoE SDO upload (Read) and download (Write) through the EtherCAT master on the AMS port of the slave.
+///Index offset = CoE index << 16 | subindex, 0x100 for complete access
+pub const ADSIGRP_CANOPEN_SDO: AdsService = AdsService {
+    index_group: 0x0000F302,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0xFFFFFFFF,
+};
+
+///AMS port of the EtherCAT master device. The AmsNetId is the one of the EtherCAT device (e.g. x.x.x.x.3.1),
+///the slaves are reached on the same AmsNetId with their fixed address as port.
+pub const ETHERCAT_MASTER_PORT: u16 = 0xFFFF;
+
+///Read the number of configured EtherCAT slaves (2 bytes) on the master port
+pub const ADSIGRP_ECAT_SLAVE_COUNT: AdsService = AdsService {
+    index_group: 0x00000006,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///Read the fixed addresses of all EtherCAT slaves (2 bytes each) on the master port
+pub const ADSIGRP_ECAT_SLAVE_ADDRESSES: AdsService = AdsService {
+    index_group: 0x00000007,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
+
+///Read the EtherCAT state and link state of all slaves (2 bytes each) on the master port
+pub const ADSIGRP_ECAT_SLAVE_STATES: AdsService = AdsService {
+    index_group: 0x00000009,
+    index_offset_start: 0x00000000,
+    index_offset_end: 0x00000000,
+};
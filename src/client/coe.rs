@@ -0,0 +1,201 @@
+use crate::client::{expect_response, AdsClient};
+use crate::proto::ads_type::AdsType;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::coe::CoeSdo;
+use crate::proto::request::Request;
+use crate::proto::response::{ReadResponse, WriteResponse};
+use std::io;
+
+///SDO upload of up to `length` bytes from the slave (AmsNetId of the EtherCAT master, port = slave address).
+///An ADS error (e.g. the abort of the SDO) is returned as io error with the [AdsError](crate::error::AdsError).
+pub fn sdo_upload<C: AdsClient>(
+    client: &mut C,
+    slave: &AmsAddress,
+    sdo: CoeSdo,
+    length: u32,
+) -> io::Result<Vec<u8>> {
+    let response = client.request(slave, Request::Read(sdo.upload(length)))?;
+    expect_response::<ReadResponse>(response).map(|r| r.data)
+}
+
+///SDO upload of a value with the size of T
+/// ```no_run
+/// use ads_proto::client::coe::sdo_read;
+/// use ads_proto::proto::ams_address::{AmsAddress, AmsNetId};
+/// use ads_proto::proto::coe::CoeSdo;
+/// # fn vendor<C: ads_proto::client::AdsClient>(client: &mut C) -> std::io::Result<()> {
+///
+/// let slave = AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 3, 1), 1001);
+/// let vendor_id: u32 = sdo_read(client, &slave, CoeSdo::new(0x1018, 1))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn sdo_read<T: AdsType, C: AdsClient>(
+    client: &mut C,
+    slave: &AmsAddress,
+    sdo: CoeSdo,
+) -> io::Result<T> {
+    let data = sdo_upload(client, slave, sdo, T::SIZE)?;
+    if data.len() != T::SIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} bytes uploaded from {:#06X}:{}, expected {}",
+                data.len(),
+                sdo.index,
+                sdo.sub_index,
+                T::SIZE
+            ),
+        ));
+    }
+    T::from_bytes(&data)
+}
+
+pub fn sdo_download<C: AdsClient>(
+    client: &mut C,
+    slave: &AmsAddress,
+    sdo: CoeSdo,
+    data: Vec<u8>,
+) -> io::Result<()> {
+    let response = client.request(slave, Request::Write(sdo.download(data)))?;
+    expect_response::<WriteResponse>(response).map(|_| ())
+}
+
+pub fn sdo_write<T: AdsType, C: AdsClient>(
+    client: &mut C,
+    slave: &AmsAddress,
+    sdo: CoeSdo,
+    value: &T,
+) -> io::Result<()> {
+    sdo_download(client, slave, sdo, value.to_bytes())
+}
+
+///Download the objects in order, stops at the first error.
+///Returns the index of the failed object with the error.
+pub fn sdo_download_all<C: AdsClient>(
+    client: &mut C,
+    slave: &AmsAddress,
+    objects: &[(CoeSdo, Vec<u8>)],
+) -> Result<(), (usize, io::Error)> {
+    for (n, (sdo, data)) in objects.iter().enumerate() {
+        sdo_download(client, slave, *sdo, data.clone()).map_err(|e| (n, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ads_services::system_services::ADSIGRP_CANOPEN_SDO;
+    use crate::error::AdsError;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::proto::response::Response;
+    use std::collections::BTreeMap;
+
+    ///Object dictionary of a slave
+    struct Slave {
+        objects: BTreeMap<(u16, u8), Vec<u8>>,
+    }
+
+    impl Slave {
+        fn new() -> Self {
+            let mut objects = BTreeMap::new();
+            objects.insert((0x1018, 0), vec![2]);
+            objects.insert((0x1018, 1), vec![2, 0, 0, 0]);
+            objects.insert((0x1018, 2), vec![0x52, 0x1C, 0x09, 0x11]);
+            objects.insert((0x8011, 0x11), vec![0, 0, 0, 0]);
+            Slave { objects }
+        }
+    }
+
+    impl AdsClient for Slave {
+        fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+            assert_eq!(target.port, 1001);
+            match request {
+                Request::Read(r) => {
+                    assert_eq!(r.index_group, ADSIGRP_CANOPEN_SDO.index_group);
+                    let sdo = CoeSdo::from_index_offset(r.index_offset);
+                    let data: Vec<u8> = self
+                        .objects
+                        .range((sdo.index, sdo.sub_index)..=(sdo.index, u8::MAX))
+                        .take(if sdo.complete_access { 256 } else { 1 })
+                        .flat_map(|(_, data)| data.clone())
+                        .collect();
+                    let response = if data.is_empty() {
+                        ReadResponse::new(AdsError::AdsErrDeviceNotFound, Vec::new())
+                    } else {
+                        ReadResponse::new(AdsError::ErrNoError, data)
+                    };
+                    Ok(Response::Read(response))
+                }
+                Request::Write(r) => {
+                    let sdo = CoeSdo::from_index_offset(r.index_offset);
+                    let result = match self.objects.get_mut(&(sdo.index, sdo.sub_index)) {
+                        Some(data) if data.len() == r.data.len() => {
+                            *data = r.data;
+                            AdsError::ErrNoError
+                        }
+                        Some(_) => AdsError::AdsErrDeviceInvalidSize,
+                        None => AdsError::AdsErrDeviceNotFound,
+                    };
+                    Ok(Response::Write(WriteResponse::new(result)))
+                }
+                _ => panic!("unexpected request"),
+            }
+        }
+    }
+
+    fn slave_address() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 3, 1), 1001)
+    }
+
+    #[test]
+    fn sdo_read_write_test() {
+        let mut slave = Slave::new();
+        let vendor_id: u32 =
+            sdo_read(&mut slave, &slave_address(), CoeSdo::new(0x1018, 1)).unwrap();
+        assert_eq!(vendor_id, 2);
+
+        sdo_write(
+            &mut slave,
+            &slave_address(),
+            CoeSdo::new(0x8011, 0x11),
+            &12000u32,
+        )
+        .unwrap();
+        let speed: u32 = sdo_read(&mut slave, &slave_address(), CoeSdo::new(0x8011, 0x11)).unwrap();
+        assert_eq!(speed, 12000);
+
+        let data =
+            sdo_upload(&mut slave, &slave_address(), CoeSdo::complete(0x1018, 0), 9).unwrap();
+        assert_eq!(data, vec![2, 2, 0, 0, 0, 0x52, 0x1C, 0x09, 0x11]);
+
+        let error =
+            sdo_read::<u16, _>(&mut slave, &slave_address(), CoeSdo::new(0x1018, 1)).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error =
+            sdo_read::<u8, _>(&mut slave, &slave_address(), CoeSdo::new(0x6000, 1)).unwrap_err();
+        assert_eq!(
+            *error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            AdsError::AdsErrDeviceNotFound
+        );
+    }
+
+    #[test]
+    fn sdo_download_all_test() {
+        let mut slave = Slave::new();
+        let objects = vec![
+            (CoeSdo::new(0x8011, 0x11), 6000u32.to_bytes()),
+            (CoeSdo::new(0x1018, 2), vec![1]),
+            (CoeSdo::new(0x1018, 1), 3u32.to_bytes()),
+        ];
+        let (n, error) = sdo_download_all(&mut slave, &slave_address(), &objects).unwrap_err();
+        assert_eq!(n, 1);
+        assert_eq!(
+            *error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            AdsError::AdsErrDeviceInvalidSize
+        );
+        assert_eq!(slave.objects[&(0x8011, 0x11)], 6000u32.to_bytes());
+        assert_eq!(slave.objects[&(0x1018, 1)], vec![2, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::proto::ads_udp::{AdsUdpMessage, DeviceIdentity, ADS_UDP_PORT};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+///Device which answered a discovery request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredDevice {
+    ///Address the response was sent from
+    pub addr: SocketAddr,
+    pub identity: DeviceIdentity,
+}
+
+///Broadcast an identify request on the local network (255.255.255.255:48899)
+///and collect the responses until the timeout elapsed.
+pub fn discover(timeout: Duration) -> io::Result<Vec<DiscoveredDevice>> {
+    discover_at(
+        SocketAddr::new(Ipv4Addr::BROADCAST.into(), ADS_UDP_PORT),
+        timeout,
+    )
+}
+
+///Send an identify request to `target` (a broadcast address or a single device)
+///and collect the responses until the timeout elapsed.
+///Each device is reported once, invalid responses are ignored.
+pub fn discover_at(target: SocketAddr, timeout: Duration) -> io::Result<Vec<DiscoveredDevice>> {
+    let socket = bind_for(&target)?;
+    socket.set_broadcast(true)?;
+
+    let invoke_id = next_invoke_id();
+    let mut buffer: Vec<u8> = Vec::new();
+    AdsUdpMessage::identify_request(invoke_id).write_to(&mut buffer)?;
+    socket.send_to(&buffer, target)?;
+
+    let mut devices: Vec<DiscoveredDevice> = Vec::new();
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let (len, addr) = match socket.recv_from(&mut buffer) {
+            Ok(r) => r,
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                break
+            }
+            Err(e) => return Err(e),
+        };
+        let identity = AdsUdpMessage::read_from(&mut &buffer[..len])
+            .ok()
+            .filter(|m| m.invoke_id == invoke_id)
+            .and_then(|m| DeviceIdentity::from_response(&m).ok());
+        if let Some(identity) = identity {
+            if !devices.iter().any(|d| d.identity.net_id == identity.net_id) {
+                devices.push(DiscoveredDevice { addr, identity });
+            }
+        }
+    }
+    Ok(devices)
+}
+
+///UDP socket on any local address of the address family of the target
+pub(crate) fn bind_for(target: &SocketAddr) -> io::Result<UdpSocket> {
+    let bind_addr: SocketAddr = match target {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    UdpSocket::bind(bind_addr)
+}
+
+static NEXT_INVOKE_ID: AtomicU32 = AtomicU32::new(1);
+
+///Invoke id of a UDP request, a new one for each call
+pub(crate) fn next_invoke_id() -> u32 {
+    NEXT_INVOKE_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ads_udp::TcVersion;
+    use crate::proto::ams_address::AmsNetId;
+    use std::thread;
+
+    #[test]
+    fn discover_test() {
+        let responder = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = responder.local_addr().unwrap();
+        let stub = thread::spawn(move || {
+            let mut buffer = [0; 2048];
+            let (len, client) = responder.recv_from(&mut buffer).unwrap();
+            let request = AdsUdpMessage::read_from(&mut &buffer[..len]).unwrap();
+            assert!(!request.is_response());
+
+            let mut identity = DeviceIdentity::new(AmsNetId::new(5, 1, 2, 3, 1, 1), "CX-1234");
+            identity.tc_version = Some(TcVersion::new(3, 1, 4024));
+            let mut response: Vec<u8> = Vec::new();
+            //Wrong invoke id, garbage, valid response twice
+            identity
+                .response(request.invoke_id + 1)
+                .write_to(&mut response)
+                .unwrap();
+            responder.send_to(&response, client).unwrap();
+            responder.send_to(&[1, 2, 3], client).unwrap();
+            response.clear();
+            identity
+                .response(request.invoke_id)
+                .write_to(&mut response)
+                .unwrap();
+            responder.send_to(&response, client).unwrap();
+            responder.send_to(&response, client).unwrap();
+        });
+
+        let devices = discover_at(target, Duration::from_millis(300)).unwrap();
+        stub.join().unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].addr, target);
+        assert_eq!(devices[0].identity.net_id, AmsNetId::new(5, 1, 2, 3, 1, 1));
+        assert_eq!(devices[0].identity.host_name, "CX-1234");
+        assert_eq!(
+            devices[0].identity.tc_version,
+            Some(TcVersion::new(3, 1, 4024))
+        );
+    }
+
+    #[test]
+    fn next_invoke_id_test() {
+        let first = next_invoke_id();
+        let second = next_invoke_id();
+        assert_ne!(first, second);
+    }
+}
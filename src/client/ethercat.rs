@@ -0,0 +1,209 @@
+use crate::client::{expect_response, AdsClient};
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::ethercat::{
+    slave_addresses_from, slave_count_from, slave_states_from, EcMasterRequest, EcSlaveState,
+    EcState,
+};
+use crate::proto::request::Request;
+use crate::proto::response::{ReadResponse, Response, WriteControlResponse};
+use std::io;
+
+///Address and state of an EtherCAT slave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EcSlaveInfo {
+    ///Fixed address, also the AMS port of the slave
+    pub address: u16,
+    pub state: EcSlaveState,
+}
+
+///EtherCAT master device reached over ADS (AmsNetId of the EtherCAT device).
+/// ```no_run
+/// use ads_proto::client::ethercat::EtherCatMaster;
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// use ads_proto::proto::ethercat::EcState;
+/// # fn commissioning<C: ads_proto::client::AdsClient>(client: &mut C) -> std::io::Result<()> {
+///
+/// let mut master = EtherCatMaster::new(client, AmsNetId::new(192, 168, 1, 2, 3, 1));
+/// for slave in master.slaves()? {
+///     if !slave.state.is(EcState::Op) {
+///         println!("{}: {:?}", slave.address, slave.state);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct EtherCatMaster<'a, C: AdsClient> {
+    client: &'a mut C,
+    net_id: AmsNetId,
+}
+
+impl<'a, C: AdsClient> EtherCatMaster<'a, C> {
+    pub fn new(client: &'a mut C, net_id: AmsNetId) -> Self {
+        EtherCatMaster { client, net_id }
+    }
+
+    ///Number of configured slaves
+    pub fn slave_count(&mut self) -> io::Result<u16> {
+        slave_count_from(&self.read(EcMasterRequest::SlaveCount)?)
+    }
+
+    ///Fixed addresses of the slaves in the order of the configuration
+    pub fn slave_addresses(&mut self) -> io::Result<Vec<u16>> {
+        let count = self.slave_count()?;
+        slave_addresses_from(&self.read(EcMasterRequest::SlaveAddresses { count })?)
+    }
+
+    ///States of the slaves in the order of the configuration
+    pub fn slave_states(&mut self) -> io::Result<Vec<EcSlaveState>> {
+        let count = self.slave_count()?;
+        slave_states_from(&self.read(EcMasterRequest::SlaveStates { count })?)
+    }
+
+    ///Addresses and states of all slaves
+    pub fn slaves(&mut self) -> io::Result<Vec<EcSlaveInfo>> {
+        let count = self.slave_count()?;
+        let addresses =
+            slave_addresses_from(&self.read(EcMasterRequest::SlaveAddresses { count })?)?;
+        let states = slave_states_from(&self.read(EcMasterRequest::SlaveStates { count })?)?;
+        if addresses.len() != states.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} slave addresses but {} slave states",
+                    addresses.len(),
+                    states.len()
+                ),
+            ));
+        }
+        Ok(addresses
+            .into_iter()
+            .zip(states)
+            .map(|(address, state)| EcSlaveInfo { address, state })
+            .collect())
+    }
+
+    ///Request a state change of the slave, returns without waiting for the state
+    pub fn request_slave_state(&mut self, address: u16, state: EcState) -> io::Result<()> {
+        let request = EcMasterRequest::RequestSlaveState { address, state };
+        expect_response::<WriteControlResponse>(self.request(request)?).map(|_| ())
+    }
+
+    fn read(&mut self, request: EcMasterRequest) -> io::Result<Vec<u8>> {
+        expect_response::<ReadResponse>(self.request(request)?).map(|r| r.data)
+    }
+
+    fn request(&mut self, request: EcMasterRequest) -> io::Result<Response> {
+        let target = AmsAddress::new(self.net_id.clone(), request.port());
+        self.client.request(&target, Request::from(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ads_services::system_services::{
+        ADSIGRP_ECAT_SLAVE_ADDRESSES, ADSIGRP_ECAT_SLAVE_COUNT, ADSIGRP_ECAT_SLAVE_STATES,
+        ETHERCAT_MASTER_PORT,
+    };
+    use crate::error::AdsError;
+    use crate::proto::ethercat::{EcDeviceState, EcLinkState};
+    use crate::proto::proto_traits::WriteTo;
+
+    ///Master with the slaves (address, state)
+    struct Master {
+        slaves: Vec<(u16, EcSlaveState)>,
+    }
+
+    impl Master {
+        fn new() -> Self {
+            let state = |device_state, link_state| EcSlaveState {
+                device_state: EcDeviceState(device_state),
+                link_state: EcLinkState(link_state),
+            };
+            Master {
+                slaves: vec![
+                    (1001, state(0x08, 0x00)),
+                    (1002, state(0x12, 0x00)),
+                    (1003, state(0x01, 0x01)),
+                ],
+            }
+        }
+    }
+
+    impl AdsClient for Master {
+        fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+            match request {
+                Request::Read(r) => {
+                    assert_eq!(target.port, ETHERCAT_MASTER_PORT);
+                    let mut data: Vec<u8> = Vec::new();
+                    let index_group = r.index_group;
+                    if index_group == ADSIGRP_ECAT_SLAVE_COUNT.index_group {
+                        data.extend_from_slice(&(self.slaves.len() as u16).to_le_bytes());
+                    } else if index_group == ADSIGRP_ECAT_SLAVE_ADDRESSES.index_group {
+                        for (address, _) in &self.slaves {
+                            data.extend_from_slice(&address.to_le_bytes());
+                        }
+                    } else if index_group == ADSIGRP_ECAT_SLAVE_STATES.index_group {
+                        for (_, state) in &self.slaves {
+                            state.write_to(&mut data)?;
+                        }
+                    } else {
+                        let response = ReadResponse::new(AdsError::AdsErrDeviceInvalidGrp, data);
+                        return Ok(Response::Read(response));
+                    }
+                    data.truncate(r.length as usize);
+                    Ok(Response::Read(ReadResponse::new(
+                        AdsError::ErrNoError,
+                        data,
+                    )))
+                }
+                Request::WriteControl(r) => {
+                    let result = match self.slaves.iter_mut().find(|s| s.0 == target.port) {
+                        Some((_, state)) => {
+                            state.device_state = EcDeviceState(r.device_state as u8);
+                            AdsError::ErrNoError
+                        }
+                        None => AdsError::ErrTargetPortNotFound,
+                    };
+                    Ok(Response::WriteControl(WriteControlResponse::new(result)))
+                }
+                _ => panic!("unexpected request"),
+            }
+        }
+    }
+
+    fn net_id() -> AmsNetId {
+        AmsNetId::new(192, 168, 1, 2, 3, 1)
+    }
+
+    #[test]
+    fn ethercat_master_test() {
+        let mut client = Master::new();
+        let mut master = EtherCatMaster::new(&mut client, net_id());
+        assert_eq!(master.slave_count().unwrap(), 3);
+        assert_eq!(master.slave_addresses().unwrap(), vec![1001, 1002, 1003]);
+
+        let states = master.slave_states().unwrap();
+        assert!(states[0].is(EcState::Op));
+        assert!(states[1].device_state.error());
+        assert!(states[2].link_state.not_present());
+
+        let slaves = master.slaves().unwrap();
+        assert_eq!(slaves.len(), 3);
+        assert_eq!(slaves[1].address, 1002);
+        assert_eq!(slaves[1].state.device_state.state(), EcState::PreOp);
+    }
+
+    #[test]
+    fn ethercat_request_slave_state_test() {
+        let mut client = Master::new();
+        let mut master = EtherCatMaster::new(&mut client, net_id());
+        master.request_slave_state(1002, EcState::Op).unwrap();
+        let error = master.request_slave_state(1009, EcState::Op).unwrap_err();
+        assert_eq!(
+            *error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            AdsError::ErrTargetPortNotFound
+        );
+        assert!(master.slaves().unwrap()[1].state.is(EcState::Op));
+    }
+}
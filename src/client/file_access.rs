@@ -0,0 +1,512 @@
+use crate::ads_services::system_services::SYSTEM_SERVICE_PORT;
+use crate::client::{expect_response, AdsClient};
+use crate::error::AdsError;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::file_access::{BasePath, FileFindEntry, FileRequest, OpenMode};
+use crate::proto::proto_traits::ReadFrom;
+use crate::proto::response::ReadWriteResponse;
+use std::io::{self, Read, Write};
+
+///Max number of bytes read or written with one request
+pub const FILE_CHUNK_SIZE: usize = 0x4000;
+
+///Send a file request to the system service of the target and return the read data.
+///An ADS error of the response is returned as io error with the [AdsError].
+pub fn file_request<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    request: FileRequest,
+) -> io::Result<Vec<u8>> {
+    let target = AmsAddress::new(net_id.clone(), SYSTEM_SERVICE_PORT);
+    let response = client.request(&target, request.into())?;
+    expect_response::<ReadWriteResponse>(response).map(|r| r.data)
+}
+
+///File on the target opened by the system service.
+///Reads and writes are sent in chunks of [FILE_CHUNK_SIZE], the file is closed when dropped.
+/// ```no_run
+/// use ads_proto::client::file_access::AdsFile;
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// use ads_proto::proto::file_access::{BasePath, OpenMode};
+/// # fn deploy<C: ads_proto::client::AdsClient>(client: &mut C) -> std::io::Result<()> {
+/// use std::io::Write;
+///
+/// let net_id = AmsNetId::new(192, 168, 1, 2, 1, 1);
+/// let mut file = AdsFile::open(
+///     client,
+///     &net_id,
+///     "C:\\TwinCAT\\3.1\\Boot\\config.xml",
+///     OpenMode::WRITE | OpenMode::BINARY,
+///     BasePath::Generic,
+/// )?;
+/// file.write_all(b"<config/>")?;
+/// file.close()
+/// # }
+/// ```
+pub struct AdsFile<'a, C: AdsClient> {
+    client: &'a mut C,
+    net_id: AmsNetId,
+    handle: u32,
+    open: bool,
+}
+
+impl<'a, C: AdsClient> AdsFile<'a, C> {
+    pub fn open(
+        client: &'a mut C,
+        net_id: &AmsNetId,
+        path: &str,
+        mode: OpenMode,
+        base: BasePath,
+    ) -> io::Result<Self> {
+        let request = FileRequest::Open {
+            path: path.to_string(),
+            mode,
+            base,
+        };
+        let data = file_request(client, net_id, request)?;
+        let handle = u32::read_from(&mut data.as_slice())?;
+        Ok(AdsFile {
+            client,
+            net_id: net_id.clone(),
+            handle,
+            open: true,
+        })
+    }
+
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    ///Close the file and return the error of the close request
+    pub fn close(mut self) -> io::Result<()> {
+        self.open = false;
+        file_request(
+            self.client,
+            &self.net_id,
+            FileRequest::Close {
+                handle: self.handle,
+            },
+        )
+        .map(|_| ())
+    }
+}
+
+impl<C: AdsClient> Read for AdsFile<'_, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let length = buf.len().min(FILE_CHUNK_SIZE);
+        let request = FileRequest::Read {
+            handle: self.handle,
+            length: length as u32,
+        };
+        let data = file_request(self.client, &self.net_id, request)?;
+        if data.len() > length {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} bytes received for a read of {}", data.len(), length),
+            ));
+        }
+        buf[..data.len()].copy_from_slice(&data);
+        Ok(data.len())
+    }
+}
+
+impl<C: AdsClient> Write for AdsFile<'_, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(FILE_CHUNK_SIZE);
+        let request = FileRequest::Write {
+            handle: self.handle,
+            data: buf[..length].to_vec(),
+        };
+        file_request(self.client, &self.net_id, request)?;
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C: AdsClient> Drop for AdsFile<'_, C> {
+    fn drop(&mut self) {
+        if self.open {
+            let request = FileRequest::Close {
+                handle: self.handle,
+            };
+            let _ = file_request(self.client, &self.net_id, request);
+        }
+    }
+}
+
+pub fn delete_file<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    path: &str,
+    base: BasePath,
+) -> io::Result<()> {
+    let request = FileRequest::Delete {
+        path: path.to_string(),
+        base,
+    };
+    file_request(client, net_id, request).map(|_| ())
+}
+
+pub fn create_dir<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    path: &str,
+    base: BasePath,
+) -> io::Result<()> {
+    let request = FileRequest::MakeDir {
+        path: path.to_string(),
+        base,
+    };
+    file_request(client, net_id, request).map(|_| ())
+}
+
+///Remove an empty directory
+pub fn remove_dir<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    path: &str,
+    base: BasePath,
+) -> io::Result<()> {
+    let request = FileRequest::RemoveDir {
+        path: path.to_string(),
+        base,
+    };
+    file_request(client, net_id, request).map(|_| ())
+}
+
+///Entries matching the pattern (e.g. `C:\TwinCAT\Boot\*.xml`) with find first/next.
+///The entries `.` and `..` are skipped. No match is an empty iterator.
+pub fn read_dir<'a, C: AdsClient>(
+    client: &'a mut C,
+    net_id: &AmsNetId,
+    pattern: &str,
+    base: BasePath,
+) -> io::Result<ReadDir<'a, C>> {
+    let request = FileRequest::FindFirst {
+        pattern: pattern.to_string(),
+        base,
+    };
+    let pending = match file_request(client, net_id, request) {
+        Ok(data) => Some(FileFindEntry::read_from(&mut data.as_slice())?),
+        Err(e) if is_not_found(&e) => None,
+        Err(e) => return Err(e),
+    };
+    Ok(ReadDir {
+        client,
+        net_id: net_id.clone(),
+        find_handle: pending.as_ref().map(|e| e.find_handle).unwrap_or_default(),
+        done: pending.is_none(),
+        pending,
+    })
+}
+
+fn is_not_found(error: &io::Error) -> bool {
+    error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<AdsError>())
+        .map(|e| *e == AdsError::AdsErrDeviceNotFound)
+        .unwrap_or(false)
+}
+
+///Iterator over the entries of [read_dir]. The find handle is closed at the end or when dropped.
+pub struct ReadDir<'a, C: AdsClient> {
+    client: &'a mut C,
+    net_id: AmsNetId,
+    find_handle: u32,
+    pending: Option<FileFindEntry>,
+    done: bool,
+}
+
+impl<C: AdsClient> ReadDir<'_, C> {
+    fn find_next(&mut self) -> io::Result<Option<FileFindEntry>> {
+        let request = FileRequest::FindNext {
+            find_handle: self.find_handle,
+        };
+        let result = file_request(self.client, &self.net_id, request)
+            .and_then(|data| FileFindEntry::read_from(&mut data.as_slice()));
+        match result {
+            Ok(entry) => Ok(Some(entry)),
+            Err(e) => {
+                self.finish();
+                if is_not_found(&e) {
+                    Ok(None)
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if !self.done {
+            self.done = true;
+            let request = FileRequest::Close {
+                handle: self.find_handle,
+            };
+            let _ = file_request(self.client, &self.net_id, request);
+        }
+    }
+}
+
+impl<C: AdsClient> Iterator for ReadDir<'_, C> {
+    type Item = io::Result<FileFindEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let entry = match self.pending.take() {
+                Some(entry) => entry,
+                None if self.done => return None,
+                None => match self.find_next() {
+                    Ok(Some(entry)) => entry,
+                    Ok(None) => return None,
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            if entry.file_name != "." && entry.file_name != ".." {
+                return Some(Ok(entry));
+            }
+        }
+    }
+}
+
+impl<C: AdsClient> Drop for ReadDir<'_, C> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ads_services::system_services::*;
+    use crate::proto::file_access::{path_from_data, FILE_ATTRIBUTE_DIRECTORY};
+    use crate::proto::proto_traits::WriteTo;
+    use crate::proto::request::{ReadWriteRequest, Request};
+    use crate::proto::response::Response;
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    ///In-memory system service
+    #[derive(Default)]
+    struct FileSystem {
+        files: BTreeMap<String, Vec<u8>>,
+        dirs: BTreeSet<String>,
+        open: HashMap<u32, (String, usize)>,
+        finds: HashMap<u32, Vec<FileFindEntry>>,
+        next_handle: u32,
+        requests: usize,
+    }
+
+    impl FileSystem {
+        fn handle(&mut self) -> u32 {
+            self.next_handle += 1;
+            self.next_handle
+        }
+
+        fn read_write(&mut self, r: &ReadWriteRequest) -> Result<Vec<u8>, AdsError> {
+            let not_found = AdsError::AdsErrDeviceNotFound;
+            let ig = r.index_group;
+            if ig == SYSTEMSERVICE_FOPEN.index_group {
+                let path = path_from_data(&r.data);
+                let mode = OpenMode(r.index_offset & 0xFFFF);
+                assert_eq!(r.index_offset >> 16, BasePath::Generic.as_u32());
+                let position = if mode.contains(OpenMode::WRITE) {
+                    self.files.insert(path.clone(), Vec::new());
+                    0
+                } else if mode.contains(OpenMode::APPEND) {
+                    self.files.entry(path.clone()).or_default().len()
+                } else {
+                    self.files.get(&path).ok_or(not_found)?;
+                    0
+                };
+                let handle = self.handle();
+                self.open.insert(handle, (path, position));
+                Ok(handle.to_le_bytes().to_vec())
+            } else if ig == SYSTEMSERVICE_FREAD.index_group {
+                let (path, position) = self.open.get_mut(&r.index_offset).ok_or(not_found)?;
+                let file = &self.files[path.as_str()];
+                let end = file.len().min(*position + r.read_length as usize);
+                let data = file[*position..end].to_vec();
+                *position = end;
+                Ok(data)
+            } else if ig == SYSTEMSERVICE_FWRITE.index_group {
+                let (path, position) = self.open.get_mut(&r.index_offset).ok_or(not_found)?;
+                let file = self.files.get_mut(path.as_str()).unwrap();
+                file.truncate(*position);
+                file.extend_from_slice(&r.data);
+                *position = file.len();
+                Ok(Vec::new())
+            } else if ig == SYSTEMSERVICE_FCLOSE.index_group {
+                let closed = self.open.remove(&r.index_offset).is_some()
+                    || self.finds.remove(&r.index_offset).is_some();
+                closed.then_some(Vec::new()).ok_or(not_found)
+            } else if ig == SYSTEMSERVICE_FDELETE.index_group {
+                let path = path_from_data(&r.data);
+                self.files
+                    .remove(&path)
+                    .map(|_| Vec::new())
+                    .ok_or(not_found)
+            } else if ig == SYSTEMSERVICE_MKDIR.index_group {
+                self.dirs.insert(path_from_data(&r.data));
+                Ok(Vec::new())
+            } else if ig == SYSTEMSERVICE_RMDIR.index_group {
+                let path = path_from_data(&r.data);
+                self.dirs
+                    .remove(&path)
+                    .then_some(Vec::new())
+                    .ok_or(not_found)
+            } else if ig == SYSTEMSERVICE_FFILEFIND.index_group {
+                let handle = if r.data.is_empty() {
+                    r.index_offset
+                } else {
+                    let pattern = path_from_data(&r.data);
+                    let prefix = pattern.trim_end_matches('*');
+                    let handle = self.handle();
+                    let mut entries = vec![
+                        FileFindEntry::new(handle, ".", FILE_ATTRIBUTE_DIRECTORY, 0),
+                        FileFindEntry::new(handle, "..", FILE_ATTRIBUTE_DIRECTORY, 0),
+                    ];
+                    for dir in self.dirs.iter().filter(|d| d.starts_with(prefix)) {
+                        let name = &dir[prefix.len()..];
+                        entries.push(FileFindEntry::new(
+                            handle,
+                            name,
+                            FILE_ATTRIBUTE_DIRECTORY,
+                            0,
+                        ));
+                    }
+                    for (path, data) in self.files.iter().filter(|(p, _)| p.starts_with(prefix)) {
+                        let name = &path[prefix.len()..];
+                        entries.push(FileFindEntry::new(handle, name, 0, data.len() as u64));
+                    }
+                    entries.reverse();
+                    self.finds.insert(handle, entries);
+                    handle
+                };
+                let entry = self
+                    .finds
+                    .get_mut(&handle)
+                    .ok_or(AdsError::AdsErrDeviceInvalidParm)?
+                    .pop()
+                    .ok_or(not_found)?;
+                let mut data: Vec<u8> = Vec::new();
+                entry.write_to(&mut data).unwrap();
+                Ok(data)
+            } else {
+                Err(AdsError::AdsErrDeviceSrvNotSupp)
+            }
+        }
+    }
+
+    impl AdsClient for FileSystem {
+        fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+            assert_eq!(target.port, SYSTEM_SERVICE_PORT);
+            self.requests += 1;
+            let response = match request {
+                Request::ReadWrite(r) => match self.read_write(&r) {
+                    Ok(data) => ReadWriteResponse::new(AdsError::ErrNoError, data),
+                    Err(e) => ReadWriteResponse::new(e, Vec::new()),
+                },
+                _ => ReadWriteResponse::new(AdsError::AdsErrDeviceSrvNotSupp, Vec::new()),
+            };
+            Ok(Response::ReadWrite(response))
+        }
+    }
+
+    fn net_id() -> AmsNetId {
+        AmsNetId::new(192, 168, 1, 2, 1, 1)
+    }
+
+    #[test]
+    fn ads_file_read_write_test() {
+        let mut fs = FileSystem::default();
+        let content: Vec<u8> = (0..40000u32).map(|i| i as u8).collect();
+        let path = "C:\\Boot\\data.bin";
+
+        let mut file = AdsFile::open(
+            &mut fs,
+            &net_id(),
+            path,
+            OpenMode::WRITE | OpenMode::BINARY,
+            BasePath::Generic,
+        )
+        .unwrap();
+        file.write_all(&content).unwrap();
+        file.close().unwrap();
+        //open, 3 chunks, close
+        assert_eq!(fs.requests, 5);
+        assert_eq!(fs.files[path], content);
+
+        let mut file =
+            AdsFile::open(&mut fs, &net_id(), path, OpenMode::READ, BasePath::Generic).unwrap();
+        let mut read = Vec::new();
+        file.read_to_end(&mut read).unwrap();
+        assert_eq!(read, content);
+        drop(file);
+        assert!(fs.open.is_empty(), "closed when dropped");
+
+        let mut file = AdsFile::open(
+            &mut fs,
+            &net_id(),
+            path,
+            OpenMode::APPEND,
+            BasePath::Generic,
+        )
+        .unwrap();
+        file.write_all(b"end").unwrap();
+        drop(file);
+        assert_eq!(fs.files[path].len(), 40003);
+
+        delete_file(&mut fs, &net_id(), path, BasePath::Generic).unwrap();
+        let error = AdsFile::open(&mut fs, &net_id(), path, OpenMode::READ, BasePath::Generic)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            Box::new(AdsError::AdsErrDeviceNotFound)
+        );
+    }
+
+    #[test]
+    fn read_dir_test() {
+        let mut fs = FileSystem::default();
+        fs.files.insert("C:\\Boot\\a.xml".to_string(), vec![0; 10]);
+        fs.files.insert("C:\\Boot\\b.xml".to_string(), vec![0; 20]);
+        create_dir(&mut fs, &net_id(), "C:\\Boot\\Plc", BasePath::Generic).unwrap();
+
+        let entries: Vec<FileFindEntry> =
+            read_dir(&mut fs, &net_id(), "C:\\Boot\\*", BasePath::Generic)
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+        let names: Vec<(&str, bool, u64)> = entries
+            .iter()
+            .map(|e| (e.file_name.as_str(), e.is_dir(), e.file_size))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("Plc", true, 0), ("a.xml", false, 10), ("b.xml", false, 20)]
+        );
+        assert!(fs.finds.is_empty(), "find handle closed at the end");
+
+        //Dropped before the end
+        let mut dir = read_dir(&mut fs, &net_id(), "C:\\Boot\\*", BasePath::Generic).unwrap();
+        assert!(dir.next().unwrap().is_ok());
+        drop(dir);
+        assert!(fs.finds.is_empty(), "find handle closed when dropped");
+
+        remove_dir(&mut fs, &net_id(), "C:\\Boot\\Plc", BasePath::Generic).unwrap();
+        assert!(remove_dir(&mut fs, &net_id(), "C:\\Boot\\Plc", BasePath::Generic).is_err());
+        assert_eq!(
+            read_dir(&mut fs, &net_id(), "D:\\*", BasePath::Generic)
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
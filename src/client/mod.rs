@@ -0,0 +1,101 @@
+pub mod coe;
+pub mod discovery;
+pub mod ethercat;
+pub mod file_access;
+#[cfg(feature = "mqtt")]
+pub mod mqtt_client;
+pub mod notification_registry;
+pub mod poll_scheduler;
+pub mod port_scan;
+pub mod remote_route;
+pub mod runtime_control;
+#[cfg(feature = "tls")]
+pub mod tls_client;
+pub mod udp_client;
+
+use crate::error::AdsError;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::request::Request;
+use crate::proto::response::*;
+use std::io;
+
+///Send a request to an ADS device and wait for the matching response.
+///The helpers in this module only depend on this trait and not on a specific transport.
+pub trait AdsClient {
+    fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response>;
+}
+
+///Responses with an ADS result which are expected by the helpers in this module
+pub(crate) trait AdsResponse: Sized {
+    fn result(&self) -> &AdsError;
+    ///The response if it is of this type, otherwise the response is returned as error
+    fn from_response(response: Response) -> Result<Self, Response>;
+}
+
+macro_rules! ads_responses {
+    ($($variant:ident => $response:ty),*) => {
+        $(impl AdsResponse for $response {
+            fn result(&self) -> &AdsError {
+                &self.result
+            }
+
+            fn from_response(response: Response) -> Result<Self, Response> {
+                match response {
+                    Response::$variant(r) => Ok(r),
+                    r => Err(r),
+                }
+            }
+        })*
+    };
+}
+
+ads_responses! {
+    ReadDeviceInfo => ReadDeviceInfoResponse,
+    ReadState => ReadStateResponse,
+    Read => ReadResponse,
+    Write => WriteResponse,
+    ReadWrite => ReadWriteResponse,
+    WriteControl => WriteControlResponse
+}
+
+///Response of the expected type without error.
+///An ADS error of the response is returned as io error with the [AdsError],
+///another response as InvalidData.
+pub(crate) fn expect_response<T: AdsResponse>(response: Response) -> io::Result<T> {
+    match T::from_response(response) {
+        Ok(r) if *r.result() == AdsError::ErrNoError => Ok(r),
+        Ok(r) => Err(io::Error::other(r.result().clone())),
+        Err(r) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unexpected response {:?}", r),
+        )),
+    }
+}
+
+///ADS error of an io error returned by a client or [expect_response]
+pub(crate) fn ads_error(error: &io::Error) -> Option<&AdsError> {
+    error.get_ref().and_then(|e| e.downcast_ref::<AdsError>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expect_response_test() {
+        let response = Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![1]));
+        assert_eq!(
+            expect_response::<ReadResponse>(response).unwrap().data,
+            vec![1]
+        );
+
+        let response = Response::Write(WriteResponse::new(AdsError::AdsErrDeviceNotFound));
+        let error = expect_response::<WriteResponse>(response).unwrap_err();
+        assert_eq!(ads_error(&error), Some(&AdsError::AdsErrDeviceNotFound));
+
+        let response = Response::Write(WriteResponse::new(AdsError::ErrNoError));
+        let error = expect_response::<ReadResponse>(response).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(ads_error(&error), None);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::client::AdsClient;
+use crate::mqtt::ads_mqtt::{header_from_payload, AdsMqttTopics, MqttRouterInfo};
+use crate::mqtt::connection::MqttConnection;
+use crate::mqtt::packet::MqttConnect;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::request::Request;
+use crate::proto::response::Response;
+use crate::proto::state_flags::StateFlags;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::time::{Duration, Instant};
+
+///Time to wait for the response of a request
+pub const MQTT_DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+///ADS client over an MQTT broker (ADS over MQTT, see [AdsMqttTopics]).
+///
+///Requests are published to the ams topic of the target AmsNetId, the responses are received on
+///the response topic of the AmsNetId of `source`. The client is announced as online on its info topic,
+///the will of the connection sets it offline if the connection is lost.
+///Device notifications are discarded.
+/// ```no_run
+/// use ads_proto::client::mqtt_client::MqttClient;
+/// use ads_proto::client::AdsClient;
+/// use ads_proto::mqtt::ads_mqtt::AdsMqttTopics;
+/// use ads_proto::mqtt::packet::MqttConnect;
+/// use ads_proto::proto::ams_address::{AmsAddress, AmsNetId};
+/// use ads_proto::proto::request::{ReadDeviceInfoRequest, Request};
+///
+/// let source = AmsAddress::new(AmsNetId::new(192, 168, 1, 10, 1, 1), 30000);
+/// let connect = MqttConnect::new("ads-client", 30).with_credentials("user", "password");
+/// let mut client =
+///     MqttClient::connect("broker:1883", connect, AdsMqttTopics::default(), source).unwrap();
+/// let target = AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 1, 1), 851);
+/// let response = client.request(&target, Request::ReadDeviceInfo(ReadDeviceInfoRequest::new()));
+/// ```
+pub struct MqttClient {
+    connection: MqttConnection,
+    topics: AdsMqttTopics,
+    source: AmsAddress,
+    invoke_id: u32,
+    timeout: Duration,
+}
+
+impl MqttClient {
+    ///Connect to the broker, subscribe the ams topics of the AmsNetId of `source` and publish the info.
+    ///The will of `connect` is replaced by the offline info.
+    pub fn connect<A: ToSocketAddrs>(
+        broker: A,
+        mut connect: MqttConnect,
+        topics: AdsMqttTopics,
+        source: AmsAddress,
+    ) -> io::Result<Self> {
+        let name = connect.client_id.clone();
+        let info = MqttRouterInfo::new(source.ams_net_id.clone(), &name, true);
+        connect.will = Some(
+            MqttRouterInfo {
+                online: false,
+                ..info.clone()
+            }
+            .publish(&topics),
+        );
+        let mut connection = MqttConnection::connect(broker, connect)?;
+        connection.subscribe(&topics.ams_filter(&source.ams_net_id))?;
+        connection.publish(info.publish(&topics))?;
+        Ok(MqttClient {
+            connection,
+            topics,
+            source,
+            invoke_id: 0,
+            timeout: MQTT_DEFAULT_TIMEOUT,
+        })
+    }
+
+    pub fn source(&self) -> &AmsAddress {
+        &self.source
+    }
+
+    pub fn topics(&self) -> &AdsMqttTopics {
+        &self.topics
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+}
+
+impl AdsClient for MqttClient {
+    fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+        self.invoke_id = self.invoke_id.wrapping_add(1);
+        let header = AmsHeader::new(
+            target.clone(),
+            self.source.clone(),
+            StateFlags::req_default(),
+            self.invoke_id,
+            request,
+        );
+        self.connection.publish(self.topics.publish(header)?)?;
+
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no response from {} over MQTT", target),
+                ));
+            }
+            let publish = match self.connection.receive(remaining)? {
+                Some(publish) => publish,
+                None => continue,
+            };
+            let mut header = match header_from_payload(&publish.payload) {
+                Ok(header) => header,
+                Err(_) => continue,
+            };
+            if header.state_flags().is_response()
+                && header.invoke_id() == self.invoke_id
+                && header.source_address() == target
+                && header.target_address() == &self.source
+            {
+                return header.response();
+            }
+        }
+    }
+}
+
+impl Drop for MqttClient {
+    fn drop(&mut self) {
+        let info = MqttRouterInfo::new(self.source.ams_net_id.clone(), "", false);
+        let _ = self.connection.publish(info.publish(&self.topics));
+    }
+}
+
+///Routers of the virtual AMS network which are online: the retained info messages received within the timeout
+pub fn discover<A: ToSocketAddrs>(
+    broker: A,
+    connect: MqttConnect,
+    topics: &AdsMqttTopics,
+    timeout: Duration,
+) -> io::Result<Vec<MqttRouterInfo>> {
+    let mut connection = MqttConnection::connect(broker, connect)?;
+    connection.subscribe(&topics.info_filter())?;
+    let mut routers: Vec<MqttRouterInfo> = Vec::new();
+    let deadline = Instant::now() + timeout;
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        if let Some(publish) = connection.receive(remaining)? {
+            if let Ok(info) = MqttRouterInfo::from_publish(topics, &publish) {
+                routers.retain(|r| r.net_id != info.net_id);
+                if info.online {
+                    routers.push(info);
+                }
+            }
+        }
+    }
+    Ok(routers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::broker::MqttBroker;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::proto::request::ReadRequest;
+
+    #[test]
+    fn mqtt_client_timeout_and_discover_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let topics = AdsMqttTopics::new("test");
+        let source = AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 30000);
+        let mut client = MqttClient::connect(
+            broker.local_addr(),
+            MqttConnect::new("client", 30),
+            topics.clone(),
+            source.clone(),
+        )
+        .unwrap();
+        client.set_timeout(Duration::from_millis(50));
+        let error = client
+            .request(
+                &AmsAddress::new(AmsNetId::new(10, 0, 0, 2, 1, 1), 851),
+                Request::Read(ReadRequest::new(0x4020, 0, 1)),
+            )
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        let routers = discover(
+            broker.local_addr(),
+            MqttConnect::new("discover", 30),
+            &topics,
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert_eq!(
+            routers,
+            vec![MqttRouterInfo::new(
+                source.ams_net_id.clone(),
+                "client",
+                true
+            )]
+        );
+
+        //Offline after the client is gone
+        drop(client);
+        let routers = discover(
+            broker.local_addr(),
+            MqttConnect::new("discover", 30),
+            &topics,
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        assert!(routers.is_empty());
+        broker.shutdown();
+    }
+}
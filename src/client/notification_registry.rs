@@ -0,0 +1,435 @@
+use crate::client::AdsClient;
+use crate::error::AdsError;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::proto_traits::ReadFrom;
+use crate::proto::request::{
+    AddDeviceNotificationRequest, DeleteDeviceNotificationRequest, Request,
+};
+use crate::proto::response::{AddDeviceNotificationResponse, AdsNotificationStream};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+///A decoded notification sample delivered to a subscription
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationSample<T> {
+    pub notification_handle: u32,
+    ///Time stamp of the AdsStampHeader the sample was part of
+    pub time_stamp: SystemTime,
+    pub value: T,
+}
+
+type Sink = Box<dyn FnMut(u32, SystemTime, &[u8]) -> bool + Send>;
+type Subscriptions = Arc<Mutex<HashMap<(AmsAddress, u32), Sink>>>;
+
+///Routes the samples of received notification streams to the subscriptions by notification handle.
+///Notification handles are only unique per device, the subscriptions are therefore keyed by the device address and the handle.
+///
+///The callbacks are called while the registry is locked. Don't drop subscriptions of the same registry within a callback.
+#[derive(Clone, Default)]
+pub struct NotificationRegistry {
+    subscriptions: Subscriptions,
+}
+
+///Handle to a registered subscription.
+///Dropping it removes the subscription from the registry.
+///If it was created with [NotificationRegistry::subscribe] a DeleteDeviceNotificationRequest is sent as well.
+pub struct Subscription {
+    target: AmsAddress,
+    request: AddDeviceNotificationRequest,
+    notification_handle: u32,
+    release: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl Subscription {
+    pub fn target(&self) -> &AmsAddress {
+        &self.target
+    }
+
+    pub fn request(&self) -> &AddDeviceNotificationRequest {
+        &self.request
+    }
+
+    pub fn notification_handle(&self) -> u32 {
+        self.notification_handle
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+impl NotificationRegistry {
+    pub fn new() -> Self {
+        NotificationRegistry::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscriptions
+            .lock()
+            .expect("registry lock poisoned")
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Pair a request with the handle of its response and register the callback for this handle.
+    ///Use this if the AddDeviceNotificationRequest was sent by the caller.
+    ///Dropping the subscription only removes it from the registry.
+    ///A handle of the device which is already registered is rejected with [io::ErrorKind::AlreadyExists].
+    pub fn register<T, F>(
+        &self,
+        target: &AmsAddress,
+        request: AddDeviceNotificationRequest,
+        response: &AddDeviceNotificationResponse,
+        callback: F,
+    ) -> io::Result<Subscription>
+    where
+        T: ReadFrom,
+        F: FnMut(NotificationSample<T>) + Send + 'static,
+    {
+        if response.result != AdsError::ErrNoError {
+            return Err(io::Error::other(response.result.clone()));
+        }
+        let key = (target.clone(), response.notification_handle);
+        {
+            let mut subscriptions = self.subscriptions.lock().expect("registry lock poisoned");
+            if subscriptions.contains_key(&key) {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "notification handle {} of {} already registered",
+                        response.notification_handle, target
+                    ),
+                ));
+            }
+            subscriptions.insert(key.clone(), NotificationRegistry::sink(callback));
+        }
+
+        let subscriptions = self.subscriptions.clone();
+        Ok(Subscription {
+            target: target.clone(),
+            request,
+            notification_handle: response.notification_handle,
+            release: Some(Box::new(move || {
+                subscriptions
+                    .lock()
+                    .expect("registry lock poisoned")
+                    .remove(&key);
+            })),
+        })
+    }
+
+    ///Send the AddDeviceNotificationRequest and register the callback for the returned handle.
+    ///A DeleteDeviceNotificationRequest is sent when the subscription is dropped
+    ///or if the returned handle cannot be registered.
+    pub fn subscribe<C, T, F>(
+        &self,
+        client: &Arc<Mutex<C>>,
+        target: &AmsAddress,
+        request: AddDeviceNotificationRequest,
+        callback: F,
+    ) -> io::Result<Subscription>
+    where
+        C: AdsClient + Send + 'static,
+        T: ReadFrom,
+        F: FnMut(NotificationSample<T>) + Send + 'static,
+    {
+        let response: AddDeviceNotificationResponse = client
+            .lock()
+            .expect("client lock poisoned")
+            .request(target, Request::AddDeviceNotification(request.clone()))?
+            .try_into()
+            .map_err(io::Error::other)?;
+        let mut subscription = match self.register(target, request, &response, callback) {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                //The device accepted the notification, nobody would delete it
+                if response.result == AdsError::ErrNoError {
+                    let _ = client.lock().expect("client lock poisoned").request(
+                        target,
+                        Request::DeleteDeviceNotification(DeleteDeviceNotificationRequest::new(
+                            response.notification_handle,
+                        )),
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        let unregister = subscription.release.take();
+        let client = client.clone();
+        let target = target.clone();
+        let handle = response.notification_handle;
+        subscription.release = Some(Box::new(move || {
+            if let Some(unregister) = unregister {
+                unregister();
+            }
+            //Nobody could handle an error while dropping. The device releases the handle on disconnect anyway.
+            if let Ok(mut client) = client.lock() {
+                let _ = client.request(
+                    &target,
+                    Request::DeleteDeviceNotification(DeleteDeviceNotificationRequest::new(handle)),
+                );
+            }
+        }));
+        Ok(subscription)
+    }
+
+    ///Same as [NotificationRegistry::subscribe] but the samples are sent to the returned receiver.
+    pub fn subscribe_channel<C, T>(
+        &self,
+        client: &Arc<Mutex<C>>,
+        target: &AmsAddress,
+        request: AddDeviceNotificationRequest,
+    ) -> io::Result<(Subscription, Receiver<NotificationSample<T>>)>
+    where
+        C: AdsClient + Send + 'static,
+        T: ReadFrom + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        let subscription = self.subscribe(client, target, request, move |sample| {
+            let _ = tx.send(sample);
+        })?;
+        Ok((subscription, rx))
+    }
+
+    fn sink<T, F>(mut callback: F) -> Sink
+    where
+        T: ReadFrom,
+        F: FnMut(NotificationSample<T>) + Send + 'static,
+    {
+        Box::new(
+            move |notification_handle, time_stamp, mut data| match T::read_from(&mut data) {
+                Ok(value) => {
+                    callback(NotificationSample {
+                        notification_handle,
+                        time_stamp,
+                        value,
+                    });
+                    true
+                }
+                Err(_) => false,
+            },
+        )
+    }
+
+    ///Deliver all samples of a notification stream received from `source` to the subscriptions.
+    ///Samples with an unknown handle or data that can't be decoded are skipped.
+    ///Returns the number of delivered samples.
+    pub fn dispatch(&self, source: &AmsAddress, stream: &AdsNotificationStream) -> usize {
+        let mut subscriptions = self.subscriptions.lock().expect("registry lock poisoned");
+        let mut delivered = 0;
+        for (time_stamp, sample) in stream.timestamped_samples() {
+            let key = (source.clone(), sample.notification_handle);
+            if let Some(sink) = subscriptions.get_mut(&key) {
+                if sink(sample.notification_handle, time_stamp, &sample.data) {
+                    delivered += 1;
+                }
+            }
+        }
+        delivered
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ads_transition_mode::AdsTransMode;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::proto::file_time::FileTime;
+    use crate::proto::response::{
+        AdsNotificationSample, AdsStampHeader, DeleteDeviceNotificationResponse, Response,
+    };
+    use std::time::UNIX_EPOCH;
+
+    #[derive(Default)]
+    struct TestClient {
+        next_handle: u32,
+        deleted: Vec<u32>,
+    }
+
+    impl AdsClient for TestClient {
+        fn request(&mut self, _target: &AmsAddress, request: Request) -> io::Result<Response> {
+            match request {
+                Request::AddDeviceNotification(_) => {
+                    self.next_handle += 1;
+                    Ok(Response::AddDeviceNotification(
+                        AddDeviceNotificationResponse::new(AdsError::ErrNoError, self.next_handle),
+                    ))
+                }
+                Request::DeleteDeviceNotification(r) => {
+                    self.deleted.push(r.handle);
+                    Ok(Response::DeleteDeviceNotification(
+                        DeleteDeviceNotificationResponse::new(AdsError::ErrNoError),
+                    ))
+                }
+                _ => Err(io::Error::other(AdsError::AdsErrDeviceSrvNotSupp)),
+            }
+        }
+    }
+
+    fn target() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 1, 1), 851)
+    }
+
+    fn request(length: u32) -> AddDeviceNotificationRequest {
+        AddDeviceNotificationRequest::new(0x4020, 0, length, AdsTransMode::OnChange, 0, 0)
+    }
+
+    fn stream(samples: Vec<AdsNotificationSample>) -> AdsNotificationStream {
+        let stamp =
+            AdsStampHeader::with_time(FileTime::new(FileTime::UNIX_EPOCH.as_u64() + 10), samples);
+        let length = stamp.stamp_len() as u32 + 4;
+        AdsNotificationStream::new(length, 1, vec![stamp])
+    }
+
+    #[test]
+    fn notification_registry_dispatch_test() {
+        let registry = NotificationRegistry::new();
+        let client = Arc::new(Mutex::new(TestClient::default()));
+
+        let (sub_u16, rx_u16) = registry
+            .subscribe_channel::<_, u16>(&client, &target(), request(2))
+            .unwrap();
+        let (sub_f32, rx_f32) = registry
+            .subscribe_channel::<_, f32>(&client, &target(), request(4))
+            .unwrap();
+        assert_eq!(sub_u16.notification_handle(), 1);
+        assert_eq!(sub_f32.notification_handle(), 2);
+        assert_eq!(registry.len(), 2);
+
+        let delivered = registry.dispatch(
+            &target(),
+            &stream(vec![
+                AdsNotificationSample::new(1, vec![7, 0]),
+                AdsNotificationSample::new(2, 1.5f32.to_le_bytes().to_vec()),
+                AdsNotificationSample::new(3, vec![1]), //unknown handle
+            ]),
+        );
+        assert_eq!(delivered, 2);
+
+        let sample = rx_u16.try_recv().unwrap();
+        assert_eq!(sample.value, 7);
+        assert_eq!(
+            sample.time_stamp,
+            UNIX_EPOCH + std::time::Duration::from_nanos(1000)
+        );
+        assert_eq!(rx_f32.try_recv().unwrap().value, 1.5);
+
+        //Same handle from another device is not routed
+        let other = AmsAddress::new(AmsNetId::new(192, 168, 1, 3, 1, 1), 851);
+        let delivered = registry.dispatch(
+            &other,
+            &stream(vec![AdsNotificationSample::new(1, vec![7, 0])]),
+        );
+        assert_eq!(delivered, 0);
+    }
+
+    #[test]
+    fn notification_registry_drop_test() {
+        let registry = NotificationRegistry::new();
+        let client = Arc::new(Mutex::new(TestClient::default()));
+
+        let subscription = registry
+            .subscribe(
+                &client,
+                &target(),
+                request(2),
+                |_: NotificationSample<u16>| {},
+            )
+            .unwrap();
+        assert_eq!(registry.len(), 1);
+        drop(subscription);
+        assert!(registry.is_empty());
+        assert_eq!(client.lock().unwrap().deleted, vec![1]);
+    }
+
+    #[test]
+    fn notification_registry_subscribe_rejected_test() {
+        let registry = NotificationRegistry::new();
+        let client = Arc::new(Mutex::new(TestClient::default()));
+        let response = AddDeviceNotificationResponse::new(AdsError::ErrNoError, 1);
+        let _registered = registry
+            .register(
+                &target(),
+                request(2),
+                &response,
+                |_: NotificationSample<u16>| {},
+            )
+            .unwrap();
+
+        //The device returns the handle 1 which is already registered
+        let error = registry
+            .subscribe(
+                &client,
+                &target(),
+                request(2),
+                |_: NotificationSample<u16>| {},
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(client.lock().unwrap().deleted, vec![1]);
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn notification_registry_register_test() {
+        let registry = NotificationRegistry::new();
+        let failed = AddDeviceNotificationResponse::new(AdsError::AdsErrDeviceNoMoreHDLS, 0);
+        assert!(registry
+            .register(
+                &target(),
+                request(2),
+                &failed,
+                |_: NotificationSample<u16>| {}
+            )
+            .is_err());
+
+        let response = AddDeviceNotificationResponse::new(AdsError::ErrNoError, 5);
+        let subscription = registry
+            .register(
+                &target(),
+                request(2),
+                &response,
+                |_: NotificationSample<u16>| {},
+            )
+            .unwrap();
+        assert_eq!(subscription.notification_handle(), 5);
+        assert_eq!(subscription.request().length, 2);
+
+        //Same handle again does not replace the subscription
+        let error = registry
+            .register(
+                &target(),
+                request(2),
+                &response,
+                |_: NotificationSample<u16>| {},
+            )
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(registry.len(), 1);
+
+        drop(subscription);
+        assert!(registry.is_empty());
+        assert!(registry
+            .register(
+                &target(),
+                request(2),
+                &response,
+                |_: NotificationSample<u16>| {}
+            )
+            .is_ok());
+    }
+}
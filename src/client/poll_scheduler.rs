@@ -0,0 +1,430 @@
+use crate::client::AdsClient;
+use crate::error::AdsError;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::proto_traits::ReadFrom;
+use crate::proto::request::{ReadRequest, Request};
+use crate::proto::response::ReadWriteResponse;
+use crate::proto::sumup::sumup_request::SumupReadRequest;
+use crate::proto::sumup::sumup_response::SumupReadResponse;
+use std::convert::TryInto;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+///Max number of sub commands in one sumup request
+pub const MAX_SUMUP_REQUESTS: usize = 500;
+///Shortest poll period, shorter periods are raised to it
+pub const MIN_POLL_PERIOD: Duration = Duration::from_millis(1);
+
+///Identifies a poll entry added to a [PollScheduler]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PollId(u32);
+
+///Result of a single poll entry delivered by the [PollScheduler]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PollResult {
+    pub id: PollId,
+    pub request: ReadRequest,
+    ///The read data or the ADS error of this entry (not of the whole sumup)
+    pub result: Result<Vec<u8>, AdsError>,
+    ///The time the entry was due
+    pub scheduled: Instant,
+    ///The time the entry was actually polled
+    pub polled: Instant,
+}
+
+///Jitter statistic (polled - scheduled) of a poll entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct JitterStats {
+    pub samples: u64,
+    pub min: Duration,
+    pub max: Duration,
+    pub last: Duration,
+    total: Duration,
+}
+
+impl JitterStats {
+    fn update(&mut self, jitter: Duration) {
+        if self.samples == 0 || jitter < self.min {
+            self.min = jitter;
+        }
+        if jitter > self.max {
+            self.max = jitter;
+        }
+        self.last = jitter;
+        self.total += jitter;
+        self.samples += 1;
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.samples == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total.as_nanos() / self.samples as u128) as u64)
+    }
+}
+
+enum PollSink {
+    Callback(Box<dyn FnMut(PollResult) + Send>),
+    Channel(Sender<PollResult>),
+}
+
+impl PollSink {
+    fn deliver(&mut self, result: PollResult) {
+        match self {
+            PollSink::Callback(f) => f(result),
+            //The receiver may already be dropped. The entry stays scheduled until it is removed.
+            PollSink::Channel(tx) => {
+                let _ = tx.send(result);
+            }
+        }
+    }
+}
+
+struct PollEntry {
+    id: PollId,
+    request: ReadRequest,
+    period: Duration,
+    next_due: Instant,
+    sink: PollSink,
+    jitter: JitterStats,
+}
+
+///Polls read requests with individual periods.
+///All entries due on a tick are bundled into as few sumup read requests as possible (max 500 entries each).
+/// ```
+/// use ads_proto::client::poll_scheduler::PollScheduler;
+/// use ads_proto::proto::ams_address::{AmsAddress, AmsNetId};
+/// use ads_proto::proto::request::ReadRequest;
+/// use std::time::Duration;
+///
+/// let mut scheduler = PollScheduler::new(AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 1, 1), 851));
+/// let (_id, rx) = scheduler.add_channel(ReadRequest::new(0x4020, 0, 4), Duration::from_millis(100));
+/// scheduler.add_callback(ReadRequest::new(0x4020, 4, 2), Duration::from_secs(1), |result| {
+///     println!("{:?}", result.result);
+/// });
+/// assert_eq!(scheduler.len(), 2);
+/// ```
+pub struct PollScheduler {
+    target: AmsAddress,
+    entries: Vec<PollEntry>,
+    next_id: u32,
+}
+
+impl PollScheduler {
+    pub fn new(target: AmsAddress) -> Self {
+        PollScheduler {
+            target,
+            entries: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn target(&self) -> &AmsAddress {
+        &self.target
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    ///Add a poll entry. The callback is called with the result of every poll.
+    ///The entry is due immediately. The period is at least [MIN_POLL_PERIOD].
+    pub fn add_callback(
+        &mut self,
+        request: ReadRequest,
+        period: Duration,
+        callback: impl FnMut(PollResult) + Send + 'static,
+    ) -> PollId {
+        self.add(request, period, PollSink::Callback(Box::new(callback)))
+    }
+
+    ///Add a poll entry. The results are sent to the returned receiver.
+    ///The entry is due immediately. The period is at least [MIN_POLL_PERIOD].
+    pub fn add_channel(
+        &mut self,
+        request: ReadRequest,
+        period: Duration,
+    ) -> (PollId, Receiver<PollResult>) {
+        let (tx, rx) = channel();
+        (self.add(request, period, PollSink::Channel(tx)), rx)
+    }
+
+    fn add(&mut self, request: ReadRequest, period: Duration, sink: PollSink) -> PollId {
+        let id = PollId(self.next_id);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.entries.push(PollEntry {
+            id,
+            request,
+            period: period.max(MIN_POLL_PERIOD),
+            next_due: Instant::now(),
+            sink,
+            jitter: JitterStats::default(),
+        });
+        id
+    }
+
+    ///Remove a poll entry. Returns false if the id is unknown.
+    pub fn remove(&mut self, id: PollId) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| e.id != id);
+        len != self.entries.len()
+    }
+
+    pub fn jitter(&self, id: PollId) -> Option<&JitterStats> {
+        self.entries.iter().find(|e| e.id == id).map(|e| &e.jitter)
+    }
+
+    ///The time the next entry is due
+    pub fn next_due(&self) -> Option<Instant> {
+        self.entries.iter().map(|e| e.next_due).min()
+    }
+
+    ///Get the sumup requests for all entries due at `now` without polling them.
+    pub fn due_requests(&self, now: Instant) -> Vec<SumupReadRequest> {
+        self.due_batches(now)
+            .iter()
+            .map(|batch| self.sumup(batch))
+            .collect()
+    }
+
+    fn due_batches(&self, now: Instant) -> Vec<Vec<usize>> {
+        let due: Vec<usize> = (0..self.entries.len())
+            .filter(|i| self.entries[*i].next_due <= now)
+            .collect();
+        due.chunks(MAX_SUMUP_REQUESTS)
+            .map(|chunk| chunk.to_vec())
+            .collect()
+    }
+
+    fn sumup(&self, batch: &[usize]) -> SumupReadRequest {
+        SumupReadRequest::new(
+            batch
+                .iter()
+                .map(|i| self.entries[*i].request.clone())
+                .collect(),
+        )
+    }
+
+    ///Poll all entries due at `now` and deliver the results.
+    ///Returns the number of sumup requests sent.
+    pub fn tick<C: AdsClient>(&mut self, client: &mut C, now: Instant) -> io::Result<usize> {
+        let batches = self.due_batches(now);
+        for batch in &batches {
+            let request = self.sumup(batch).read_write_request();
+            let response: ReadWriteResponse = client
+                .request(&self.target, Request::ReadWrite(request))?
+                .try_into()
+                .map_err(io::Error::other)?;
+            let polled = Instant::now();
+
+            let mut results: Vec<Result<Vec<u8>, AdsError>> = Vec::with_capacity(batch.len());
+            if response.result != AdsError::ErrNoError {
+                results.resize(batch.len(), Err(response.result));
+            } else {
+                let sumup = SumupReadResponse::read_from(&mut response.data.as_slice())?;
+                for n in 0..batch.len() {
+                    results.push(match sumup.read_responses.get(n) {
+                        Some(r) if r.result == AdsError::ErrNoError => Ok(r.data.clone()),
+                        Some(r) => Err(r.result.clone()),
+                        None => Err(AdsError::AdsErrClientSyncResInvalid),
+                    });
+                }
+            }
+
+            for (i, result) in batch.iter().zip(results) {
+                let entry = &mut self.entries[*i];
+                let scheduled = entry.next_due;
+                entry
+                    .jitter
+                    .update(polled.saturating_duration_since(scheduled));
+                //Skip missed periods instead of polling them in a burst
+                if entry.next_due <= now {
+                    let missed = (now - entry.next_due).as_nanos() / entry.period.as_nanos() + 1;
+                    entry.next_due +=
+                        Duration::from_nanos((missed * entry.period.as_nanos()) as u64);
+                }
+                entry.sink.deliver(PollResult {
+                    id: entry.id,
+                    request: entry.request.clone(),
+                    result,
+                    scheduled,
+                    polled,
+                });
+            }
+        }
+        Ok(batches.len())
+    }
+
+    ///Poll until `stop` is set or the client returns an error.
+    pub fn run<C: AdsClient>(&mut self, client: &mut C, stop: &AtomicBool) -> io::Result<()> {
+        while !stop.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            match self.next_due() {
+                Some(due) if due > now => {
+                    thread::sleep((due - now).min(Duration::from_millis(100)))
+                }
+                Some(_) => {
+                    self.tick(client, now)?;
+                }
+                None => thread::sleep(Duration::from_millis(100)),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::proto::proto_traits::WriteTo;
+    use crate::proto::request::ReadWriteRequest;
+    use crate::proto::response::{ReadResponse, Response};
+    use crate::proto::sumup::sumup_request::SumupReadRequest;
+    use std::sync::{Arc, Mutex};
+
+    //Answers every sumup read with the index offset as u32 value. Index group 0 fails.
+    struct TestClient {
+        sumups: Vec<u32>,
+    }
+
+    impl AdsClient for TestClient {
+        fn request(&mut self, _target: &AmsAddress, request: Request) -> io::Result<Response> {
+            let request: ReadWriteRequest = request.try_into().unwrap();
+            self.sumups.push(request.index_offset);
+            let sumup = SumupReadRequest::read_from(&mut request.data.as_slice())?;
+            let responses = sumup
+                .read_requests()
+                .iter()
+                .map(|r| match r.index_group {
+                    0 => ReadResponse::new(AdsError::AdsErrDeviceInvalidGrp, Vec::new()),
+                    _ => ReadResponse::new(
+                        AdsError::ErrNoError,
+                        r.index_offset.to_le_bytes().to_vec(),
+                    ),
+                })
+                .collect();
+            let mut data = Vec::new();
+            SumupReadResponse::new(responses).write_to(&mut data)?;
+            Ok(Response::ReadWrite(ReadWriteResponse::new(
+                AdsError::ErrNoError,
+                data,
+            )))
+        }
+    }
+
+    fn scheduler() -> PollScheduler {
+        PollScheduler::new(AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 1, 1), 851))
+    }
+
+    #[test]
+    fn poll_scheduler_tick_test() {
+        let mut scheduler = scheduler();
+        let (fast, fast_rx) =
+            scheduler.add_channel(ReadRequest::new(0x4020, 1, 4), Duration::from_millis(100));
+        let (slow, slow_rx) =
+            scheduler.add_channel(ReadRequest::new(0x4020, 2, 4), Duration::from_secs(1));
+        let (_, error_rx) =
+            scheduler.add_channel(ReadRequest::new(0, 3, 4), Duration::from_secs(10));
+        let mut client = TestClient { sumups: Vec::new() };
+
+        let start = Instant::now();
+        assert_eq!(scheduler.tick(&mut client, start).unwrap(), 1);
+        assert_eq!(client.sumups, vec![3]);
+        assert_eq!(fast_rx.try_recv().unwrap().result, Ok(vec![1, 0, 0, 0]));
+        assert_eq!(slow_rx.try_recv().unwrap().result, Ok(vec![2, 0, 0, 0]));
+        assert_eq!(
+            error_rx.try_recv().unwrap().result,
+            Err(AdsError::AdsErrDeviceInvalidGrp)
+        );
+
+        //Only the fast entry is due after 100ms
+        let now = start + Duration::from_millis(150);
+        assert_eq!(scheduler.due_requests(now).len(), 1);
+        assert_eq!(scheduler.due_requests(now)[0].request_count(), 1);
+        scheduler.tick(&mut client, now).unwrap();
+        assert_eq!(client.sumups, vec![3, 1]);
+        assert_eq!(fast_rx.try_recv().unwrap().id, fast);
+        assert!(slow_rx.try_recv().is_err());
+
+        assert_eq!(scheduler.jitter(fast).unwrap().samples, 2);
+        assert_eq!(scheduler.jitter(slow).unwrap().samples, 1);
+        assert!(scheduler.remove(slow));
+        assert!(!scheduler.remove(slow));
+        assert_eq!(scheduler.len(), 2);
+    }
+
+    #[test]
+    fn poll_scheduler_batch_test() {
+        let mut scheduler = scheduler();
+        let results = Arc::new(Mutex::new(0));
+        for n in 0..1001 {
+            let results = results.clone();
+            scheduler.add_callback(
+                ReadRequest::new(0x4020, n, 4),
+                Duration::from_secs(1),
+                move |r| {
+                    assert_eq!(r.result, Ok(n.to_le_bytes().to_vec()));
+                    *results.lock().unwrap() += 1;
+                },
+            );
+        }
+
+        let now = Instant::now();
+        let due = scheduler.due_requests(now);
+        assert_eq!(due.len(), 3);
+        assert_eq!(due[0].request_count(), 500);
+        assert_eq!(due[2].request_count(), 1);
+
+        let mut client = TestClient { sumups: Vec::new() };
+        assert_eq!(scheduler.tick(&mut client, now).unwrap(), 3);
+        assert_eq!(client.sumups, vec![500, 500, 1]);
+        assert_eq!(*results.lock().unwrap(), 1001);
+        assert!(scheduler.next_due().unwrap() > now);
+    }
+
+    #[test]
+    fn jitter_stats_test() {
+        let mut stats = JitterStats::default();
+        assert_eq!(stats.mean(), Duration::ZERO);
+        stats.update(Duration::from_millis(4));
+        stats.update(Duration::from_millis(2));
+        stats.update(Duration::from_millis(6));
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.min, Duration::from_millis(2));
+        assert_eq!(stats.max, Duration::from_millis(6));
+        assert_eq!(stats.last, Duration::from_millis(6));
+        assert_eq!(stats.mean(), Duration::from_millis(4));
+
+        let stats = JitterStats {
+            samples: u32::MAX as u64 + 1,
+            total: Duration::from_secs(u32::MAX as u64 + 1),
+            ..Default::default()
+        };
+        assert_eq!(stats.mean(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn poll_scheduler_zero_period_test() {
+        let mut scheduler = scheduler();
+        let (id, rx) = scheduler.add_channel(ReadRequest::new(0x4020, 0, 4), Duration::ZERO);
+        let mut client = TestClient { sumups: Vec::new() };
+        let start = Instant::now();
+        scheduler.tick(&mut client, start).unwrap();
+        assert_eq!(rx.try_recv().unwrap().id, id);
+        assert!(scheduler.next_due().unwrap() > start);
+
+        //Missed periods are skipped
+        let now = start + Duration::from_secs(10);
+        scheduler.tick(&mut client, now).unwrap();
+        assert!(scheduler.next_due().unwrap() > now);
+        assert!(scheduler.next_due().unwrap() <= now + MIN_POLL_PERIOD);
+    }
+}
@@ -0,0 +1,271 @@
+use crate::ads_services::system_services::SYSTEM_SERVICE_PORT;
+use crate::client::{ads_error, expect_response, AdsClient, AdsResponse};
+use crate::error::AdsError;
+use crate::proto::ads_state::AdsState;
+use crate::proto::ads_version::AdsVersion;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::request::{ReadDeviceInfoRequest, ReadStateRequest, Request};
+use crate::proto::response::{ReadDeviceInfoResponse, ReadStateResponse};
+use std::io;
+
+///AMS port of the NC
+pub const NC_PORT: u16 = 500;
+///AMS ports of the TwinCAT 2 PLC runtimes
+pub const TC2_PLC_PORTS: [u16; 4] = [801, 811, 821, 831];
+///AMS ports of the TwinCAT 3 PLC runtimes
+pub const TC3_PLC_PORTS: [u16; 4] = [851, 852, 853, 854];
+
+///Ports scanned by [scan_ports]: NC, TwinCAT 2 and 3 PLC runtimes and the system service
+pub fn default_scan_ports() -> Vec<u16> {
+    let mut ports = vec![NC_PORT];
+    ports.extend_from_slice(&TC2_PLC_PORTS);
+    ports.extend_from_slice(&TC3_PLC_PORTS);
+    ports.push(SYSTEM_SERVICE_PORT);
+    ports
+}
+
+///What is expected behind a well known AMS port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+    Nc,
+    Tc2Plc,
+    Tc3Plc,
+    SystemService,
+    Other,
+}
+
+impl From<u16> for PortKind {
+    fn from(port: u16) -> Self {
+        match port {
+            NC_PORT => PortKind::Nc,
+            SYSTEM_SERVICE_PORT => PortKind::SystemService,
+            p if TC2_PLC_PORTS.contains(&p) => PortKind::Tc2Plc,
+            p if TC3_PLC_PORTS.contains(&p) => PortKind::Tc3Plc,
+            _ => PortKind::Other,
+        }
+    }
+}
+
+///Device found on an AMS port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInfo {
+    pub port: u16,
+    pub kind: PortKind,
+    ///None if the device does not support read device info
+    pub device_name: Option<String>,
+    ///None if the device does not support read device info
+    pub version: Option<AdsVersion>,
+    ///None if the device does not support read state
+    pub ads_state: Option<AdsState>,
+    pub device_state: u16,
+}
+
+///Devices found on a target, ordered like the scanned ports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortInventory {
+    pub net_id: AmsNetId,
+    pub ports: Vec<PortInfo>,
+}
+
+impl PortInventory {
+    pub fn port(&self, port: u16) -> Option<&PortInfo> {
+        self.ports.iter().find(|p| p.port == port)
+    }
+
+    ///TwinCAT 2 and 3 PLC runtimes found
+    pub fn plc_runtimes(&self) -> impl Iterator<Item = &PortInfo> {
+        self.ports
+            .iter()
+            .filter(|p| matches!(p.kind, PortKind::Tc2Plc | PortKind::Tc3Plc))
+    }
+
+    ///2 or 3 depending on the PLC runtimes found, None if there is no PLC runtime
+    pub fn twincat_version(&self) -> Option<u8> {
+        self.plc_runtimes().next().map(|p| match p.kind {
+            PortKind::Tc2Plc => 2,
+            _ => 3,
+        })
+    }
+}
+
+///Scan the [default_scan_ports] of the target with ReadDeviceInfo and ReadState.
+///A port is in the inventory if one of the requests is answered without an ADS error.
+///Transport errors (e.g. no connection to the target) abort the scan.
+pub fn scan_ports<C: AdsClient>(client: &mut C, net_id: &AmsNetId) -> io::Result<PortInventory> {
+    scan(client, net_id, &default_scan_ports())
+}
+
+///Scan the given ports of the target, see [scan_ports]
+pub fn scan<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    ports: &[u16],
+) -> io::Result<PortInventory> {
+    let mut inventory = PortInventory {
+        net_id: net_id.clone(),
+        ports: Vec::new(),
+    };
+    for port in ports {
+        if let Some(info) = scan_port(client, net_id, *port)? {
+            inventory.ports.push(info);
+        }
+    }
+    Ok(inventory)
+}
+
+fn scan_port<C: AdsClient>(
+    client: &mut C,
+    net_id: &AmsNetId,
+    port: u16,
+) -> io::Result<Option<PortInfo>> {
+    let target = AmsAddress::new(net_id.clone(), port);
+    let mut info = PortInfo {
+        port,
+        kind: PortKind::from(port),
+        device_name: None,
+        version: None,
+        ads_state: None,
+        device_state: 0,
+    };
+
+    let request = Request::ReadDeviceInfo(ReadDeviceInfoRequest::new());
+    match ads_request::<ReadDeviceInfoResponse, _>(client, &target, request)? {
+        Ok(r) => {
+            info.device_name = r.get_device_name().ok();
+            info.version = Some(r.version());
+        }
+        Err(e) if is_unreachable(&e) => return Ok(None),
+        Err(_) => (),
+    }
+
+    let request = Request::ReadState(ReadStateRequest::new());
+    if let Ok(r) = ads_request::<ReadStateResponse, _>(client, &target, request)? {
+        info.ads_state = Some(r.ads_state);
+        info.device_state = r.device_state;
+    }
+
+    if info.version.is_none() && info.ads_state.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(info))
+}
+
+///Response of the request, the ADS error of the response or of the client as Err
+fn ads_request<T: AdsResponse, C: AdsClient>(
+    client: &mut C,
+    target: &AmsAddress,
+    request: Request,
+) -> io::Result<Result<T, AdsError>> {
+    match client
+        .request(target, request)
+        .and_then(expect_response::<T>)
+    {
+        Ok(response) => Ok(Ok(response)),
+        Err(e) => match ads_error(&e) {
+            Some(ads_error) => Ok(Err(ads_error.clone())),
+            None => Err(e),
+        },
+    }
+}
+
+fn is_unreachable(error: &AdsError) -> bool {
+    matches!(
+        error,
+        AdsError::ErrTargetPortNotFound | AdsError::ErrTargetMachineNotFound
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ams_header::AmsHeader;
+    use crate::proto::response::Response;
+    use crate::proto::state_flags::StateFlags;
+    use crate::server::tcp_server::AdsServer;
+    use crate::server::virtual_plc::VirtualPlc;
+    use crate::server::AdsDevice;
+
+    ///Sends the requests to the server without a transport
+    struct LocalClient(AdsServer);
+
+    impl AdsClient for LocalClient {
+        fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+            let source = AmsAddress::new(AmsNetId::new(192, 168, 1, 3, 1, 1), 30000);
+            let header = AmsHeader::new(
+                target.clone(),
+                source,
+                StateFlags::req_default(),
+                1,
+                request,
+            );
+            self.0
+                .handle(header)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::TimedOut))?
+                .response()
+        }
+    }
+
+    ///Device which only supports read state
+    struct StateOnly;
+
+    impl AdsDevice for StateOnly {
+        fn read_state(&mut self, _source: &AmsAddress) -> ReadStateResponse {
+            ReadStateResponse::new(AdsError::ErrNoError, AdsState::AdsStateConfig, 1)
+        }
+    }
+
+    #[test]
+    fn scan_ports_test() {
+        let net_id = AmsNetId::new(192, 168, 1, 2, 1, 1);
+        let server = AdsServer::new(net_id.clone());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        let plc = server.add_device(852, VirtualPlc::new(4, 0, 0));
+        plc.lock().unwrap().set_state(AdsState::AdsStateStop, 0);
+        server.add_device(SYSTEM_SERVICE_PORT, StateOnly);
+        server.add_device(4000, VirtualPlc::new(4, 0, 0));
+
+        let inventory = scan_ports(&mut LocalClient(server), &net_id).unwrap();
+        assert_eq!(
+            inventory.ports.iter().map(|p| p.port).collect::<Vec<u16>>(),
+            vec![851, 852, SYSTEM_SERVICE_PORT]
+        );
+        assert_eq!(
+            inventory.port(851).unwrap(),
+            &PortInfo {
+                port: 851,
+                kind: PortKind::Tc3Plc,
+                device_name: Some(String::from("Virtual PLC")),
+                version: Some(AdsVersion::new(3, 1, 4024)),
+                ads_state: Some(AdsState::AdsStateRun),
+                device_state: 0,
+            }
+        );
+        assert_eq!(
+            inventory.port(852).unwrap().ads_state,
+            Some(AdsState::AdsStateStop)
+        );
+        let system = inventory.port(SYSTEM_SERVICE_PORT).unwrap();
+        assert_eq!(system.kind, PortKind::SystemService);
+        assert_eq!(system.device_name, None);
+        assert_eq!(system.ads_state, Some(AdsState::AdsStateConfig));
+        assert_eq!(inventory.plc_runtimes().count(), 2);
+        assert_eq!(inventory.twincat_version(), Some(3));
+    }
+
+    #[test]
+    fn scan_tc2_test() {
+        let net_id = AmsNetId::new(192, 168, 1, 2, 1, 1);
+        let server = AdsServer::new(net_id.clone());
+        server.add_device(801, VirtualPlc::new(4, 0, 0));
+        server.add_device(NC_PORT, StateOnly);
+        let mut client = LocalClient(server);
+
+        let inventory = scan(&mut client, &net_id, &[NC_PORT, 801, 811]).unwrap();
+        assert_eq!(inventory.ports.len(), 2);
+        assert_eq!(inventory.ports[0].kind, PortKind::Nc);
+        assert_eq!(inventory.twincat_version(), Some(2));
+
+        let other = AmsNetId::new(192, 168, 1, 9, 1, 1);
+        assert!(scan_ports(&mut client, &other).unwrap().ports.is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::client::discovery::{bind_for, next_invoke_id};
+use crate::error::AdsError;
+use crate::proto::ads_udp::{AddRouteRequest, AddRouteResponse, AdsUdpMessage};
+use crate::proto::ams_address::AmsNetId;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+///Send an add route request to the system service of the remote router (usually ip:48899)
+///and wait for the acknowledgement.
+///Returns the AmsNetId of the remote router. A rejected request (e.g. wrong password) is returned
+///as error with the [AdsError] of the acknowledgement.
+pub fn add_route(
+    target: SocketAddr,
+    request: &AddRouteRequest,
+    timeout: Duration,
+) -> io::Result<AmsNetId> {
+    let socket = bind_for(&target)?;
+    let invoke_id = next_invoke_id();
+    let mut buffer: Vec<u8> = Vec::new();
+    request.message(invoke_id).write_to(&mut buffer)?;
+    socket.send_to(&buffer, target)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("no add route response from {}", target),
+            ));
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        let len = match socket.recv_from(&mut buffer) {
+            Ok((len, addr)) if addr == target => len,
+            Ok(_) => continue,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Err(e),
+        };
+        let response = AdsUdpMessage::read_from(&mut &buffer[..len])
+            .ok()
+            .filter(|m| m.invoke_id == invoke_id)
+            .and_then(|m| AddRouteResponse::from_message(&m).ok());
+        if let Some(response) = response {
+            if response.result != AdsError::ErrNoError {
+                return Err(io::Error::other(response.result));
+            }
+            return Ok(response.net_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+    use std::thread;
+
+    fn remote_router(password: &'static str) -> (SocketAddr, thread::JoinHandle<AddRouteRequest>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let thread = thread::spawn(move || {
+            let mut buffer = [0; 2048];
+            let (len, client) = socket.recv_from(&mut buffer).unwrap();
+            let message = AdsUdpMessage::read_from(&mut &buffer[..len]).unwrap();
+            let request = AddRouteRequest::from_message(&message).unwrap();
+            let result = if request.password == password {
+                AdsError::ErrNoError
+            } else {
+                AdsError::AdsErrDeviceInvalidAccess
+            };
+            let mut response: Vec<u8> = Vec::new();
+            AddRouteResponse::new(AmsNetId::new(5, 1, 2, 3, 1, 1), result)
+                .message(message.invoke_id)
+                .write_to(&mut response)
+                .unwrap();
+            socket.send_to(&response, client).unwrap();
+            request
+        });
+        (addr, thread)
+    }
+
+    fn request() -> AddRouteRequest {
+        AddRouteRequest::new(
+            "linux-host",
+            AmsNetId::new(127, 0, 0, 1, 1, 1),
+            "127.0.0.1",
+            "Administrator",
+            "1",
+        )
+    }
+
+    #[test]
+    fn add_route_test() {
+        let (addr, remote) = remote_router("1");
+        let net_id = add_route(addr, &request(), Duration::from_secs(5)).unwrap();
+        assert_eq!(net_id, AmsNetId::new(5, 1, 2, 3, 1, 1));
+        assert_eq!(remote.join().unwrap(), request());
+    }
+
+    #[test]
+    fn add_route_rejected_test() {
+        let (addr, remote) = remote_router("secret");
+        let error = add_route(addr, &request(), Duration::from_secs(5)).unwrap_err();
+        assert_eq!(
+            error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            Box::new(AdsError::AdsErrDeviceInvalidAccess)
+        );
+        remote.join().unwrap();
+    }
+
+    #[test]
+    fn add_route_timeout_test() {
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let error = add_route(
+            silent.local_addr().unwrap(),
+            &request(),
+            Duration::from_millis(50),
+        )
+        .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
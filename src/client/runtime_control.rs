@@ -0,0 +1,389 @@
+use crate::ads_services::system_services::SYSTEM_SERVICE_PORT;
+use crate::client::{expect_response, AdsClient};
+use crate::proto::ads_state::AdsState;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::request::{ReadStateRequest, Request, WriteControlRequest};
+use crate::proto::response::{ReadStateResponse, WriteControlResponse};
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+///AMS port of the first PLC runtime
+pub const PLC_RUNTIME_PORT: u16 = 851;
+///Default time to wait for the expected state after an action
+pub const RUNTIME_CONTROL_TIMEOUT: Duration = Duration::from_secs(10);
+///Default interval of the read state requests while waiting
+pub const RUNTIME_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+///Device state of the WriteControl shutdown request to reboot instead of shut down
+const DEVICE_STATE_REBOOT: u16 = 1;
+
+///Actions of [RuntimeControl]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeAction {
+    ///Start the PLC runtime
+    Start,
+    ///Stop the PLC runtime
+    Stop,
+    ///Reset (cold) the PLC runtime, it is stopped afterwards
+    Reset,
+    ///Restart the TwinCAT system in config mode
+    ConfigMode,
+    ///Restart the TwinCAT system in run mode
+    RunMode,
+    ///Reboot the target
+    Reboot,
+}
+
+impl RuntimeAction {
+    ///The action is sent to the system service and not to the PLC runtime
+    pub fn is_system(&self) -> bool {
+        matches!(
+            self,
+            RuntimeAction::ConfigMode | RuntimeAction::RunMode | RuntimeAction::Reboot
+        )
+    }
+
+    pub fn write_control_request(&self) -> WriteControlRequest {
+        let (ads_state, device_state) = match self {
+            RuntimeAction::Start => (AdsState::AdsStateRun, 0),
+            RuntimeAction::Stop => (AdsState::AdsStateStop, 0),
+            RuntimeAction::Reset => (AdsState::AdsStateReset, 0),
+            RuntimeAction::ConfigMode => (AdsState::AdsStateReconfig, 0),
+            RuntimeAction::RunMode => (AdsState::AdsStateReset, 0),
+            RuntimeAction::Reboot => (AdsState::AdsStateShutDown, DEVICE_STATE_REBOOT),
+        };
+        WriteControlRequest::new(ads_state, device_state, 0, Vec::new())
+    }
+
+    ///State reached after the action. None for reboot, the target is gone until it is up again.
+    pub fn expected_state(&self) -> Option<AdsState> {
+        match self {
+            RuntimeAction::Start => Some(AdsState::AdsStateRun),
+            RuntimeAction::Stop | RuntimeAction::Reset => Some(AdsState::AdsStateStop),
+            RuntimeAction::ConfigMode => Some(AdsState::AdsStateConfig),
+            RuntimeAction::RunMode => Some(AdsState::AdsStateRun),
+            RuntimeAction::Reboot => None,
+        }
+    }
+}
+
+///Switch the TwinCAT system between run and config mode, start/stop/reset a PLC runtime or reboot the target.
+///After each action the state is polled with ReadState until the expected state is reached
+///or the timeout expires (ErrorKind::TimedOut). Errors while polling are expected during a restart
+///and are only returned if the timeout expires.
+/// ```no_run
+/// use ads_proto::client::runtime_control::RuntimeControl;
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// # fn restart<C: ads_proto::client::AdsClient>(client: &mut C) -> std::io::Result<()> {
+///
+/// let mut control = RuntimeControl::new(client, AmsNetId::new(192, 168, 1, 2, 1, 1));
+/// control.config_mode()?;
+/// control.run_mode()?;
+/// control.start()
+/// # }
+/// ```
+pub struct RuntimeControl<'a, C: AdsClient> {
+    client: &'a mut C,
+    net_id: AmsNetId,
+    plc_port: u16,
+    timeout: Duration,
+    poll_interval: Duration,
+}
+
+impl<'a, C: AdsClient> RuntimeControl<'a, C> {
+    pub fn new(client: &'a mut C, net_id: AmsNetId) -> Self {
+        RuntimeControl {
+            client,
+            net_id,
+            plc_port: PLC_RUNTIME_PORT,
+            timeout: RUNTIME_CONTROL_TIMEOUT,
+            poll_interval: RUNTIME_POLL_INTERVAL,
+        }
+    }
+
+    ///AMS port of the PLC runtime for start, stop and reset (851 for the first runtime, 852, ...)
+    pub fn set_plc_port(&mut self, port: u16) {
+        self.plc_port = port;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    pub fn start(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::Start)
+    }
+
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::Stop)
+    }
+
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::Reset)
+    }
+
+    pub fn config_mode(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::ConfigMode)
+    }
+
+    pub fn run_mode(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::RunMode)
+    }
+
+    ///Reboot the target, returns after the WriteControl response without waiting
+    pub fn reboot(&mut self) -> io::Result<()> {
+        self.execute(RuntimeAction::Reboot)
+    }
+
+    ///Send the WriteControl request of the action and wait for the expected state.
+    ///A restart of the system (run or config mode) in the expected state waits until the state
+    ///was left first, i.e. reading the state failed or returned another state.
+    ///The PLC runtime switches its state before the response, an action in the expected state
+    ///(e.g. start while running) returns without waiting.
+    pub fn execute(&mut self, action: RuntimeAction) -> io::Result<()> {
+        let port = self.port(action);
+        let expected = action.expected_state();
+        let restart = match expected {
+            Some(state) if action.is_system() => {
+                matches!(self.read_state(port), Ok((ads_state, _)) if ads_state == state)
+            }
+            _ => false,
+        };
+        let target = AmsAddress::new(self.net_id.clone(), port);
+        let request = Request::WriteControl(action.write_control_request());
+        expect_response::<WriteControlResponse>(self.client.request(&target, request)?)?;
+        let state = match expected {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let deadline = Instant::now() + self.timeout;
+        if restart {
+            self.poll_until(port, deadline, &format!("leaving {:?}", state), |s| {
+                s != Some(state)
+            })?;
+        }
+        self.poll_until(port, deadline, &format!("{:?}", state), |s| {
+            s == Some(state)
+        })
+    }
+
+    ///AdsState and device state of the device on the port
+    pub fn read_state(&mut self, port: u16) -> io::Result<(AdsState, u16)> {
+        let target = AmsAddress::new(self.net_id.clone(), port);
+        let response = self
+            .client
+            .request(&target, Request::ReadState(ReadStateRequest::new()))?;
+        expect_response::<ReadStateResponse>(response).map(|r| (r.ads_state, r.device_state))
+    }
+
+    ///Poll the state of the device on the port until it is `state`
+    pub fn wait_for_state(&mut self, port: u16, state: AdsState) -> io::Result<()> {
+        let deadline = Instant::now() + self.timeout;
+        self.poll_until(port, deadline, &format!("{:?}", state), |s| {
+            s == Some(state)
+        })
+    }
+
+    ///Poll the state until `done` returns true for it (None if reading the state failed)
+    fn poll_until<F: Fn(Option<AdsState>) -> bool>(
+        &mut self,
+        port: u16,
+        deadline: Instant,
+        goal: &str,
+        done: F,
+    ) -> io::Result<()> {
+        loop {
+            let state = self.read_state(port);
+            if done(state.as_ref().ok().map(|(ads_state, _)| *ads_state)) {
+                return Ok(());
+            }
+            let last = match state {
+                Ok((ads_state, _)) => format!("state {:?}", ads_state),
+                Err(e) => e.to_string(),
+            };
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} not reached on port {}, {}", goal, port, last),
+                ));
+            }
+            thread::sleep(self.poll_interval);
+        }
+    }
+
+    fn port(&self, action: RuntimeAction) -> u16 {
+        if action.is_system() {
+            SYSTEM_SERVICE_PORT
+        } else {
+            self.plc_port
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AdsError;
+    use crate::proto::response::Response;
+    use std::collections::HashMap;
+
+    ///Target which reaches the requested state after some read state requests
+    struct Target {
+        states: HashMap<u16, AdsState>,
+        pending: Option<(u16, AdsState, u32)>,
+        ///Failing read state requests after a WriteControl
+        delay: u32,
+        requests: Vec<(u16, WriteControlRequest)>,
+    }
+
+    impl Target {
+        fn new(delay: u32) -> Self {
+            let mut states = HashMap::new();
+            states.insert(SYSTEM_SERVICE_PORT, AdsState::AdsStateRun);
+            states.insert(PLC_RUNTIME_PORT, AdsState::AdsStateRun);
+            Target {
+                states,
+                pending: Some((0, AdsState::AdsStateInvalid, delay)),
+                delay,
+                requests: Vec::new(),
+            }
+        }
+    }
+
+    impl AdsClient for Target {
+        fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+            let port = target.port;
+            match request {
+                Request::WriteControl(r) => {
+                    let state = match r.ads_state {
+                        AdsState::AdsStateReconfig => AdsState::AdsStateConfig,
+                        AdsState::AdsStateReset if port == SYSTEM_SERVICE_PORT => {
+                            AdsState::AdsStateRun
+                        }
+                        AdsState::AdsStateReset => AdsState::AdsStateStop,
+                        AdsState::AdsStateRun
+                            if self.states[&SYSTEM_SERVICE_PORT] != AdsState::AdsStateRun =>
+                        {
+                            return Ok(Response::WriteControl(WriteControlResponse::new(
+                                AdsError::AdsErrDeviceInvalidState,
+                            )))
+                        }
+                        s => s,
+                    };
+                    self.pending = Some((port, state, self.delay));
+                    self.requests.push((port, r));
+                    Ok(Response::WriteControl(WriteControlResponse::new(
+                        AdsError::ErrNoError,
+                    )))
+                }
+                Request::ReadState(_) => {
+                    if let Some((p, state, delay)) = self.pending {
+                        if delay == 0 {
+                            self.states.insert(p, state);
+                        } else {
+                            self.pending = Some((p, state, delay - 1));
+                            return Err(io::Error::new(
+                                io::ErrorKind::ConnectionRefused,
+                                "restarting",
+                            ));
+                        }
+                    }
+                    Ok(Response::ReadState(ReadStateResponse::new(
+                        AdsError::ErrNoError,
+                        self.states[&port],
+                        0,
+                    )))
+                }
+                _ => Ok(Response::WriteControl(WriteControlResponse::new(
+                    AdsError::AdsErrDeviceSrvNotSupp,
+                ))),
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_control_test() {
+        let mut target = Target::new(3);
+        let mut control = RuntimeControl::new(&mut target, AmsNetId::new(192, 168, 1, 2, 1, 1));
+        control.set_poll_interval(Duration::from_millis(1));
+
+        control.stop().unwrap();
+        assert_eq!(
+            control.read_state(PLC_RUNTIME_PORT).unwrap().0,
+            AdsState::AdsStateStop
+        );
+        control.config_mode().unwrap();
+        assert_eq!(
+            control.read_state(SYSTEM_SERVICE_PORT).unwrap().0,
+            AdsState::AdsStateConfig
+        );
+        let error = control.start().unwrap_err();
+        assert_eq!(
+            *error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            AdsError::AdsErrDeviceInvalidState
+        );
+        control.run_mode().unwrap();
+        control.start().unwrap();
+        control.reset().unwrap();
+        control.reboot().unwrap();
+
+        let requests: Vec<(u16, AdsState, u16)> = target
+            .requests
+            .iter()
+            .map(|(port, r)| (*port, r.ads_state, r.device_state))
+            .collect();
+        assert_eq!(
+            requests,
+            vec![
+                (PLC_RUNTIME_PORT, AdsState::AdsStateStop, 0),
+                (SYSTEM_SERVICE_PORT, AdsState::AdsStateReconfig, 0),
+                (SYSTEM_SERVICE_PORT, AdsState::AdsStateReset, 0),
+                (PLC_RUNTIME_PORT, AdsState::AdsStateRun, 0),
+                (PLC_RUNTIME_PORT, AdsState::AdsStateReset, 0),
+                (SYSTEM_SERVICE_PORT, AdsState::AdsStateShutDown, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_control_timeout_test() {
+        let mut target = Target::new(u32::MAX);
+        target.states.insert(852, AdsState::AdsStateRun);
+        let mut control = RuntimeControl::new(&mut target, AmsNetId::new(192, 168, 1, 2, 1, 1));
+        control.set_plc_port(852);
+        control.set_timeout(Duration::from_millis(20));
+        control.set_poll_interval(Duration::from_millis(1));
+
+        let error = control.stop().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(target.requests[0].0, 852);
+    }
+
+    #[test]
+    fn runtime_control_restart_in_state_test() {
+        //The restart is only seen as failing read state requests
+        let mut target = Target::new(2);
+        target.pending = None;
+        let mut control = RuntimeControl::new(&mut target, AmsNetId::new(192, 168, 1, 2, 1, 1));
+        control.set_timeout(Duration::from_millis(20));
+        control.set_poll_interval(Duration::from_millis(1));
+        control.run_mode().unwrap();
+        //Started while running, the state is not left
+        control.start().unwrap();
+
+        //The state is never left
+        let mut target = Target::new(0);
+        target.pending = None;
+        let mut control = RuntimeControl::new(&mut target, AmsNetId::new(192, 168, 1, 2, 1, 1));
+        control.set_timeout(Duration::from_millis(20));
+        control.set_poll_interval(Duration::from_millis(1));
+        let error = control.run_mode().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+        assert!(error.to_string().starts_with("leaving AdsStateRun"));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::client::AdsClient;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::ams_header::{AmsHeader, AmsTcpHeader};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::request::Request;
+use crate::proto::response::Response;
+use crate::proto::state_flags::StateFlags;
+use crate::tls::{ads_tls_error, handshake_error};
+use openssl::ssl::{SslConnector, SslStream};
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+///Secure ADS client: AMS/TCP frames over a TLS connection (port [ADS_SECURE_PORT](crate::tls::ADS_SECURE_PORT)).
+///Requests are answered in order, device notifications received while waiting for a response are discarded.
+///
+///A rejected handshake is returned as error with ErrAccessDenied, other TLS errors with ErrTlsSend.
+pub struct TlsClient {
+    stream: SslStream<TcpStream>,
+    source: AmsAddress,
+    invoke_id: u32,
+}
+
+impl TlsClient {
+    ///Connect and complete the handshake.
+    ///`server_name` is checked against the certificate of the server (not used with a PSK identity).
+    ///Create the config with [client_config](crate::tls::client_config).
+    pub fn connect<A: ToSocketAddrs>(
+        addr: A,
+        server_name: &str,
+        config: &SslConnector,
+        source: AmsAddress,
+    ) -> io::Result<Self> {
+        let stream = config
+            .connect(server_name, TcpStream::connect(addr)?)
+            .map_err(handshake_error)?;
+        Ok(TlsClient {
+            stream,
+            source,
+            invoke_id: 0,
+        })
+    }
+
+    pub fn source(&self) -> &AmsAddress {
+        &self.source
+    }
+
+    ///Timeout for the response. None waits forever.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.get_ref().set_read_timeout(timeout)
+    }
+}
+
+impl AdsClient for TlsClient {
+    fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+        self.invoke_id = self.invoke_id.wrapping_add(1);
+        let header = AmsHeader::new(
+            target.clone(),
+            self.source.clone(),
+            StateFlags::req_default(),
+            self.invoke_id,
+            request,
+        );
+        let mut buffer: Vec<u8> = Vec::new();
+        AmsTcpHeader::from(header).write_to(&mut buffer)?;
+        self.stream
+            .write_all(&buffer)
+            .and_then(|_| self.stream.flush())
+            .map_err(ads_tls_error)?;
+
+        loop {
+            let mut frame = AmsTcpHeader::read_from(&mut self.stream).map_err(ads_tls_error)?;
+            let header = &mut frame.ams_header;
+            if header.state_flags().is_response()
+                && header.invoke_id() == self.invoke_id
+                && header.source_address() == target
+            {
+                return header.response();
+            }
+        }
+    }
+}
+
+impl Drop for TlsClient {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown();
+    }
+}
@@ -0,0 +1,241 @@
+use crate::client::AdsClient;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::request::Request;
+use crate::proto::response::Response;
+use crate::proto::state_flags::{NetProto, StateFlags};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+///Time to wait for a response before the request is sent again
+pub const UDP_DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+///Number of times a request is sent again if no response was received
+pub const UDP_DEFAULT_RETRIES: u32 = 2;
+
+///ADS client over UDP. Each AmsHeader is sent as one datagram with the UDP state flag.
+///
+///Lost datagrams are handled by sending the request again with the same invoke id.
+///Responses with another invoke id (e.g. duplicated or late responses of previous requests)
+///and datagrams of other senders are discarded. Device notifications are discarded as well.
+/// ```no_run
+/// use ads_proto::client::udp_client::UdpClient;
+/// use ads_proto::client::AdsClient;
+/// use ads_proto::proto::ams_address::{AmsAddress, AmsNetId};
+/// use ads_proto::proto::request::{ReadDeviceInfoRequest, Request};
+///
+/// let source = AmsAddress::new(AmsNetId::new(192, 168, 1, 10, 1, 1), 30000);
+/// let mut client = UdpClient::connect("192.168.1.2:48899".parse().unwrap(), source).unwrap();
+/// let target = AmsAddress::new(AmsNetId::new(192, 168, 1, 2, 1, 1), 851);
+/// let response = client.request(&target, Request::ReadDeviceInfo(ReadDeviceInfoRequest::new()));
+/// ```
+pub struct UdpClient {
+    socket: UdpSocket,
+    remote: SocketAddr,
+    source: AmsAddress,
+    invoke_id: u32,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl UdpClient {
+    ///Bind an UDP socket and send the requests to `remote` (ip of the device, port 48899).
+    ///`source` is the AmsAddress of this client the device sends the responses to.
+    pub fn connect(remote: SocketAddr, source: AmsAddress) -> io::Result<Self> {
+        let bind_addr: SocketAddr = match remote {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind_addr)?;
+        socket.connect(remote)?;
+        Ok(UdpClient {
+            socket,
+            remote,
+            source,
+            invoke_id: 0,
+            timeout: UDP_DEFAULT_TIMEOUT,
+            retries: UDP_DEFAULT_RETRIES,
+        })
+    }
+
+    pub fn source(&self) -> &AmsAddress {
+        &self.source
+    }
+
+    pub fn remote(&self) -> SocketAddr {
+        self.remote
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    ///Time to wait for the response of a single attempt
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    ///Wait for the response with the invoke id until the deadline.
+    ///Returns None if the deadline passed.
+    fn receive(
+        &self,
+        target: &AmsAddress,
+        invoke_id: u32,
+        deadline: Instant,
+    ) -> io::Result<Option<AmsHeader>> {
+        let mut buffer = [0; 65536];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            self.socket.set_read_timeout(Some(remaining))?;
+            let len = match self.socket.recv(&mut buffer) {
+                Ok(len) => len,
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            let header = match AmsHeader::from_datagram(&buffer[..len]) {
+                Ok(h) => h,
+                Err(_) => continue,
+            };
+            if header.state_flags().is_response()
+                && header.invoke_id() == invoke_id
+                && header.source_address() == target
+                && header.target_address() == &self.source
+            {
+                return Ok(Some(header));
+            }
+        }
+    }
+}
+
+impl AdsClient for UdpClient {
+    fn request(&mut self, target: &AmsAddress, request: Request) -> io::Result<Response> {
+        self.invoke_id = self.invoke_id.wrapping_add(1);
+        let invoke_id = self.invoke_id;
+        let datagram = AmsHeader::new(
+            target.clone(),
+            self.source.clone(),
+            StateFlags::new(false, true, NetProto::Udp),
+            invoke_id,
+            request,
+        )
+        .to_datagram();
+
+        for _ in 0..=self.retries {
+            self.socket.send(&datagram)?;
+            if let Some(mut header) =
+                self.receive(target, invoke_id, Instant::now() + self.timeout)?
+            {
+                return header.response();
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "no response from {} after {} attempts",
+                self.remote,
+                self.retries + 1
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AdsError;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::proto::request::ReadRequest;
+    use crate::proto::response::ReadResponse;
+    use std::thread;
+
+    fn source() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(127, 0, 0, 1, 1, 1), 30000)
+    }
+
+    fn target() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(127, 0, 0, 1, 2, 1), 851)
+    }
+
+    #[test]
+    fn udp_client_lost_and_duplicated_test() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let remote = device.local_addr().unwrap();
+        let stub = thread::spawn(move || {
+            let mut buffer = [0; 2048];
+            let respond = |len: usize, buffer: &[u8], value: u8, invoke_offset: u32| {
+                let header = AmsHeader::from_datagram(&buffer[..len]).unwrap();
+                assert!(!header.state_flags().is_tcp());
+                let invoke_id = header.invoke_id();
+                let response = AmsHeader::new(
+                    header.source_address().clone(),
+                    header.target_address().clone(),
+                    StateFlags::new(true, true, NetProto::Udp),
+                    invoke_id - invoke_offset,
+                    Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![value])),
+                );
+                response.to_datagram()
+            };
+
+            //First request: lost, then answered twice
+            let (len, client) = device.recv_from(&mut buffer).unwrap();
+            let first = buffer[..len].to_vec();
+            let (len, _) = device.recv_from(&mut buffer).unwrap();
+            assert_eq!(buffer[..len], first[..], "same invoke id for the retry");
+            let response = respond(len, &buffer, 1, 0);
+            device.send_to(&response, client).unwrap();
+            device.send_to(&response, client).unwrap();
+
+            //Second request: stale response first, garbage, then the response
+            let (len, client) = device.recv_from(&mut buffer).unwrap();
+            device
+                .send_to(&respond(len, &buffer, 9, 1), client)
+                .unwrap();
+            device.send_to(&[0, 1, 2], client).unwrap();
+            device
+                .send_to(&respond(len, &buffer, 2, 0), client)
+                .unwrap();
+        });
+
+        let mut client = UdpClient::connect(remote, source()).unwrap();
+        client.set_timeout(Duration::from_millis(200));
+        for value in [1, 2] {
+            let response = client
+                .request(&target(), Request::Read(ReadRequest::new(0x4020, 0, 1)))
+                .unwrap();
+            assert_eq!(
+                response,
+                Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![value]))
+            );
+        }
+        stub.join().unwrap();
+    }
+
+    #[test]
+    fn udp_client_timeout_test() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = UdpClient::connect(device.local_addr().unwrap(), source()).unwrap();
+        client.set_timeout(Duration::from_millis(20));
+        client.set_retries(1);
+        let error = client
+            .request(&target(), Request::Read(ReadRequest::new(0x4020, 0, 1)))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+
+        //Both attempts were sent
+        let mut buffer = [0; 2048];
+        device.recv_from(&mut buffer).unwrap();
+        device.recv_from(&mut buffer).unwrap();
+    }
+}
@@ -1,3 +1,5 @@
+use crate::proto::ads_transition_mode::AdsTransMode;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
@@ -16,6 +18,49 @@ pub enum AmsAddressError {
     SplitError { length: usize },
     #[error("Supplied address length {}! Expected a length of 6", length)]
     InvalidAddressLength { length: usize },
+    #[error("{} is no IPv4 address", ip)]
+    NoIpv4Address { ip: std::net::IpAddr },
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum AdsVersionError {
+    #[error("Failed parsing version number")]
+    ParseError { source: std::num::ParseIntError },
+    #[error("{} is no version major.minor.build", version)]
+    InvalidFormat { version: String },
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum RouteTableError {
+    #[error("Failed reading route file: {}", message)]
+    Io { message: String },
+    #[error("Failed parsing route file: {}", message)]
+    Parse { message: String },
+    #[error("Route {} has no {}", route, field)]
+    MissingField { route: usize, field: &'static str },
+    #[error("Invalid AmsNetId {}", net_id)]
+    InvalidNetId {
+        net_id: String,
+        source: AmsAddressError,
+    },
+    #[error("Failed resolving address {}", address)]
+    InvalidAddress { address: String },
+}
+
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum NotificationOptionsError {
+    #[error("No transmission mode set")]
+    MissingTransMode,
+    #[error("Transmission mode {:?} is not supported by the ADS server", mode)]
+    UnsupportedTransMode { mode: AdsTransMode },
+    #[error("No length set")]
+    MissingLength,
+    #[error("Length {} does not match the type size {}", length, type_size)]
+    LengthMismatch { length: u32, type_size: u32 },
+    #[error("{:?} is too long. Max is {:?}", duration, max)]
+    DurationOutOfRange { duration: Duration, max: Duration },
+    #[error("Transmission mode {:?} needs a cycle time", mode)]
+    ZeroCycleTime { mode: AdsTransMode },
 }
 
 #[derive(Error, Debug, PartialEq, Eq, Clone)]
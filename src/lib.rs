@@ -4,7 +4,19 @@
 
 ///A collection of System Services with index group and index offset
 pub mod ads_services;
+///Client side helpers built on top of the request/response types (polling, notifications, ...).
+pub mod client;
 ///contains the ADS error codes and additional error types used in the module proto.
 pub mod error;
+///ADS over MQTT: minimal MQTT 3.1.1 client, the topic scheme of TwinCAT ADS over MQTT (feature mqtt).
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
 ///contains everything you need to create an [AMS header](proto::ams_header) and it's payload.
 pub mod proto;
+///Userspace AMS router for hosts without TwinCAT (port connect, routing to remote AmsNetIds).
+pub mod router;
+///Server side: host [AdsDevice](server::AdsDevice) implementations on AMS ports and answer their requests.
+pub mod server;
+///Secure ADS: TLS configs and identities for the TLS client and server (feature tls).
+#[cfg(feature = "tls")]
+pub mod tls;
@@ -0,0 +1,219 @@
+use crate::mqtt::packet::MqttPublish;
+use crate::proto::ams_address::AmsNetId;
+use crate::proto::ams_header::{AmsHeader, AmsTcpHeader};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use std::io;
+use std::str::FromStr;
+
+///Default TCP port of MQTT brokers
+pub const MQTT_PORT: u16 = 1883;
+///Topic of the virtual AMS network in the TwinCAT default configuration
+pub const ADS_MQTT_DEFAULT_TOPIC: &str = "VirtualAmsNetwork1";
+
+///Topics of ADS over MQTT below the topic of a virtual AMS network:
+///- `<topic>/<AmsNetId>/ams` requests (and device notifications) to the router with the AmsNetId
+///- `<topic>/<AmsNetId>/ams/res` responses to the router with the AmsNetId
+///- `<topic>/<AmsNetId>/info` retained online state of the router, see [MqttRouterInfo]
+///
+///The payload of the ams topics is the AMS/TCP frame (AmsTcpHeader with the AMS header and data).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdsMqttTopics {
+    pub topic: String,
+}
+
+impl AdsMqttTopics {
+    pub fn new(topic: &str) -> Self {
+        AdsMqttTopics {
+            topic: topic.to_string(),
+        }
+    }
+
+    pub fn ams(&self, net_id: &AmsNetId) -> String {
+        format!("{}/{}/ams", self.topic, net_id)
+    }
+
+    pub fn ams_response(&self, net_id: &AmsNetId) -> String {
+        format!("{}/{}/ams/res", self.topic, net_id)
+    }
+
+    pub fn info(&self, net_id: &AmsNetId) -> String {
+        format!("{}/{}/info", self.topic, net_id)
+    }
+
+    ///Filter for requests and responses to the router with the AmsNetId
+    pub fn ams_filter(&self, net_id: &AmsNetId) -> String {
+        format!("{}/{}/ams/#", self.topic, net_id)
+    }
+
+    ///Filter for the info topics of all routers of the virtual AMS network
+    pub fn info_filter(&self) -> String {
+        format!("{}/+/info", self.topic)
+    }
+
+    ///AmsNetId of an info topic
+    pub fn info_net_id(&self, topic: &str) -> Option<AmsNetId> {
+        let net_id = topic
+            .strip_prefix(self.topic.as_str())?
+            .strip_prefix('/')?
+            .strip_suffix("/info")?;
+        AmsNetId::from_str(net_id).ok()
+    }
+
+    ///Message with the AMS/TCP frame of the header. Responses are published to the response topic
+    ///of the target, requests to its ams topic.
+    pub fn publish(&self, header: AmsHeader) -> io::Result<MqttPublish> {
+        let net_id = &header.target_address().ams_net_id;
+        let topic = if header.state_flags().is_response() {
+            self.ams_response(net_id)
+        } else {
+            self.ams(net_id)
+        };
+        let mut payload: Vec<u8> = Vec::new();
+        AmsTcpHeader::from(header).write_to(&mut payload)?;
+        Ok(MqttPublish::new(&topic, payload, false))
+    }
+}
+
+impl Default for AdsMqttTopics {
+    fn default() -> Self {
+        AdsMqttTopics::new(ADS_MQTT_DEFAULT_TOPIC)
+    }
+}
+
+///AMS header of the payload of an ams topic
+pub fn header_from_payload(payload: &[u8]) -> io::Result<AmsHeader> {
+    Ok(AmsTcpHeader::read_from(&mut &payload[..])?.ams_header)
+}
+
+///Online state of a router published (retained) on its info topic.
+///The payload is `<info><online name="...">true</online></info>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttRouterInfo {
+    pub net_id: AmsNetId,
+    pub name: String,
+    pub online: bool,
+}
+
+impl MqttRouterInfo {
+    pub fn new(net_id: AmsNetId, name: &str, online: bool) -> Self {
+        MqttRouterInfo {
+            net_id,
+            name: name.to_string(),
+            online,
+        }
+    }
+
+    ///Retained message for the info topic
+    pub fn publish(&self, topics: &AdsMqttTopics) -> MqttPublish {
+        let payload = format!(
+            "<info><online name=\"{}\">{}</online></info>",
+            escape(&self.name),
+            self.online
+        );
+        MqttPublish::new(&topics.info(&self.net_id), payload.into_bytes(), true)
+    }
+
+    ///Info of a message received on an info topic
+    pub fn from_publish(topics: &AdsMqttTopics, publish: &MqttPublish) -> io::Result<Self> {
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let net_id = topics
+            .info_net_id(&publish.topic)
+            .ok_or_else(|| invalid("no ADS over MQTT info topic"))?;
+        let payload = std::str::from_utf8(&publish.payload).map_err(|e| invalid(&e.to_string()))?;
+        let online = &payload[payload
+            .find("<online")
+            .ok_or_else(|| invalid("no online element in the info"))?..];
+        let end = online
+            .find('>')
+            .ok_or_else(|| invalid("invalid online element"))?;
+        let (attributes, content) = online.split_at(end);
+        let name = attributes
+            .split_once("name=\"")
+            .and_then(|(_, name)| name.split_once('"'))
+            .map(|(name, _)| unescape(name))
+            .unwrap_or_default();
+        let online = content[1..].trim_start().starts_with("true");
+        Ok(MqttRouterInfo::new(net_id, &name, online))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&gt;", ">")
+        .replace("&lt;", "<")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ams_address::AmsAddress;
+    use crate::proto::request::{ReadRequest, Request};
+    use crate::proto::state_flags::StateFlags;
+
+    #[test]
+    fn ads_mqtt_topics_test() {
+        let topics = AdsMqttTopics::default();
+        let net_id = AmsNetId::new(192, 168, 1, 2, 1, 1);
+        assert_eq!(
+            topics.ams(&net_id),
+            "VirtualAmsNetwork1/192.168.1.2.1.1/ams"
+        );
+        assert_eq!(
+            topics.ams_response(&net_id),
+            "VirtualAmsNetwork1/192.168.1.2.1.1/ams/res"
+        );
+        assert_eq!(topics.info_filter(), "VirtualAmsNetwork1/+/info");
+        assert_eq!(
+            topics.info_net_id(&topics.info(&net_id)),
+            Some(net_id.clone())
+        );
+        assert_eq!(topics.info_net_id("VirtualAmsNetwork1/x/info"), None);
+        assert_eq!(topics.info_net_id("Other/192.168.1.2.1.1/info"), None);
+
+        let header = AmsHeader::new(
+            AmsAddress::new(net_id.clone(), 851),
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 30000),
+            StateFlags::req_default(),
+            1,
+            Request::Read(ReadRequest::new(0x4020, 0, 4)),
+        );
+        let publish = topics.publish(header).unwrap();
+        assert_eq!(publish.topic, topics.ams(&net_id));
+        assert!(!publish.retain);
+        let decoded = header_from_payload(&publish.payload).unwrap();
+        assert_eq!(decoded.invoke_id(), 1);
+        assert_eq!(topics.publish(decoded).unwrap(), publish);
+    }
+
+    #[test]
+    fn mqtt_router_info_test() {
+        let topics = AdsMqttTopics::new("net");
+        let info = MqttRouterInfo::new(AmsNetId::new(1, 2, 3, 4, 1, 1), "CX \"1\" <&>", true);
+        let publish = info.publish(&topics);
+        assert!(publish.retain);
+        assert_eq!(publish.topic, "net/1.2.3.4.1.1/info");
+        assert_eq!(
+            MqttRouterInfo::from_publish(&topics, &publish).unwrap(),
+            info
+        );
+
+        let offline = MqttRouterInfo::new(AmsNetId::new(1, 2, 3, 4, 1, 1), "", false);
+        assert_eq!(
+            MqttRouterInfo::from_publish(&topics, &offline.publish(&topics)).unwrap(),
+            offline
+        );
+        let invalid = MqttPublish::new("net/1.2.3.4.1.1/info", b"offline".to_vec(), true);
+        assert!(MqttRouterInfo::from_publish(&topics, &invalid).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::mqtt::packet::{topic_matches, MqttPacket, MqttPublish, MQTT_CONNECTION_ACCEPTED};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+///Minimal in-process MQTT 3.1.1 broker (QoS 0, retained messages, wills).
+///Stands in for a real broker in the tests of ADS over MQTT. There is no authentication,
+///credentials in CONNECT are ignored.
+pub struct MqttBroker {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    state: Arc<Mutex<BrokerState>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Session {
+    stream: TcpStream,
+    filters: Vec<String>,
+}
+
+#[derive(Default)]
+struct BrokerState {
+    sessions: HashMap<usize, Session>,
+    retained: BTreeMap<String, MqttPublish>,
+}
+
+impl BrokerState {
+    fn send(&mut self, id: usize, packet: &MqttPacket) {
+        if let Some(session) = self.sessions.get_mut(&id) {
+            let mut buffer: Vec<u8> = Vec::new();
+            if packet.write_to(&mut buffer).is_ok() {
+                let _ = session.stream.write_all(&buffer);
+            }
+        }
+    }
+
+    ///Store a retained message and forward it to the matching subscriptions
+    fn publish(&mut self, publish: MqttPublish) {
+        if publish.retain {
+            if publish.payload.is_empty() {
+                self.retained.remove(&publish.topic);
+            } else {
+                self.retained.insert(publish.topic.clone(), publish.clone());
+            }
+        }
+        let topic = publish.topic.clone();
+        let packet = MqttPacket::Publish(MqttPublish {
+            retain: false,
+            ..publish
+        });
+        let mut buffer: Vec<u8> = Vec::new();
+        if packet.write_to(&mut buffer).is_err() {
+            return;
+        }
+        for session in self.sessions.values_mut() {
+            if session
+                .filters
+                .iter()
+                .any(|filter| topic_matches(filter, &topic))
+            {
+                let _ = session.stream.write_all(&buffer);
+            }
+        }
+    }
+
+    fn subscribe(&mut self, id: usize, packet_id: u16, filters: Vec<(String, u8)>) {
+        let return_codes = vec![0; filters.len()];
+        let retained: Vec<MqttPublish> = self
+            .retained
+            .values()
+            .filter(|publish| {
+                filters
+                    .iter()
+                    .any(|(filter, _)| topic_matches(filter, &publish.topic))
+            })
+            .cloned()
+            .collect();
+        if let Some(session) = self.sessions.get_mut(&id) {
+            session
+                .filters
+                .extend(filters.into_iter().map(|(filter, _)| filter));
+        }
+        self.send(
+            id,
+            &MqttPacket::SubAck {
+                packet_id,
+                return_codes,
+            },
+        );
+        for publish in retained {
+            self.send(id, &MqttPacket::Publish(publish));
+        }
+    }
+}
+
+impl MqttBroker {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(BrokerState::default()));
+
+        let accept_stop = stop.clone();
+        let accept_state = state.clone();
+        let thread = thread::spawn(move || {
+            for (id, stream) in listener.incoming().enumerate() {
+                if accept_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let state = accept_state.clone();
+                    thread::spawn(move || serve_session(id, stream, &state));
+                }
+            }
+        });
+
+        Ok(MqttBroker {
+            local_addr,
+            stop,
+            state,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    ///Retained message of the topic
+    pub fn retained(&self, topic: &str) -> Option<MqttPublish> {
+        self.state
+            .lock()
+            .expect("broker lock poisoned")
+            .retained
+            .get(topic)
+            .cloned()
+    }
+
+    ///Stop accepting connections and close the open connections
+    pub fn shutdown(mut self) {
+        self.stop_broker();
+    }
+
+    fn stop_broker(&mut self) {
+        let thread = match self.thread.take() {
+            Some(thread) => thread,
+            None => return,
+        };
+        self.stop.store(true, Ordering::SeqCst);
+        //Wake up the blocking accept
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        let _ = TcpStream::connect(wake_addr);
+        let _ = thread.join();
+        for session in self
+            .state
+            .lock()
+            .expect("broker lock poisoned")
+            .sessions
+            .values()
+        {
+            let _ = session.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for MqttBroker {
+    fn drop(&mut self) {
+        self.stop_broker();
+    }
+}
+
+///Serve one client connection. The will is published if the connection is lost without DISCONNECT.
+fn serve_session(id: usize, mut stream: TcpStream, state: &Mutex<BrokerState>) {
+    let connect = match MqttPacket::read_from(&mut stream) {
+        Ok(MqttPacket::Connect(connect)) => connect,
+        _ => return,
+    };
+    let mut will = connect.will;
+    {
+        let mut state = state.lock().expect("broker lock poisoned");
+        let session_stream = match stream.try_clone() {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        state.sessions.insert(
+            id,
+            Session {
+                stream: session_stream,
+                filters: Vec::new(),
+            },
+        );
+        state.send(
+            id,
+            &MqttPacket::ConnAck {
+                session_present: false,
+                return_code: MQTT_CONNECTION_ACCEPTED,
+            },
+        );
+    }
+
+    while let Ok(packet) = MqttPacket::read_from(&mut stream) {
+        let mut state = state.lock().expect("broker lock poisoned");
+        match packet {
+            MqttPacket::Publish(publish) => state.publish(publish),
+            MqttPacket::Subscribe { packet_id, filters } => state.subscribe(id, packet_id, filters),
+            MqttPacket::PingReq => state.send(id, &MqttPacket::PingResp),
+            MqttPacket::Disconnect => {
+                will = None;
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    let mut state = state.lock().expect("broker lock poisoned");
+    state.sessions.remove(&id);
+    if let Some(will) = will {
+        state.publish(will);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::connection::MqttConnection;
+    use crate::mqtt::packet::MqttConnect;
+    use std::time::Duration;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn mqtt_broker_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let publisher =
+            MqttConnection::connect(broker.local_addr(), MqttConnect::new("a", 30)).unwrap();
+        publisher
+            .publish(MqttPublish::new("net/1/info", b"online".to_vec(), true))
+            .unwrap();
+
+        let mut subscriber =
+            MqttConnection::connect(broker.local_addr(), MqttConnect::new("b", 30)).unwrap();
+        subscriber.subscribe("net/+/info").unwrap();
+        subscriber.subscribe("net/1/ams/#").unwrap();
+        //Retained message first
+        let retained = subscriber.receive(TIMEOUT).unwrap().unwrap();
+        assert_eq!(
+            retained,
+            MqttPublish::new("net/1/info", b"online".to_vec(), true)
+        );
+
+        publisher
+            .publish(MqttPublish::new("net/2/ams", vec![1], false))
+            .unwrap();
+        publisher
+            .publish(MqttPublish::new("net/1/ams/res", vec![2], false))
+            .unwrap();
+        let received = subscriber.receive(TIMEOUT).unwrap().unwrap();
+        assert_eq!(received, MqttPublish::new("net/1/ams/res", vec![2], false));
+        assert_eq!(subscriber.receive(Duration::from_millis(50)).unwrap(), None);
+        broker.shutdown();
+    }
+
+    #[test]
+    fn mqtt_broker_will_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let mut subscriber =
+            MqttConnection::connect(broker.local_addr(), MqttConnect::new("b", 30)).unwrap();
+        subscriber.subscribe("will/#").unwrap();
+
+        //Connection closed without DISCONNECT
+        let mut stream = TcpStream::connect(broker.local_addr()).unwrap();
+        let mut connect = MqttConnect::new("a", 30);
+        connect.will = Some(MqttPublish::new("will/a", b"gone".to_vec(), true));
+        MqttPacket::Connect(connect).write_to(&mut stream).unwrap();
+        assert!(matches!(
+            MqttPacket::read_from(&mut stream).unwrap(),
+            MqttPacket::ConnAck { return_code: 0, .. }
+        ));
+        drop(stream);
+
+        let will = subscriber.receive(TIMEOUT).unwrap().unwrap();
+        assert_eq!(will, MqttPublish::new("will/a", b"gone".to_vec(), false));
+        assert!(broker.retained("will/a").is_some());
+
+        //No will after DISCONNECT
+        let mut connect = MqttConnect::new("c", 30);
+        connect.will = Some(MqttPublish::new("will/c", b"gone".to_vec(), false));
+        drop(MqttConnection::connect(broker.local_addr(), connect).unwrap());
+        assert_eq!(
+            subscriber.receive(Duration::from_millis(100)).unwrap(),
+            None
+        );
+        broker.shutdown();
+    }
+}
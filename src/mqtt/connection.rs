@@ -0,0 +1,202 @@
+use crate::mqtt::packet::{
+    packet_len, MqttConnect, MqttPacket, MqttPublish, MQTT_CONNECTION_ACCEPTED,
+    MQTT_SUBSCRIPTION_FAILURE,
+};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+///Keep alive in seconds sent in the CONNECT of the transports
+pub const MQTT_KEEP_ALIVE: u16 = 30;
+///Time to wait for CONNACK and SUBACK
+pub const MQTT_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+///Read timeout of the background thread to check for shutdown and keep alive
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+///Minimal MQTT 3.1.1 client connection (QoS 0).
+///A background thread reads the packets from the broker and sends PINGREQ after half of the keep alive,
+///the received messages are returned by [MqttConnection::receive].
+///DISCONNECT is sent when dropped, so the broker does not publish the will.
+pub struct MqttConnection {
+    stream: Arc<Mutex<TcpStream>>,
+    publishes: Receiver<MqttPublish>,
+    sub_acks: Receiver<(u16, Vec<u8>)>,
+    packet_id: u16,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MqttConnection {
+    ///Connect to the broker and wait for CONNACK.
+    ///A refused connection (e.g. wrong credentials) is returned as ConnectionRefused with the return code.
+    pub fn connect<A: ToSocketAddrs>(broker: A, connect: MqttConnect) -> io::Result<Self> {
+        let keep_alive = Duration::from_secs(connect.keep_alive as u64);
+        let mut stream = TcpStream::connect(broker)?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(MQTT_ACK_TIMEOUT))?;
+        send(&mut stream, &MqttPacket::Connect(connect))?;
+        match MqttPacket::read_from(&mut stream)? {
+            MqttPacket::ConnAck {
+                return_code: MQTT_CONNECTION_ACCEPTED,
+                ..
+            } => {}
+            MqttPacket::ConnAck { return_code, .. } => {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionRefused,
+                    format!("MQTT connection refused (return code {})", return_code),
+                ))
+            }
+            packet => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected CONNACK, received {:?}", packet),
+                ))
+            }
+        }
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (publish_tx, publishes) = channel();
+        let (sub_ack_tx, sub_acks) = channel();
+        let mut reader = stream.try_clone()?;
+        let stream = Arc::new(Mutex::new(stream));
+        let writer = stream.clone();
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut received: Vec<u8> = Vec::new();
+            let mut buffer = [0; 4096];
+            let mut last_ping = Instant::now();
+            while !thread_stop.load(Ordering::SeqCst) {
+                match reader.read(&mut buffer) {
+                    Ok(0) => return,
+                    Ok(n) => received.extend_from_slice(&buffer[..n]),
+                    Err(e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut => {}
+                    Err(_) => return,
+                }
+                loop {
+                    let len = match packet_len(&received) {
+                        Ok(Some(len)) => len,
+                        Ok(None) => break,
+                        Err(_) => return,
+                    };
+                    let packet = MqttPacket::read_from(&mut &received[..len]);
+                    received.drain(..len);
+                    match packet {
+                        Ok(MqttPacket::Publish(publish)) => {
+                            let _ = publish_tx.send(publish);
+                        }
+                        Ok(MqttPacket::SubAck {
+                            packet_id,
+                            return_codes,
+                        }) => {
+                            let _ = sub_ack_tx.send((packet_id, return_codes));
+                        }
+                        Ok(_) => {}
+                        Err(_) => return,
+                    }
+                }
+                if !keep_alive.is_zero() && last_ping.elapsed() >= keep_alive / 2 {
+                    let mut writer = writer.lock().expect("mqtt stream lock poisoned");
+                    if send(&mut writer, &MqttPacket::PingReq).is_err() {
+                        return;
+                    }
+                    last_ping = Instant::now();
+                }
+            }
+        });
+
+        Ok(MqttConnection {
+            stream,
+            publishes,
+            sub_acks,
+            packet_id: 0,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    ///Subscribe the topic filter with QoS 0 and wait for SUBACK.
+    ///Messages matching the filter are received from now on (retained messages first).
+    pub fn subscribe(&mut self, filter: &str) -> io::Result<()> {
+        self.packet_id = self.packet_id.checked_add(1).unwrap_or(1);
+        self.send(&MqttPacket::Subscribe {
+            packet_id: self.packet_id,
+            filters: vec![(filter.to_string(), 0)],
+        })?;
+        let deadline = Instant::now() + MQTT_ACK_TIMEOUT;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.sub_acks.recv_timeout(remaining) {
+                Ok((packet_id, return_codes)) if packet_id == self.packet_id => {
+                    if return_codes.contains(&MQTT_SUBSCRIPTION_FAILURE) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::PermissionDenied,
+                            format!("MQTT subscription of {} rejected", filter),
+                        ));
+                    }
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("no SUBACK for {}", filter),
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Err(connection_closed()),
+            }
+        }
+    }
+
+    pub fn publish(&self, publish: MqttPublish) -> io::Result<()> {
+        self.send(&MqttPacket::Publish(publish))
+    }
+
+    ///Next received message. Returns None if nothing was received within the timeout.
+    pub fn receive(&self, timeout: Duration) -> io::Result<Option<MqttPublish>> {
+        match self.publishes.recv_timeout(timeout) {
+            Ok(publish) => Ok(Some(publish)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(connection_closed()),
+        }
+    }
+
+    fn send(&self, packet: &MqttPacket) -> io::Result<()> {
+        send(
+            &mut self.stream.lock().expect("mqtt stream lock poisoned"),
+            packet,
+        )
+    }
+}
+
+impl Drop for MqttConnection {
+    fn drop(&mut self) {
+        let _ = self.send(&MqttPacket::Disconnect);
+        self.stop.store(true, Ordering::SeqCst);
+        let _ = self
+            .stream
+            .lock()
+            .expect("mqtt stream lock poisoned")
+            .shutdown(Shutdown::Both);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn send(stream: &mut TcpStream, packet: &MqttPacket) -> io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    packet.write_to(&mut buffer)?;
+    stream.write_all(&buffer)
+}
+
+fn connection_closed() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "MQTT connection closed")
+}
@@ -0,0 +1,5 @@
+pub mod ads_mqtt;
+#[cfg(test)]
+pub(crate) mod broker;
+pub mod connection;
+pub mod packet;
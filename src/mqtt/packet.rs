@@ -0,0 +1,440 @@
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///Protocol level of MQTT 3.1.1
+pub const MQTT_PROTOCOL_LEVEL: u8 = 4;
+///Return code of an accepted connection (CONNACK)
+pub const MQTT_CONNECTION_ACCEPTED: u8 = 0;
+///Return code of a rejected topic filter (SUBACK)
+pub const MQTT_SUBSCRIPTION_FAILURE: u8 = 0x80;
+///Largest remaining length which can be encoded (4 bytes)
+pub const MQTT_MAX_REMAINING_LENGTH: usize = 268_435_455;
+
+const CONNECT: u8 = 1;
+const CONNACK: u8 = 2;
+const PUBLISH: u8 = 3;
+const SUBSCRIBE: u8 = 8;
+const SUBACK: u8 = 9;
+const PINGREQ: u8 = 12;
+const PINGRESP: u8 = 13;
+const DISCONNECT: u8 = 14;
+
+const CLEAN_SESSION: u8 = 0x02;
+const WILL_FLAG: u8 = 0x04;
+const WILL_RETAIN: u8 = 0x20;
+const PASSWORD_FLAG: u8 = 0x40;
+const USER_NAME_FLAG: u8 = 0x80;
+
+const RETAIN: u8 = 0x01;
+const QOS_MASK: u8 = 0x06;
+///Fixed flags of the SUBSCRIBE packet
+const SUBSCRIBE_FLAGS: u8 = 0x02;
+
+///Application message (PUBLISH). Only QoS 0 is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttPublish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    ///Stored by the broker and sent to later subscribers. An empty retained payload deletes it.
+    pub retain: bool,
+}
+
+impl MqttPublish {
+    pub fn new(topic: &str, payload: Vec<u8>, retain: bool) -> Self {
+        MqttPublish {
+            topic: topic.to_string(),
+            payload,
+            retain,
+        }
+    }
+}
+
+///CONNECT packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttConnect {
+    pub client_id: String,
+    ///Seconds. The broker closes the connection if nothing was received for 1.5 times the keep alive, 0 disables it.
+    pub keep_alive: u16,
+    pub clean_session: bool,
+    ///Published by the broker if the connection is lost without DISCONNECT
+    pub will: Option<MqttPublish>,
+    pub user_name: Option<String>,
+    pub password: Option<Vec<u8>>,
+}
+
+impl MqttConnect {
+    ///Clean session without will and credentials
+    pub fn new(client_id: &str, keep_alive: u16) -> Self {
+        MqttConnect {
+            client_id: client_id.to_string(),
+            keep_alive,
+            clean_session: true,
+            will: None,
+            user_name: None,
+            password: None,
+        }
+    }
+
+    pub fn with_credentials(mut self, user_name: &str, password: &str) -> Self {
+        self.user_name = Some(user_name.to_string());
+        self.password = Some(password.as_bytes().to_vec());
+        self
+    }
+
+    fn write_body(&self, body: &mut Vec<u8>) -> io::Result<()> {
+        write_string(body, "MQTT")?;
+        body.write_u8(MQTT_PROTOCOL_LEVEL)?;
+        let mut flags = 0;
+        if self.clean_session {
+            flags |= CLEAN_SESSION;
+        }
+        if let Some(will) = &self.will {
+            flags |= WILL_FLAG;
+            if will.retain {
+                flags |= WILL_RETAIN;
+            }
+        }
+        if self.user_name.is_some() {
+            flags |= USER_NAME_FLAG;
+        }
+        if self.password.is_some() {
+            flags |= PASSWORD_FLAG;
+        }
+        body.write_u8(flags)?;
+        body.write_u16::<BigEndian>(self.keep_alive)?;
+        write_string(body, &self.client_id)?;
+        if let Some(will) = &self.will {
+            write_string(body, &will.topic)?;
+            write_bytes(body, &will.payload)?;
+        }
+        if let Some(user_name) = &self.user_name {
+            write_string(body, user_name)?;
+        }
+        if let Some(password) = &self.password {
+            write_bytes(body, password)?;
+        }
+        Ok(())
+    }
+
+    fn read_body(body: &mut &[u8]) -> io::Result<Self> {
+        let protocol = read_string(body)?;
+        let level = body.read_u8()?;
+        if protocol != "MQTT" || level != MQTT_PROTOCOL_LEVEL {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported MQTT protocol {} level {}", protocol, level),
+            ));
+        }
+        let flags = body.read_u8()?;
+        let keep_alive = body.read_u16::<BigEndian>()?;
+        let client_id = read_string(body)?;
+        let will = if flags & WILL_FLAG != 0 {
+            let topic = read_string(body)?;
+            let payload = read_bytes(body)?;
+            Some(MqttPublish::new(&topic, payload, flags & WILL_RETAIN != 0))
+        } else {
+            None
+        };
+        let user_name = if flags & USER_NAME_FLAG != 0 {
+            Some(read_string(body)?)
+        } else {
+            None
+        };
+        let password = if flags & PASSWORD_FLAG != 0 {
+            Some(read_bytes(body)?)
+        } else {
+            None
+        };
+        Ok(MqttConnect {
+            client_id,
+            keep_alive,
+            clean_session: flags & CLEAN_SESSION != 0,
+            will,
+            user_name,
+            password,
+        })
+    }
+}
+
+///MQTT 3.1.1 control packets needed for QoS 0 messaging
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MqttPacket {
+    Connect(MqttConnect),
+    ConnAck {
+        session_present: bool,
+        return_code: u8,
+    },
+    Publish(MqttPublish),
+    ///Topic filters with the requested QoS
+    Subscribe {
+        packet_id: u16,
+        filters: Vec<(String, u8)>,
+    },
+    ///Granted QoS or [MQTT_SUBSCRIPTION_FAILURE] per topic filter
+    SubAck {
+        packet_id: u16,
+        return_codes: Vec<u8>,
+    },
+    PingReq,
+    PingResp,
+    Disconnect,
+}
+
+impl WriteTo for MqttPacket {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        let mut body: Vec<u8> = Vec::new();
+        let first = match self {
+            MqttPacket::Connect(connect) => {
+                connect.write_body(&mut body)?;
+                CONNECT << 4
+            }
+            MqttPacket::ConnAck {
+                session_present,
+                return_code,
+            } => {
+                body.write_u8(*session_present as u8)?;
+                body.write_u8(*return_code)?;
+                CONNACK << 4
+            }
+            MqttPacket::Publish(publish) => {
+                write_string(&mut body, &publish.topic)?;
+                body.write_all(&publish.payload)?;
+                PUBLISH << 4 | publish.retain as u8
+            }
+            MqttPacket::Subscribe { packet_id, filters } => {
+                body.write_u16::<BigEndian>(*packet_id)?;
+                for (filter, qos) in filters {
+                    write_string(&mut body, filter)?;
+                    body.write_u8(*qos)?;
+                }
+                SUBSCRIBE << 4 | SUBSCRIBE_FLAGS
+            }
+            MqttPacket::SubAck {
+                packet_id,
+                return_codes,
+            } => {
+                body.write_u16::<BigEndian>(*packet_id)?;
+                body.write_all(return_codes)?;
+                SUBACK << 4
+            }
+            MqttPacket::PingReq => PINGREQ << 4,
+            MqttPacket::PingResp => PINGRESP << 4,
+            MqttPacket::Disconnect => DISCONNECT << 4,
+        };
+        wtr.write_u8(first)?;
+        write_remaining_length(&mut wtr, body.len())?;
+        wtr.write_all(&body)
+    }
+}
+
+impl ReadFrom for MqttPacket {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let first = read.read_u8()?;
+        let mut body = vec![0; read_remaining_length(read)?];
+        read.read_exact(&mut body)?;
+        let mut body = body.as_slice();
+        let packet = match first >> 4 {
+            CONNECT => MqttPacket::Connect(MqttConnect::read_body(&mut body)?),
+            CONNACK => MqttPacket::ConnAck {
+                session_present: body.read_u8()? & 1 == 1,
+                return_code: body.read_u8()?,
+            },
+            PUBLISH => {
+                if first & QOS_MASK != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "only MQTT QoS 0 is supported",
+                    ));
+                }
+                let topic = read_string(&mut body)?;
+                MqttPacket::Publish(MqttPublish::new(&topic, body.to_vec(), first & RETAIN != 0))
+            }
+            SUBSCRIBE => {
+                let packet_id = body.read_u16::<BigEndian>()?;
+                let mut filters = Vec::new();
+                while !body.is_empty() {
+                    let filter = read_string(&mut body)?;
+                    filters.push((filter, body.read_u8()?));
+                }
+                MqttPacket::Subscribe { packet_id, filters }
+            }
+            SUBACK => MqttPacket::SubAck {
+                packet_id: body.read_u16::<BigEndian>()?,
+                return_codes: body.to_vec(),
+            },
+            PINGREQ => MqttPacket::PingReq,
+            PINGRESP => MqttPacket::PingResp,
+            DISCONNECT => MqttPacket::Disconnect,
+            packet_type => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported MQTT packet type {}", packet_type),
+                ))
+            }
+        };
+        Ok(packet)
+    }
+}
+
+///Length of the first packet in `buffer` if it was received completely
+pub fn packet_len(buffer: &[u8]) -> io::Result<Option<usize>> {
+    let mut remaining = 0;
+    for (i, byte) in buffer.iter().skip(1).take(4).enumerate() {
+        remaining += ((byte & 0x7F) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            let len = 2 + i + remaining;
+            return Ok((buffer.len() >= len).then_some(len));
+        }
+    }
+    if buffer.len() > 4 {
+        return Err(invalid_remaining_length());
+    }
+    Ok(None)
+}
+
+///Whether the topic matches the filter with the wildcards `+` (one level) and `#` (all remaining levels)
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    for level in filter.split('/') {
+        match level {
+            "#" => return true,
+            "+" => {
+                if levels.next().is_none() {
+                    return false;
+                }
+            }
+            _ => {
+                if levels.next() != Some(level) {
+                    return false;
+                }
+            }
+        }
+    }
+    levels.next().is_none()
+}
+
+fn invalid_remaining_length() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "invalid MQTT remaining length")
+}
+
+fn write_remaining_length<W: Write>(wtr: &mut W, mut len: usize) -> io::Result<()> {
+    if len > MQTT_MAX_REMAINING_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("MQTT packet too large ({} bytes)", len),
+        ));
+    }
+    loop {
+        let byte = (len % 128) as u8;
+        len /= 128;
+        if len == 0 {
+            return wtr.write_u8(byte);
+        }
+        wtr.write_u8(byte | 0x80)?;
+    }
+}
+
+fn read_remaining_length<R: Read>(read: &mut R) -> io::Result<usize> {
+    let mut len = 0;
+    for i in 0..4 {
+        let byte = read.read_u8()?;
+        len += ((byte & 0x7F) as usize) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(len);
+        }
+    }
+    Err(invalid_remaining_length())
+}
+
+fn write_bytes(body: &mut Vec<u8>, data: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(data.len()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "MQTT string longer than 65535 bytes",
+        )
+    })?;
+    body.write_u16::<BigEndian>(len)?;
+    body.write_all(data)
+}
+
+fn write_string(body: &mut Vec<u8>, value: &str) -> io::Result<()> {
+    write_bytes(body, value.as_bytes())
+}
+
+fn read_bytes(body: &mut &[u8]) -> io::Result<Vec<u8>> {
+    let mut data = vec![0; body.read_u16::<BigEndian>()? as usize];
+    body.read_exact(&mut data)?;
+    Ok(data)
+}
+
+fn read_string(body: &mut &[u8]) -> io::Result<String> {
+    String::from_utf8(read_bytes(body)?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(packet: MqttPacket) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        packet.write_to(&mut buffer).unwrap();
+        assert_eq!(packet_len(&buffer).unwrap(), Some(buffer.len()));
+        assert_eq!(packet_len(&buffer[..buffer.len() - 1]).unwrap(), None);
+        assert_eq!(
+            MqttPacket::read_from(&mut buffer.as_slice()).unwrap(),
+            packet
+        );
+        buffer
+    }
+
+    #[test]
+    fn mqtt_packet_test() {
+        let mut connect = MqttConnect::new("ads", 30).with_credentials("user", "secret");
+        connect.will = Some(MqttPublish::new("a/b", vec![1, 2], true));
+        round_trip(MqttPacket::Connect(connect));
+        round_trip(MqttPacket::ConnAck {
+            session_present: false,
+            return_code: MQTT_CONNECTION_ACCEPTED,
+        });
+        round_trip(MqttPacket::Subscribe {
+            packet_id: 1,
+            filters: vec![("a/#".to_string(), 0), ("+/b".to_string(), 0)],
+        });
+        round_trip(MqttPacket::SubAck {
+            packet_id: 1,
+            return_codes: vec![0, MQTT_SUBSCRIPTION_FAILURE],
+        });
+        assert_eq!(round_trip(MqttPacket::PingReq), vec![0xC0, 0]);
+        assert_eq!(round_trip(MqttPacket::Disconnect), vec![0xE0, 0]);
+
+        //Remaining length with 2 bytes
+        let buffer = round_trip(MqttPacket::Publish(MqttPublish::new(
+            "t",
+            vec![7; 200],
+            true,
+        )));
+        assert_eq!(buffer[..6], [0x31, 0xCB, 0x01, 0, 1, b't']);
+    }
+
+    #[test]
+    fn mqtt_packet_invalid_test() {
+        //QoS 1 publish
+        let data = [0x32, 5, 0, 1, b't', 0, 1];
+        assert!(MqttPacket::read_from(&mut data.as_slice()).is_err());
+        assert!(packet_len(&[0x30, 0xFF, 0xFF, 0xFF, 0xFF, 0]).is_err());
+        assert!(MqttPacket::read_from(&mut [0xF0, 0].as_slice()).is_err());
+    }
+
+    #[test]
+    fn topic_matches_test() {
+        assert!(topic_matches("a/b", "a/b"));
+        assert!(!topic_matches("a/b", "a/b/c"));
+        assert!(topic_matches("a/+/c", "a/b/c"));
+        assert!(!topic_matches("a/+", "a"));
+        assert!(topic_matches("a/#", "a"));
+        assert!(topic_matches("a/#", "a/b/c"));
+        assert!(!topic_matches("a/#", "b/c"));
+        assert!(topic_matches("+/+/info", "net/1.2.3.4.1.1/info"));
+    }
+}
@@ -46,6 +46,17 @@ impl AdsTransMode {
     pub fn as_u32(&self) -> u32 {
         *self as u32
     }
+
+    ///ClientCylcle and ClientOnChange are handled by the client (ADS dll) and are not supported by an ADS server
+    pub fn is_server_mode(&self) -> bool {
+        matches!(
+            self,
+            AdsTransMode::Cyclic
+                | AdsTransMode::OnChange
+                | AdsTransMode::CyclicInContext
+                | AdsTransMode::OnChangeInContext
+        )
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +85,17 @@ mod tests {
         assert_eq!(6, AdsTransMode::OnChangeInContext.as_u32());
     }
 
+    #[test]
+    fn ads_trans_mode_is_server_mode_test() {
+        assert!(!AdsTransMode::None.is_server_mode());
+        assert!(!AdsTransMode::ClientCylcle.is_server_mode());
+        assert!(!AdsTransMode::ClientOnChange.is_server_mode());
+        assert!(AdsTransMode::Cyclic.is_server_mode());
+        assert!(AdsTransMode::OnChange.is_server_mode());
+        assert!(AdsTransMode::CyclicInContext.is_server_mode());
+        assert!(AdsTransMode::OnChangeInContext.is_server_mode());
+    }
+
     #[test]
     fn ads_trans_mode_write_to_test() {
         let compare: [u8; 4] = [3, 0, 0, 0];
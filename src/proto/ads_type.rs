@@ -0,0 +1,184 @@
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///PLC types with a fixed size in bytes.
+///The values can be read from and written to the raw ADS data with ReadFrom/WriteTo.
+/// ```
+/// use ads_proto::proto::ads_type::AdsType;
+///
+/// assert_eq!(u32::SIZE, 4);
+/// assert_eq!(u32::from_bytes(&[1, 0, 0, 0]).unwrap(), 1);
+/// assert_eq!(1.0f32.to_bytes(), vec![0, 0, 128, 63]);
+/// ```
+pub trait AdsType: ReadFrom + WriteTo {
+    ///Size in bytes of the PLC type
+    const SIZE: u32;
+
+    fn from_bytes(mut data: &[u8]) -> io::Result<Self> {
+        Self::read_from(&mut data)
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::with_capacity(Self::SIZE as usize);
+        self.write_to(&mut buffer)
+            .expect("failed to write value to buffer!");
+        buffer
+    }
+}
+
+//BOOL
+impl ReadFrom for bool {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        Ok(read.read_u8()? != 0)
+    }
+}
+
+impl WriteTo for bool {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u8(*self as u8)
+    }
+}
+
+impl AdsType for bool {
+    const SIZE: u32 = 1;
+}
+
+//BYTE, USINT
+impl ReadFrom for u8 {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        read.read_u8()
+    }
+}
+
+impl WriteTo for u8 {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u8(*self)
+    }
+}
+
+impl AdsType for u8 {
+    const SIZE: u32 = 1;
+}
+
+//SINT
+impl ReadFrom for i8 {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        read.read_i8()
+    }
+}
+
+impl WriteTo for i8 {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_i8(*self)
+    }
+}
+
+impl AdsType for i8 {
+    const SIZE: u32 = 1;
+}
+
+macro_rules! impl_ads_type {
+    ($t:ty, $read:ident, $write:ident, $size:expr) => {
+        impl ReadFrom for $t {
+            fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+                read.$read::<LittleEndian>()
+            }
+        }
+
+        impl WriteTo for $t {
+            fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+                wtr.$write::<LittleEndian>(*self)
+            }
+        }
+
+        impl AdsType for $t {
+            const SIZE: u32 = $size;
+        }
+    };
+}
+
+//WORD, UINT
+impl_ads_type!(u16, read_u16, write_u16, 2);
+//INT
+impl_ads_type!(i16, read_i16, write_i16, 2);
+//DWORD, UDINT
+impl_ads_type!(u32, read_u32, write_u32, 4);
+//DINT
+impl_ads_type!(i32, read_i32, write_i32, 4);
+//LWORD, ULINT
+impl_ads_type!(u64, read_u64, write_u64, 8);
+//LINT
+impl_ads_type!(i64, read_i64, write_i64, 8);
+//REAL
+impl_ads_type!(f32, read_f32, write_f32, 4);
+//LREAL
+impl_ads_type!(f64, read_f64, write_f64, 8);
+
+///Raw data. Reads all remaining bytes.
+impl ReadFrom for Vec<u8> {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let mut data: Vec<u8> = Vec::new();
+        read.read_to_end(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl WriteTo for Vec<u8> {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_all(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ads_type_size_test() {
+        assert_eq!(bool::SIZE, 1);
+        assert_eq!(u8::SIZE, 1);
+        assert_eq!(i8::SIZE, 1);
+        assert_eq!(u16::SIZE, 2);
+        assert_eq!(i16::SIZE, 2);
+        assert_eq!(u32::SIZE, 4);
+        assert_eq!(i32::SIZE, 4);
+        assert_eq!(u64::SIZE, 8);
+        assert_eq!(i64::SIZE, 8);
+        assert_eq!(f32::SIZE, 4);
+        assert_eq!(f64::SIZE, 8);
+    }
+
+    #[test]
+    fn ads_type_from_bytes_test() {
+        assert!(bool::from_bytes(&[1]).unwrap());
+        assert!(!bool::from_bytes(&[0]).unwrap());
+        assert_eq!(i8::from_bytes(&[255]).unwrap(), -1);
+        assert_eq!(u16::from_bytes(&[1, 1]).unwrap(), 257);
+        assert_eq!(i32::from_bytes(&[254, 255, 255, 255]).unwrap(), -2);
+        assert_eq!(f64::from_bytes(&2.5f64.to_le_bytes()).unwrap(), 2.5);
+        assert!(u32::from_bytes(&[1, 0]).is_err());
+    }
+
+    #[test]
+    fn ads_type_to_bytes_test() {
+        assert_eq!(true.to_bytes(), vec![1]);
+        assert_eq!(258u16.to_bytes(), vec![2, 1]);
+        assert_eq!(
+            (-2i64).to_bytes(),
+            vec![254, 255, 255, 255, 255, 255, 255, 255]
+        );
+        assert_eq!(1.0f32.to_bytes(), vec![0, 0, 128, 63]);
+    }
+
+    #[test]
+    fn raw_data_read_write_test() {
+        let data: Vec<u8> = vec![1, 2, 3];
+        let value = Vec::<u8>::read_from(&mut data.as_slice()).unwrap();
+        assert_eq!(value, data);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        value.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, data);
+    }
+}
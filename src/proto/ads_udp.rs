@@ -0,0 +1,590 @@
+use crate::ads_services::system_services::SYSTEM_SERVICE_PORT;
+use crate::error::AdsError;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///UDP port of the TwinCAT system service (discovery, add route)
+pub const ADS_UDP_PORT: u16 = 48899;
+///First 4 bytes of every UDP message
+pub const ADS_UDP_MAGIC: u32 = 0x7114_6603;
+
+///Service: identify (discovery)
+pub const ADS_UDP_SERVICE_IDENTIFY: u32 = 1;
+///Service: add a route on the remote router
+pub const ADS_UDP_SERVICE_ADD_ROUTE: u32 = 6;
+///Set in the service id of responses
+pub const ADS_UDP_SERVICE_RESPONSE: u32 = 0x8000_0000;
+
+pub const ADS_UDP_TAG_STATUS: u16 = 1;
+pub const ADS_UDP_TAG_PASSWORD: u16 = 2;
+pub const ADS_UDP_TAG_TC_VERSION: u16 = 3;
+pub const ADS_UDP_TAG_OS_VERSION: u16 = 4;
+pub const ADS_UDP_TAG_COMPUTER_NAME: u16 = 5;
+pub const ADS_UDP_TAG_NET_ID: u16 = 7;
+pub const ADS_UDP_TAG_OPTIONS: u16 = 9;
+pub const ADS_UDP_TAG_ROUTE_NAME: u16 = 12;
+pub const ADS_UDP_TAG_USER_NAME: u16 = 13;
+pub const ADS_UDP_TAG_FINGERPRINT: u16 = 18;
+
+///Value of the options tag for a temporary route (removed when the remote router restarts)
+pub const ADS_UDP_ROUTE_TEMPORARY: u32 = 1;
+
+///Tag (id, length, data) of an UDP message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdsUdpTag {
+    pub id: u16,
+    pub data: Vec<u8>,
+}
+
+impl AdsUdpTag {
+    pub fn new(id: u16, data: Vec<u8>) -> Self {
+        AdsUdpTag { id, data }
+    }
+
+    ///Null terminated string tag
+    pub fn string(id: u16, value: &str) -> Self {
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        AdsUdpTag::new(id, data)
+    }
+
+    ///Data up to the first null byte as string
+    pub fn as_string(&self) -> String {
+        let end = self
+            .data
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(self.data.len());
+        String::from_utf8_lossy(&self.data[..end]).into_owned()
+    }
+
+    pub fn u32(id: u16, value: u32) -> Self {
+        AdsUdpTag::new(id, value.to_le_bytes().to_vec())
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        self.data.as_slice().read_u32::<LittleEndian>().ok()
+    }
+}
+
+///Message of the TwinCAT UDP system service (port 48899).
+///Magic, invoke id, service id, AmsAddress of the sender, tag count and the tags.
+/// ```
+/// use ads_proto::proto::ads_udp::*;
+/// use ads_proto::proto::proto_traits::{ReadFrom, WriteTo};
+///
+/// let request = AdsUdpMessage::identify_request(1);
+/// let mut buffer: Vec<u8> = Vec::new();
+/// request.write_to(&mut buffer).unwrap();
+/// assert_eq!(buffer.len(), 24);
+/// assert_eq!(AdsUdpMessage::read_from(&mut buffer.as_slice()).unwrap(), request);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdsUdpMessage {
+    pub invoke_id: u32,
+    pub service: u32,
+    pub address: AmsAddress,
+    pub tags: Vec<AdsUdpTag>,
+}
+
+impl AdsUdpMessage {
+    pub fn new(invoke_id: u32, service: u32, address: AmsAddress, tags: Vec<AdsUdpTag>) -> Self {
+        AdsUdpMessage {
+            invoke_id,
+            service,
+            address,
+            tags,
+        }
+    }
+
+    ///Broadcast search request. The sender address is not evaluated by the devices.
+    pub fn identify_request(invoke_id: u32) -> Self {
+        AdsUdpMessage::new(
+            invoke_id,
+            ADS_UDP_SERVICE_IDENTIFY,
+            AmsAddress::new(AmsNetId::from([0; 6]), SYSTEM_SERVICE_PORT),
+            Vec::new(),
+        )
+    }
+
+    pub fn is_response(&self) -> bool {
+        self.service & ADS_UDP_SERVICE_RESPONSE != 0
+    }
+
+    ///Service id without the response flag
+    pub fn service_id(&self) -> u32 {
+        self.service & !ADS_UDP_SERVICE_RESPONSE
+    }
+
+    ///First tag with the id
+    pub fn tag(&self, id: u16) -> Option<&AdsUdpTag> {
+        self.tags.iter().find(|t| t.id == id)
+    }
+
+    pub fn message_len(&self) -> usize {
+        24 + self.tags.iter().map(|t| 4 + t.data.len()).sum::<usize>()
+    }
+}
+
+impl WriteTo for AdsUdpMessage {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(ADS_UDP_MAGIC)?;
+        wtr.write_u32::<LittleEndian>(self.invoke_id)?;
+        wtr.write_u32::<LittleEndian>(self.service)?;
+        self.address.write_to(&mut wtr)?;
+        wtr.write_u32::<LittleEndian>(self.tags.len() as u32)?;
+        for tag in &self.tags {
+            let length = u16::try_from(tag.data.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("tag {} with {} bytes is too long", tag.id, tag.data.len()),
+                )
+            })?;
+            wtr.write_u16::<LittleEndian>(tag.id)?;
+            wtr.write_u16::<LittleEndian>(length)?;
+            wtr.write_all(&tag.data)?;
+        }
+        Ok(())
+    }
+}
+
+impl ReadFrom for AdsUdpMessage {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let magic = read.read_u32::<LittleEndian>()?;
+        if magic != ADS_UDP_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no ADS UDP message (magic {:#010x})", magic),
+            ));
+        }
+        let invoke_id = read.read_u32::<LittleEndian>()?;
+        let service = read.read_u32::<LittleEndian>()?;
+        let address = AmsAddress::read_from(read)?;
+        let count = read.read_u32::<LittleEndian>()?;
+        let mut tags = Vec::new();
+        for _ in 0..count {
+            let id = read.read_u16::<LittleEndian>()?;
+            let mut data = vec![0; read.read_u16::<LittleEndian>()? as usize];
+            read.read_exact(&mut data)?;
+            tags.push(AdsUdpTag::new(id, data));
+        }
+        Ok(AdsUdpMessage::new(invoke_id, service, address, tags))
+    }
+}
+
+///TwinCAT version of the identify response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TcVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub build: u16,
+}
+
+impl TcVersion {
+    pub fn new(major: u8, minor: u8, build: u16) -> Self {
+        TcVersion {
+            major,
+            minor,
+            build,
+        }
+    }
+}
+
+impl WriteTo for TcVersion {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u8(self.major)?;
+        wtr.write_u8(self.minor)?;
+        wtr.write_u16::<LittleEndian>(self.build)
+    }
+}
+
+impl ReadFrom for TcVersion {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        Ok(TcVersion {
+            major: read.read_u8()?,
+            minor: read.read_u8()?,
+            build: read.read_u16::<LittleEndian>()?,
+        })
+    }
+}
+
+///OS version of the identify response (Windows OSVERSIONINFO layout, also sent by TC/BSD)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+    pub platform: u32,
+    ///Service pack or OS name, UTF-16 in the message
+    pub description: String,
+}
+
+impl OsVersion {
+    pub fn new(major: u32, minor: u32, build: u32, platform: u32, description: &str) -> Self {
+        OsVersion {
+            major,
+            minor,
+            build,
+            platform,
+            description: description.to_string(),
+        }
+    }
+}
+
+impl WriteTo for OsVersion {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        let description: Vec<u16> = self.description.encode_utf16().chain([0]).collect();
+        wtr.write_u32::<LittleEndian>(20 + description.len() as u32 * 2)?;
+        wtr.write_u32::<LittleEndian>(self.major)?;
+        wtr.write_u32::<LittleEndian>(self.minor)?;
+        wtr.write_u32::<LittleEndian>(self.build)?;
+        wtr.write_u32::<LittleEndian>(self.platform)?;
+        for c in description {
+            wtr.write_u16::<LittleEndian>(c)?;
+        }
+        Ok(())
+    }
+}
+
+impl ReadFrom for OsVersion {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let _size = read.read_u32::<LittleEndian>()?;
+        let major = read.read_u32::<LittleEndian>()?;
+        let minor = read.read_u32::<LittleEndian>()?;
+        let build = read.read_u32::<LittleEndian>()?;
+        let platform = read.read_u32::<LittleEndian>()?;
+        let mut description: Vec<u16> = Vec::new();
+        while let Ok(c) = read.read_u16::<LittleEndian>() {
+            if c == 0 {
+                break;
+            }
+            description.push(c);
+        }
+        Ok(OsVersion {
+            major,
+            minor,
+            build,
+            platform,
+            description: String::from_utf16_lossy(&description),
+        })
+    }
+}
+
+///Device information of an identify response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceIdentity {
+    pub net_id: AmsNetId,
+    pub host_name: String,
+    pub tc_version: Option<TcVersion>,
+    pub os_version: Option<OsVersion>,
+}
+
+impl DeviceIdentity {
+    pub fn new(net_id: AmsNetId, host_name: &str) -> Self {
+        DeviceIdentity {
+            net_id,
+            host_name: host_name.to_string(),
+            tc_version: None,
+            os_version: None,
+        }
+    }
+
+    ///Parse an identify response. Tags which are not present or can't be parsed are None.
+    pub fn from_response(message: &AdsUdpMessage) -> io::Result<Self> {
+        if !message.is_response() || message.service_id() != ADS_UDP_SERVICE_IDENTIFY {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no identify response (service {:#010x})", message.service),
+            ));
+        }
+        Ok(DeviceIdentity {
+            net_id: message.address.ams_net_id.clone(),
+            host_name: message
+                .tag(ADS_UDP_TAG_COMPUTER_NAME)
+                .map(|t| t.as_string())
+                .unwrap_or_default(),
+            tc_version: message
+                .tag(ADS_UDP_TAG_TC_VERSION)
+                .and_then(|t| TcVersion::read_from(&mut t.data.as_slice()).ok()),
+            os_version: message
+                .tag(ADS_UDP_TAG_OS_VERSION)
+                .and_then(|t| OsVersion::read_from(&mut t.data.as_slice()).ok()),
+        })
+    }
+
+    ///Identify response to the request with the invoke id
+    pub fn response(&self, invoke_id: u32) -> AdsUdpMessage {
+        let mut tags = vec![AdsUdpTag::string(
+            ADS_UDP_TAG_COMPUTER_NAME,
+            &self.host_name,
+        )];
+        if let Some(tc_version) = &self.tc_version {
+            let mut data: Vec<u8> = Vec::with_capacity(4);
+            tc_version
+                .write_to(&mut data)
+                .expect("failed to write to buffer!");
+            tags.push(AdsUdpTag::new(ADS_UDP_TAG_TC_VERSION, data));
+        }
+        if let Some(os_version) = &self.os_version {
+            let mut data: Vec<u8> = Vec::new();
+            os_version
+                .write_to(&mut data)
+                .expect("failed to write to buffer!");
+            tags.push(AdsUdpTag::new(ADS_UDP_TAG_OS_VERSION, data));
+        }
+        AdsUdpMessage::new(
+            invoke_id,
+            ADS_UDP_SERVICE_IDENTIFY | ADS_UDP_SERVICE_RESPONSE,
+            AmsAddress::new(self.net_id.clone(), SYSTEM_SERVICE_PORT),
+            tags,
+        )
+    }
+}
+
+///Add a route to the sender on the remote router.
+///The credentials are the ones of a user of the remote system (e.g. Administrator).
+/// ```
+/// use ads_proto::proto::ads_udp::*;
+/// use ads_proto::proto::ams_address::AmsNetId;
+///
+/// let request = AddRouteRequest::new(
+///     "provisioning",
+///     AmsNetId::new(192, 168, 1, 10, 1, 1),
+///     "192.168.1.10",
+///     "Administrator",
+///     "1",
+/// );
+/// let message = request.message(1);
+/// assert_eq!(message.service, ADS_UDP_SERVICE_ADD_ROUTE);
+/// assert_eq!(AddRouteRequest::from_message(&message).unwrap(), request);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddRouteRequest {
+    pub route_name: String,
+    ///AmsNetId of the sender. The route on the remote router points to this AmsNetId.
+    pub net_id: AmsNetId,
+    ///Ip address or host name of the sender
+    pub host: String,
+    pub user_name: String,
+    pub password: String,
+    ///Temporary routes are removed when the remote router restarts
+    pub temporary: bool,
+}
+
+impl AddRouteRequest {
+    pub fn new(
+        route_name: &str,
+        net_id: AmsNetId,
+        host: &str,
+        user_name: &str,
+        password: &str,
+    ) -> Self {
+        AddRouteRequest {
+            route_name: route_name.to_string(),
+            net_id,
+            host: host.to_string(),
+            user_name: user_name.to_string(),
+            password: password.to_string(),
+            temporary: false,
+        }
+    }
+
+    pub fn message(&self, invoke_id: u32) -> AdsUdpMessage {
+        let mut tags = vec![
+            AdsUdpTag::string(ADS_UDP_TAG_ROUTE_NAME, &self.route_name),
+            AdsUdpTag::new(ADS_UDP_TAG_NET_ID, self.net_id.net_id().to_vec()),
+            AdsUdpTag::string(ADS_UDP_TAG_USER_NAME, &self.user_name),
+            AdsUdpTag::string(ADS_UDP_TAG_PASSWORD, &self.password),
+            AdsUdpTag::string(ADS_UDP_TAG_COMPUTER_NAME, &self.host),
+        ];
+        if self.temporary {
+            tags.push(AdsUdpTag::u32(ADS_UDP_TAG_OPTIONS, ADS_UDP_ROUTE_TEMPORARY));
+        }
+        AdsUdpMessage::new(
+            invoke_id,
+            ADS_UDP_SERVICE_ADD_ROUTE,
+            AmsAddress::new(self.net_id.clone(), SYSTEM_SERVICE_PORT),
+            tags,
+        )
+    }
+
+    ///Parse an add route request (remote router side)
+    pub fn from_message(message: &AdsUdpMessage) -> io::Result<Self> {
+        if message.service != ADS_UDP_SERVICE_ADD_ROUTE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no add route request (service {:#010x})", message.service),
+            ));
+        }
+        let string = |id| message.tag(id).map(|t| t.as_string()).unwrap_or_default();
+        let net_id = match message.tag(ADS_UDP_TAG_NET_ID) {
+            Some(tag) => AmsNetId::read_from(&mut tag.data.as_slice())?,
+            None => message.address.ams_net_id.clone(),
+        };
+        Ok(AddRouteRequest {
+            route_name: string(ADS_UDP_TAG_ROUTE_NAME),
+            net_id,
+            host: string(ADS_UDP_TAG_COMPUTER_NAME),
+            user_name: string(ADS_UDP_TAG_USER_NAME),
+            password: string(ADS_UDP_TAG_PASSWORD),
+            temporary: message
+                .tag(ADS_UDP_TAG_OPTIONS)
+                .and_then(|t| t.as_u32())
+                .is_some_and(|o| o & ADS_UDP_ROUTE_TEMPORARY != 0),
+        })
+    }
+}
+
+///Acknowledgement of an add route request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddRouteResponse {
+    ///AmsNetId of the remote router
+    pub net_id: AmsNetId,
+    pub result: AdsError,
+}
+
+impl AddRouteResponse {
+    pub fn new(net_id: AmsNetId, result: AdsError) -> Self {
+        AddRouteResponse { net_id, result }
+    }
+
+    ///Parse the acknowledgement. A missing status tag is treated as success.
+    pub fn from_message(message: &AdsUdpMessage) -> io::Result<Self> {
+        if !message.is_response() || message.service_id() != ADS_UDP_SERVICE_ADD_ROUTE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("no add route response (service {:#010x})", message.service),
+            ));
+        }
+        let status = match message.tag(ADS_UDP_TAG_STATUS) {
+            Some(tag) => tag.as_u32().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, "invalid add route status")
+            })?,
+            None => 0,
+        };
+        Ok(AddRouteResponse {
+            net_id: message.address.ams_net_id.clone(),
+            result: AdsError::from(status),
+        })
+    }
+
+    pub fn message(&self, invoke_id: u32) -> AdsUdpMessage {
+        AdsUdpMessage::new(
+            invoke_id,
+            ADS_UDP_SERVICE_ADD_ROUTE | ADS_UDP_SERVICE_RESPONSE,
+            AmsAddress::new(self.net_id.clone(), SYSTEM_SERVICE_PORT),
+            vec![AdsUdpTag::u32(ADS_UDP_TAG_STATUS, self.result.as_u32())],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ads_udp_identify_request_test() {
+        let mut buffer: Vec<u8> = Vec::new();
+        AdsUdpMessage::identify_request(5)
+            .write_to(&mut buffer)
+            .unwrap();
+        #[rustfmt::skip]
+        let expected: Vec<u8> = vec![
+            0x03, 0x66, 0x14, 0x71,
+            5, 0, 0, 0,
+            1, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0x10, 0x27,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(buffer, expected);
+
+        buffer[0] = 0;
+        assert!(AdsUdpMessage::read_from(&mut buffer.as_slice()).is_err());
+    }
+
+    #[test]
+    fn ads_udp_identify_response_test() {
+        let mut identity = DeviceIdentity::new(AmsNetId::new(5, 1, 2, 3, 1, 1), "CX-1234");
+        identity.tc_version = Some(TcVersion::new(3, 1, 4024));
+        identity.os_version = Some(OsVersion::new(10, 0, 19044, 2, "LTSC"));
+
+        let message = identity.response(9);
+        assert!(message.is_response());
+        assert_eq!(message.service_id(), ADS_UDP_SERVICE_IDENTIFY);
+        let mut buffer: Vec<u8> = Vec::new();
+        message.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), message.message_len());
+        //TcVersion tag
+        assert!(buffer
+            .windows(8)
+            .any(|w| w == [3, 0, 4, 0, 3, 1, 0xB8, 0x0F]));
+
+        let message = AdsUdpMessage::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(message.invoke_id, 9);
+        assert_eq!(DeviceIdentity::from_response(&message).unwrap(), identity);
+
+        //Request is no response
+        assert!(DeviceIdentity::from_response(&AdsUdpMessage::identify_request(1)).is_err());
+    }
+
+    #[test]
+    fn ads_udp_add_route_test() {
+        let mut request = AddRouteRequest::new(
+            "linux-host",
+            AmsNetId::new(192, 168, 1, 10, 1, 1),
+            "192.168.1.10",
+            "Administrator",
+            "1",
+        );
+        request.temporary = true;
+        let mut buffer: Vec<u8> = Vec::new();
+        request.message(2).write_to(&mut buffer).unwrap();
+        #[rustfmt::skip]
+        let header: Vec<u8> = vec![
+            0x03, 0x66, 0x14, 0x71,
+            2, 0, 0, 0,
+            6, 0, 0, 0,
+            192, 168, 1, 10, 1, 1, 0x10, 0x27,
+            6, 0, 0, 0,
+            12, 0, 11, 0,
+        ];
+        assert_eq!(buffer[..28], header[..]);
+        assert!(buffer.ends_with(&[9, 0, 4, 0, 1, 0, 0, 0]));
+
+        let message = AdsUdpMessage::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(AddRouteRequest::from_message(&message).unwrap(), request);
+
+        let response = AddRouteResponse::new(
+            AmsNetId::new(5, 1, 2, 3, 1, 1),
+            AdsError::AdsErrDeviceInvalidAccess,
+        );
+        let message = response.message(2);
+        assert_eq!(message.service, 0x8000_0006);
+        assert_eq!(
+            message.tag(ADS_UDP_TAG_STATUS).unwrap().as_u32(),
+            Some(0x704)
+        );
+        assert_eq!(AddRouteResponse::from_message(&message).unwrap(), response);
+        assert!(AddRouteResponse::from_message(&request.message(2)).is_err());
+    }
+
+    #[test]
+    fn ads_udp_tag_test() {
+        let tag = AdsUdpTag::string(ADS_UDP_TAG_COMPUTER_NAME, "PLC");
+        assert_eq!(tag.data, vec![b'P', b'L', b'C', 0]);
+        assert_eq!(tag.as_string(), "PLC");
+        assert_eq!(AdsUdpTag::new(1, vec![4, 0, 0, 0]).as_u32(), Some(4));
+        assert_eq!(AdsUdpTag::new(1, vec![4]).as_u32(), None);
+
+        let message = AdsUdpMessage::new(
+            1,
+            ADS_UDP_SERVICE_IDENTIFY,
+            AmsAddress::new(AmsNetId::from([0; 6]), SYSTEM_SERVICE_PORT),
+            vec![AdsUdpTag::new(1, vec![0; 0x1_0000])],
+        );
+        let error = message.write_to(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
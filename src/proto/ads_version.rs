@@ -0,0 +1,135 @@
+use crate::error::AdsVersionError;
+use crate::proto::response::ReadDeviceInfoResponse;
+use std::fmt;
+use std::str::FromStr;
+
+///Version of an ADS device (major.minor.build) as returned by read device info.
+///Versions are ordered by major, minor and build.
+/// ```
+/// use ads_proto::proto::ads_version::AdsVersion;
+///
+/// let version: AdsVersion = "3.1.4024".parse().unwrap();
+/// assert!(version >= AdsVersion::new(3, 1, 4022));
+/// assert!(version.supports_secure_ads());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct AdsVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub build: u16,
+}
+
+impl AdsVersion {
+    ///First version with the sum up commands including READEX2 (TwinCAT 2.11 build 1550)
+    pub const SUMUP_READEX2: AdsVersion = AdsVersion::new(2, 11, 1550);
+    ///First version with Secure ADS (TwinCAT 3.1 build 4024)
+    pub const SECURE_ADS: AdsVersion = AdsVersion::new(3, 1, 4024);
+
+    pub const fn new(major: u8, minor: u8, build: u16) -> Self {
+        AdsVersion {
+            major,
+            minor,
+            build,
+        }
+    }
+
+    pub fn supports_sumup_readex2(&self) -> bool {
+        *self >= AdsVersion::SUMUP_READEX2
+    }
+
+    pub fn supports_secure_ads(&self) -> bool {
+        *self >= AdsVersion::SECURE_ADS
+    }
+}
+
+impl From<&ReadDeviceInfoResponse> for AdsVersion {
+    fn from(response: &ReadDeviceInfoResponse) -> Self {
+        AdsVersion::new(
+            response.major_version,
+            response.minor_version,
+            response.version_build,
+        )
+    }
+}
+
+///Formats as "major.minor.build" which can be parsed again
+impl fmt::Display for AdsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.build)
+    }
+}
+
+impl FromStr for AdsVersion {
+    type Err = AdsVersionError;
+    fn from_str(version: &str) -> Result<AdsVersion, AdsVersionError> {
+        let parts: Vec<&str> = version.trim().split('.').collect();
+        if parts.len() != 3 {
+            return Err(AdsVersionError::InvalidFormat {
+                version: version.to_string(),
+            });
+        }
+        let parse_error = |source| AdsVersionError::ParseError { source };
+        Ok(AdsVersion {
+            major: parts[0].parse().map_err(parse_error)?,
+            minor: parts[1].parse().map_err(parse_error)?,
+            build: parts[2].parse().map_err(parse_error)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AdsError;
+
+    #[test]
+    fn ads_version_parse_test() {
+        let version: AdsVersion = "3.1.4024".parse().unwrap();
+        assert_eq!(version, AdsVersion::new(3, 1, 4024));
+        assert_eq!(version.to_string(), "3.1.4024");
+        assert_eq!(
+            "3.1".parse::<AdsVersion>(),
+            Err(AdsVersionError::InvalidFormat {
+                version: String::from("3.1")
+            })
+        );
+        assert!(matches!(
+            "3.1.x".parse::<AdsVersion>(),
+            Err(AdsVersionError::ParseError { .. })
+        ));
+        assert!("3.1.70000".parse::<AdsVersion>().is_err());
+    }
+
+    #[test]
+    fn ads_version_ord_test() {
+        let mut versions = vec![
+            AdsVersion::new(3, 1, 4024),
+            AdsVersion::new(2, 11, 2300),
+            AdsVersion::new(3, 1, 4022),
+            AdsVersion::new(3, 0, 9000),
+        ];
+        versions.sort();
+        assert_eq!(
+            versions,
+            vec![
+                AdsVersion::new(2, 11, 2300),
+                AdsVersion::new(3, 0, 9000),
+                AdsVersion::new(3, 1, 4022),
+                AdsVersion::new(3, 1, 4024),
+            ]
+        );
+    }
+
+    #[test]
+    fn ads_version_supports_test() {
+        assert!(!AdsVersion::new(2, 10, 1340).supports_sumup_readex2());
+        assert!(AdsVersion::new(2, 11, 2300).supports_sumup_readex2());
+        assert!(!AdsVersion::new(2, 11, 2300).supports_secure_ads());
+        assert!(!AdsVersion::new(3, 1, 4022).supports_secure_ads());
+        assert!(AdsVersion::new(3, 1, 4026).supports_secure_ads());
+
+        let response = ReadDeviceInfoResponse::new(AdsError::ErrNoError, 3, 1, 4024, [0; 16]);
+        assert_eq!(AdsVersion::from(&response), AdsVersion::SECURE_ADS);
+        assert_eq!(response.version(), AdsVersion::SECURE_ADS);
+    }
+}
@@ -1,11 +1,12 @@
 use crate::error::AmsAddressError;
 use crate::proto::proto_traits::{ReadFrom, WriteTo};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fmt;
 use std::io::{self, Read, Write};
-use std::net::SocketAddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct AmsAddress {
     pub ams_net_id: AmsNetId,
     pub port: u16,
@@ -16,17 +17,26 @@ impl AmsAddress {
         AmsAddress { ams_net_id, port }
     }
 
+    ///Takes the IPv4 address of the socket address with the suffix .1.1 as AmsNetId and the port as AMS port.
+    ///IPv6 addresses are only accepted if they are IPv4 mapped.
+    ///Use [RouteTable::ams_address](crate::router::route_table::RouteTable::ams_address) to look up the AmsNetId of a remote router.
     pub fn update_from_socket_addr(
         &mut self,
         socket_addr: SocketAddr,
     ) -> Result<(), AmsAddressError> {
-        let ams_address = AmsAddress::from_str(socket_addr.to_string().as_str())?;
-        self.ams_net_id = ams_address.ams_net_id;
-        self.port = ams_address.port;
+        self.ams_net_id = AmsNetId::from_ip(socket_addr.ip(), AmsNetId::DEFAULT_SUFFIX)?;
+        self.port = socket_addr.port();
         Ok(())
     }
 }
 
+///Formats as "AmsNetId:port" which can be parsed again
+impl fmt::Display for AmsAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ams_net_id, self.port)
+    }
+}
+
 impl WriteTo for AmsAddress {
     fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
         self.ams_net_id.write_to(&mut wtr)?;
@@ -64,7 +74,7 @@ impl FromStr for AmsAddress {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AmsNetId {
     net_id: [u8; 6],
 }
@@ -93,11 +103,8 @@ impl From<[u8; 6]> for AmsNetId {
 impl FromStr for AmsNetId {
     type Err = AmsAddressError;
     fn from_str(net_id: &str) -> Result<AmsNetId, AmsAddressError> {
-        let mut parts: Vec<&str> = net_id.split('.').collect();
-
-        if parts.len() == 4 {
-            parts.append(&mut vec!["1", "1"]);
-        } else if parts.len() != 6 {
+        let parts: Vec<&str> = net_id.split('.').collect();
+        if parts.len() != 6 {
             return Err(AmsAddressError::InvalidAddressLength {
                 length: parts.len(),
             });
@@ -125,6 +132,61 @@ impl AmsNetId {
     pub fn net_id(&self) -> [u8; 6] {
         self.net_id
     }
+
+    ///Suffix used to derive an AmsNetId from an IPv4 address
+    pub const DEFAULT_SUFFIX: [u8; 2] = [1, 1];
+
+    ///AmsNetId from the IPv4 address followed by the suffix (usually [1, 1])
+    pub fn from_ipv4(ip: Ipv4Addr, suffix: [u8; 2]) -> Self {
+        let [a, b, c, d] = ip.octets();
+        AmsNetId::new(a, b, c, d, suffix[0], suffix[1])
+    }
+
+    ///Same as [AmsNetId::from_ipv4]. IPv6 addresses are only accepted if they are IPv4 mapped.
+    pub fn from_ip(ip: IpAddr, suffix: [u8; 2]) -> Result<Self, AmsAddressError> {
+        let ipv4 = match ip {
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(ip) => ip.to_ipv4_mapped(),
+        };
+        ipv4.map(|ip| AmsNetId::from_ipv4(ip, suffix))
+            .ok_or(AmsAddressError::NoIpv4Address { ip })
+    }
+
+    ///The first four bytes as IPv4 address.
+    ///This is only the ip of the host if the AmsNetId was derived from it, which is a convention only.
+    pub fn to_ipv4(&self) -> Ipv4Addr {
+        let [a, b, c, d, _, _] = self.net_id;
+        Ipv4Addr::new(a, b, c, d)
+    }
+
+    ///Derive the local AmsNetId to use towards `peer`:
+    ///the IPv4 address of the local interface the OS routes traffic to `peer` over, with the suffix .1.1.
+    ///No packet is sent. Fails if the peer is not reachable over IPv4.
+    pub fn local_for_peer(peer: IpAddr) -> io::Result<Self> {
+        let unspecified: IpAddr = match peer {
+            IpAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            IpAddr::V6(_) => std::net::Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let socket = UdpSocket::bind((unspecified, 0))?;
+        //Any port. Connecting an udp socket only selects the route
+        socket.connect((peer, 9))?;
+        let local = socket.local_addr()?.ip();
+        if local.is_unspecified() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrNotAvailable,
+                format!("no local address to reach {}", peer),
+            ));
+        }
+        AmsNetId::from_ip(local, AmsNetId::DEFAULT_SUFFIX).map_err(io::Error::other)
+    }
+}
+
+///Formats as "a.b.c.d.e.f" which can be parsed again
+impl fmt::Display for AmsNetId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.net_id;
+        write!(f, "{}.{}.{}.{}.{}.{}", a, b, c, d, e, g)
+    }
 }
 
 #[cfg(test)]
@@ -201,9 +263,65 @@ mod tests {
 
     #[test]
     fn ams_address_from_string_test() {
-        let s = "169.0.0.1:45932";
+        let s = "169.0.0.1.1.1:45932";
         let ams_address = AmsAddress::from_str(s).unwrap();
         assert_eq!(ams_address.ams_net_id.net_id, [169, 0, 0, 1, 1, 1]);
         assert_eq!(ams_address.port, 45932);
+
+        //An ip address is no AmsNetId
+        assert_eq!(
+            AmsAddress::from_str("169.0.0.1:45932").unwrap_err(),
+            AmsAddressError::InvalidAddressLength { length: 4 }
+        );
+    }
+
+    #[test]
+    fn ams_address_display_test() {
+        let ams_address = AmsAddress::new(AmsNetId::new(5, 1, 204, 160, 1, 1), 851);
+        assert_eq!(ams_address.to_string(), "5.1.204.160.1.1:851");
+        assert_eq!(
+            AmsAddress::from_str(&ams_address.to_string()).unwrap(),
+            ams_address
+        );
+        assert_eq!(ams_address.ams_net_id.to_string(), "5.1.204.160.1.1");
+    }
+
+    #[test]
+    fn ams_net_id_ipv4_test() {
+        let ams_net_id = AmsNetId::from_ipv4(Ipv4Addr::new(192, 168, 1, 2), [1, 1]);
+        assert_eq!(ams_net_id, AmsNetId::new(192, 168, 1, 2, 1, 1));
+        assert_eq!(ams_net_id.to_ipv4(), Ipv4Addr::new(192, 168, 1, 2));
+
+        let mapped: IpAddr = "::ffff:192.168.1.2".parse().unwrap();
+        assert_eq!(
+            AmsNetId::from_ip(mapped, [2, 1]).unwrap(),
+            AmsNetId::new(192, 168, 1, 2, 2, 1)
+        );
+        let ipv6: IpAddr = "fe80::1".parse().unwrap();
+        assert_eq!(
+            AmsNetId::from_ip(ipv6, [1, 1]),
+            Err(AmsAddressError::NoIpv4Address { ip: ipv6 })
+        );
+    }
+
+    #[test]
+    fn ams_address_update_from_socket_addr_test() {
+        let mut ams_address = AmsAddress::new(AmsNetId::new(0, 0, 0, 0, 0, 0), 0);
+        ams_address
+            .update_from_socket_addr("10.0.0.5:48898".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            ams_address,
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 5, 1, 1), 48898)
+        );
+        assert!(ams_address
+            .update_from_socket_addr("[fe80::1]:48898".parse().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn ams_net_id_local_for_peer_test() {
+        let ams_net_id = AmsNetId::local_for_peer(Ipv4Addr::LOCALHOST.into()).unwrap();
+        assert_eq!(ams_net_id, AmsNetId::new(127, 0, 0, 1, 1, 1));
     }
 }
@@ -1,5 +1,6 @@
 use crate::error::AdsError;
 use crate::proto::ams_address::AmsAddress;
+use crate::proto::ams_tcp_frame::AMS_TCP_MAX_FRAME_LEN;
 use crate::proto::command_id::CommandID;
 use crate::proto::proto_traits::{Command, ReadFrom, WriteTo};
 use crate::proto::request::*;
@@ -84,6 +85,15 @@ impl ReadFrom for AmsHeader {
         let length = read.read_u32::<LittleEndian>()?;
         let ams_ads_error = AdsError::from(read.read_u32::<LittleEndian>()?);
         let invoke_id = read.read_u32::<LittleEndian>()?;
+        if length > AMS_TCP_MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "AMS data length {} exceeds {}",
+                    length, AMS_TCP_MAX_FRAME_LEN
+                ),
+            ));
+        }
         let mut data: Vec<u8> = vec![0; length as usize];
         read.read_exact(&mut data)?;
 
@@ -129,10 +139,7 @@ impl AmsHeader {
     pub fn response(&mut self) -> io::Result<Response> {
         if self.state_flags.is_response() || self.command_id == CommandID::DeviceNotification {
             match self.command_id {
-                CommandID::Invalid => Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    AdsError::AdsErrDeviceInvalidData,
-                )),
+                CommandID::Invalid => Err(io::Error::other(AdsError::AdsErrDeviceInvalidData)),
                 CommandID::ReadDeviceInfo => Ok(Response::ReadDeviceInfo(
                     ReadDeviceInfoResponse::read_from(&mut self.data.as_slice())?,
                 )),
@@ -162,10 +169,7 @@ impl AmsHeader {
                 )?)),
             }
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Is not a response but a request!",
-            ))
+            Err(io::Error::other("Is not a response but a request!"))
         }
     }
 
@@ -173,10 +177,7 @@ impl AmsHeader {
     pub fn request(&mut self) -> io::Result<Request> {
         if !self.state_flags.is_response() {
             match self.command_id {
-                CommandID::Invalid => Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    AdsError::AdsErrDeviceInvalidData,
-                )),
+                CommandID::Invalid => Err(io::Error::other(AdsError::AdsErrDeviceInvalidData)),
                 CommandID::ReadDeviceInfo => {
                     Ok(Request::ReadDeviceInfo(ReadDeviceInfoRequest::new()))
                 }
@@ -204,13 +205,25 @@ impl AmsHeader {
                 )?)),
             }
         } else {
-            Err(io::Error::new(
-                io::ErrorKind::Other,
-                "Is not a request but a response!",
-            ))
+            Err(io::Error::other("Is not a request but a response!"))
         }
     }
 
+    ///Returns the AmsAddress the header is sent to
+    pub fn target_address(&self) -> &AmsAddress {
+        &self.ams_address_targed
+    }
+
+    ///Returns the AmsAddress the header is sent from
+    pub fn source_address(&self) -> &AmsAddress {
+        &self.ams_address_source
+    }
+
+    ///Returns the state flags from the ams header
+    pub fn state_flags(&self) -> &StateFlags {
+        &self.state_flags
+    }
+
     ///Returns the command id from the ams header
     pub fn command_id(&self) -> CommandID {
         self.command_id
@@ -279,12 +292,39 @@ impl AmsHeader {
     pub fn swap_address(&mut self) {
         swap(&mut self.ams_address_source, &mut self.ams_address_targed);
     }
+
+    /// update the state flags, e.g. to send a header over UDP
+    pub fn update_state_flags(&mut self, state_flags: StateFlags) {
+        self.state_flags = state_flags;
+    }
+
+    ///Encode as UDP datagram. Over UDP the AmsHeader is sent without the AmsTcpHeader prefix.
+    pub fn to_datagram(&self) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::with_capacity(self.header_len() as usize);
+        self.write_to(&mut buffer)
+            .expect("failed to write ams header to buffer!");
+        buffer
+    }
+
+    ///Decode an UDP datagram. The datagram has to contain exactly one AmsHeader.
+    pub fn from_datagram(datagram: &[u8]) -> io::Result<Self> {
+        let mut data = datagram;
+        let header = AmsHeader::read_from(&mut data)?;
+        if !data.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} bytes after the AMS header in datagram", data.len()),
+            ));
+        }
+        Ok(header)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::proto::ams_address::*;
+    use crate::proto::state_flags::NetProto;
     use std::str::FromStr;
     #[test]
     fn ams_header_write_to_test() {
@@ -404,8 +444,8 @@ mod tests {
 
         //After swap
         assert_eq!(
-            ams_header.ams_address_targed,
-            AmsAddress::new(AmsNetId::new(192, 168, 1, 1, 1, 2), port)
+            ams_header.target_address(),
+            &AmsAddress::new(AmsNetId::new(192, 168, 1, 1, 1, 2), port)
         );
         assert_eq!(
             ams_header.ams_address_source,
@@ -712,4 +752,42 @@ mod tests {
             ams_tcp_header.ams_header.response_result()
         );
     }
+
+    #[test]
+    fn ams_header_datagram_test() {
+        let mut ams_header = AmsHeader::new(
+            AmsAddress::new(AmsNetId::new(192, 168, 1, 1, 1, 1), 851),
+            AmsAddress::new(AmsNetId::new(192, 168, 1, 1, 1, 2), 30000),
+            StateFlags::new(false, true, NetProto::Udp),
+            7,
+            Request::Read(ReadRequest::new(259, 259, 4)),
+        );
+        let datagram = ams_header.to_datagram();
+        //No AmsTcpHeader prefix
+        assert_eq!(datagram.len(), 44);
+        assert_eq!(datagram[..6], [192, 168, 1, 1, 1, 1]);
+        assert_eq!(datagram[18..20], [0x44, 0]);
+
+        let mut decoded = AmsHeader::from_datagram(&datagram).unwrap();
+        assert!(!decoded.state_flags().is_tcp());
+        assert_eq!(decoded.invoke_id(), 7);
+        assert_eq!(decoded.request().unwrap(), ams_header.request().unwrap());
+
+        //Truncated or trailing data
+        assert!(AmsHeader::from_datagram(&datagram[..43]).is_err());
+        let mut long = datagram.clone();
+        long.push(0);
+        assert!(AmsHeader::from_datagram(&long).is_err());
+
+        ams_header.update_state_flags(StateFlags::req_default());
+        assert!(ams_header.state_flags().is_tcp());
+    }
+
+    #[test]
+    fn ams_header_max_length_test() {
+        let mut data: Vec<u8> = vec![0; 32];
+        data[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        let error = AmsHeader::read_from(&mut data.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
 }
@@ -0,0 +1,188 @@
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///AMS/TCP command: the frame carries an AmsHeader
+pub const AMS_TCP_PORT_AMS_CMD: u16 = 0x0000;
+///AMS/TCP command: close the port (data = port)
+pub const AMS_TCP_PORT_CLOSE: u16 = 0x0001;
+///AMS/TCP command: open a port at the router (data = requested port, 0 for any). Response data = AmsAddress
+pub const AMS_TCP_PORT_CONNECT: u16 = 0x1000;
+///AMS/TCP command: router state notification
+pub const AMS_TCP_PORT_ROUTER_NOTE: u16 = 0x1001;
+///AMS/TCP command: request the AmsNetId of the router. Response data = AmsNetId
+pub const AMS_TCP_PORT_GET_LOCAL_NETID: u16 = 0x1002;
+///Max data length of a received frame, larger frames are rejected without allocating
+pub const AMS_TCP_MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+///Raw AMS/TCP frame (command, length, data).
+///[AmsTcpHeader](super::ams_header::AmsTcpHeader) covers the AMS command only,
+///this frame also covers the commands between a client and its AMS router (port connect, port close, ...).
+/// ```
+/// use ads_proto::proto::ams_tcp_frame::*;
+///
+/// let frame = AmsTcpFrame::port_connect(0);
+/// assert_eq!(frame.command, AMS_TCP_PORT_CONNECT);
+/// assert_eq!(frame.data, vec![0, 0]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmsTcpFrame {
+    pub command: u16,
+    pub data: Vec<u8>,
+}
+
+impl AmsTcpFrame {
+    pub fn new(command: u16, data: Vec<u8>) -> Self {
+        AmsTcpFrame { command, data }
+    }
+
+    ///Request a port from the router. 0 requests any free port.
+    pub fn port_connect(port: u16) -> Self {
+        AmsTcpFrame::new(AMS_TCP_PORT_CONNECT, port.to_le_bytes().to_vec())
+    }
+
+    ///Response to a port connect with the assigned address
+    pub fn port_connect_response(address: &AmsAddress) -> Self {
+        let mut data: Vec<u8> = Vec::with_capacity(8);
+        address
+            .write_to(&mut data)
+            .expect("failed to write address to buffer!");
+        AmsTcpFrame::new(AMS_TCP_PORT_CONNECT, data)
+    }
+
+    pub fn port_close(port: u16) -> Self {
+        AmsTcpFrame::new(AMS_TCP_PORT_CLOSE, port.to_le_bytes().to_vec())
+    }
+
+    pub fn get_local_net_id() -> Self {
+        AmsTcpFrame::new(AMS_TCP_PORT_GET_LOCAL_NETID, Vec::new())
+    }
+
+    pub fn get_local_net_id_response(net_id: &AmsNetId) -> Self {
+        AmsTcpFrame::new(AMS_TCP_PORT_GET_LOCAL_NETID, net_id.net_id().to_vec())
+    }
+
+    pub fn is_ams_command(&self) -> bool {
+        self.command == AMS_TCP_PORT_AMS_CMD
+    }
+
+    ///Parse the AmsHeader of an AMS command frame
+    pub fn ams_header(&self) -> io::Result<AmsHeader> {
+        if !self.is_ams_command() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("AMS/TCP command {:#06x} has no AMS header", self.command),
+            ));
+        }
+        AmsHeader::read_from(&mut self.data.as_slice())
+    }
+
+    ///The port of a port connect or port close frame
+    pub fn port(&self) -> io::Result<u16> {
+        self.data.as_slice().read_u16::<LittleEndian>()
+    }
+
+    ///The address of a port connect response
+    pub fn address(&self) -> io::Result<AmsAddress> {
+        AmsAddress::read_from(&mut self.data.as_slice())
+    }
+}
+
+impl From<AmsHeader> for AmsTcpFrame {
+    fn from(ams_header: AmsHeader) -> Self {
+        let mut data: Vec<u8> = Vec::new();
+        ams_header
+            .write_to(&mut data)
+            .expect("failed to write ams header to buffer!");
+        AmsTcpFrame::new(AMS_TCP_PORT_AMS_CMD, data)
+    }
+}
+
+impl WriteTo for AmsTcpFrame {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u16::<LittleEndian>(self.command)?;
+        wtr.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        wtr.write_all(&self.data)?;
+        Ok(())
+    }
+}
+
+impl ReadFrom for AmsTcpFrame {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let command = read.read_u16::<LittleEndian>()?;
+        let length = read.read_u32::<LittleEndian>()?;
+        if length > AMS_TCP_MAX_FRAME_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "AMS/TCP frame length {} exceeds {}",
+                    length, AMS_TCP_MAX_FRAME_LEN
+                ),
+            ));
+        }
+        let mut data: Vec<u8> = vec![0; length as usize];
+        read.read_exact(&mut data)?;
+        Ok(AmsTcpFrame { command, data })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ams_header::AmsTcpHeader;
+    use crate::proto::request::{ReadRequest, Request};
+    use crate::proto::state_flags::StateFlags;
+
+    #[test]
+    fn ams_tcp_frame_port_connect_test() {
+        let mut buffer: Vec<u8> = Vec::new();
+        AmsTcpFrame::port_connect(851)
+            .write_to(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, [0, 16, 2, 0, 0, 0, 83, 3]);
+
+        let address = AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 30000);
+        let frame = AmsTcpFrame::port_connect_response(&address);
+        let mut buffer: Vec<u8> = Vec::new();
+        frame.write_to(&mut buffer).unwrap();
+        let frame = AmsTcpFrame::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(frame.command, AMS_TCP_PORT_CONNECT);
+        assert_eq!(frame.address().unwrap(), address);
+        assert!(frame.ams_header().is_err());
+    }
+
+    #[test]
+    fn ams_tcp_frame_ams_header_test() {
+        let ams_header = AmsHeader::new(
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 851),
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 2, 1, 1), 30000),
+            StateFlags::req_default(),
+            5,
+            Request::Read(ReadRequest::new(0x4020, 0, 4)),
+        );
+
+        //Same bytes as the AmsTcpHeader
+        let mut frame_buffer: Vec<u8> = Vec::new();
+        AmsTcpFrame::from(ams_header.clone())
+            .write_to(&mut frame_buffer)
+            .unwrap();
+        let mut header_buffer: Vec<u8> = Vec::new();
+        AmsTcpHeader::from(ams_header)
+            .write_to(&mut header_buffer)
+            .unwrap();
+        assert_eq!(frame_buffer, header_buffer);
+
+        let frame = AmsTcpFrame::read_from(&mut frame_buffer.as_slice()).unwrap();
+        assert!(frame.is_ams_command());
+        assert_eq!(frame.ams_header().unwrap().invoke_id(), 5);
+    }
+
+    #[test]
+    fn ams_tcp_frame_max_length_test() {
+        let data: Vec<u8> = vec![0, 0, 0xFF, 0xFF, 0xFF, 0xFF];
+        let error = AmsTcpFrame::read_from(&mut data.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::ads_services::system_services::ADSIGRP_CANOPEN_SDO;
+use crate::proto::ads_type::AdsType;
+use crate::proto::request::{ReadRequest, WriteRequest};
+
+///Index offset flag to access all subindices of an object at once
+pub const COE_COMPLETE_ACCESS: u32 = 0x0100;
+
+///CoE object (index and subindex) of an EtherCAT slave, accessed with SDO upload (Read)
+///and download (Write) requests to the AMS port of the slave on the EtherCAT master.
+/// ```
+/// use ads_proto::proto::coe::CoeSdo;
+///
+/// //Max motor speed of a drive
+/// let request = CoeSdo::new(0x8011, 0x11).download_value(&12000u32);
+/// assert_eq!(request.index_group, 0xF302);
+/// assert_eq!(request.index_offset, 0x8011_0011);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CoeSdo {
+    pub index: u16,
+    pub sub_index: u8,
+    ///Access the subindices from `sub_index` (0 or 1) to the end of the object
+    pub complete_access: bool,
+}
+
+impl CoeSdo {
+    pub fn new(index: u16, sub_index: u8) -> Self {
+        CoeSdo {
+            index,
+            sub_index,
+            complete_access: false,
+        }
+    }
+
+    ///Complete access to the object from the subindex (0 includes the number of entries, 1 skips it)
+    pub fn complete(index: u16, sub_index: u8) -> Self {
+        CoeSdo {
+            index,
+            sub_index,
+            complete_access: true,
+        }
+    }
+
+    pub fn index_offset(&self) -> u32 {
+        let flags = if self.complete_access {
+            COE_COMPLETE_ACCESS
+        } else {
+            0
+        };
+        (self.index as u32) << 16 | flags | self.sub_index as u32
+    }
+
+    pub fn from_index_offset(index_offset: u32) -> Self {
+        CoeSdo {
+            index: (index_offset >> 16) as u16,
+            sub_index: index_offset as u8,
+            complete_access: index_offset & COE_COMPLETE_ACCESS != 0,
+        }
+    }
+
+    ///SDO upload of up to `length` bytes
+    pub fn upload(&self, length: u32) -> ReadRequest {
+        ReadRequest::new(ADSIGRP_CANOPEN_SDO.index_group, self.index_offset(), length)
+    }
+
+    pub fn upload_value<T: AdsType>(&self) -> ReadRequest {
+        self.upload(T::SIZE)
+    }
+
+    pub fn download(&self, data: Vec<u8>) -> WriteRequest {
+        WriteRequest::new(ADSIGRP_CANOPEN_SDO.index_group, self.index_offset(), data)
+    }
+
+    pub fn download_value<T: AdsType>(&self, value: &T) -> WriteRequest {
+        self.download(value.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coe_sdo_index_offset_test() {
+        let sdo = CoeSdo::new(0x1018, 2);
+        assert_eq!(sdo.index_offset(), 0x1018_0002);
+        assert_eq!(CoeSdo::from_index_offset(0x1018_0002), sdo);
+
+        let sdo = CoeSdo::complete(0x1C12, 0);
+        assert_eq!(sdo.index_offset(), 0x1C12_0100);
+        assert_eq!(CoeSdo::from_index_offset(0x1C12_0100), sdo);
+    }
+
+    #[test]
+    fn coe_sdo_request_test() {
+        let sdo = CoeSdo::new(0x1018, 1);
+        assert_eq!(
+            sdo.upload_value::<u32>(),
+            ReadRequest::new(0xF302, 0x1018_0001, 4)
+        );
+        assert_eq!(
+            sdo.download_value(&-2i16),
+            WriteRequest::new(0xF302, 0x1018_0001, vec![0xFE, 0xFF])
+        );
+        assert_eq!(
+            CoeSdo::complete(0x1A00, 0).upload(42),
+            ReadRequest::new(0xF302, 0x1A00_0100, 42)
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::ads_services::system_services::{
+    ADSIGRP_ECAT_SLAVE_ADDRESSES, ADSIGRP_ECAT_SLAVE_COUNT, ADSIGRP_ECAT_SLAVE_STATES,
+    ETHERCAT_MASTER_PORT,
+};
+use crate::proto::ads_state::AdsState;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::request::{ReadRequest, Request, WriteControlRequest};
+use bitfield::Bit;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///Length of the state of one slave in the slave states
+pub const EC_SLAVE_STATE_LEN: u32 = 2;
+
+///States of the EtherCAT state machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EcState {
+    Init,
+    PreOp,
+    Boot,
+    SafeOp,
+    Op,
+    Unknown(u8),
+}
+
+impl EcState {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            EcState::Init => 0x01,
+            EcState::PreOp => 0x02,
+            EcState::Boot => 0x03,
+            EcState::SafeOp => 0x04,
+            EcState::Op => 0x08,
+            EcState::Unknown(value) => *value,
+        }
+    }
+}
+
+impl From<u8> for EcState {
+    fn from(value: u8) -> Self {
+        match value {
+            0x01 => EcState::Init,
+            0x02 => EcState::PreOp,
+            0x03 => EcState::Boot,
+            0x04 => EcState::SafeOp,
+            0x08 => EcState::Op,
+            value => EcState::Unknown(value),
+        }
+    }
+}
+
+///Device state of a slave: EtherCAT state in the low nibble and error bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EcDeviceState(pub u8);
+
+impl EcDeviceState {
+    pub fn state(&self) -> EcState {
+        EcState::from(self.0 & 0x0F)
+    }
+
+    ///The slave signals an error (AL status error indication)
+    pub fn error(&self) -> bool {
+        self.0.bit(4)
+    }
+
+    ///Vendor id, product code, revision or serial number do not match the configuration
+    pub fn invalid_vprs(&self) -> bool {
+        self.0.bit(5)
+    }
+
+    ///An init command of the state transition failed
+    pub fn init_cmd_error(&self) -> bool {
+        self.0.bit(6)
+    }
+
+    ///The slave is disabled in the configuration
+    pub fn disabled(&self) -> bool {
+        self.0.bit(7)
+    }
+}
+
+///Link state of a slave, 0 if the slave is connected as configured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EcLinkState(pub u8);
+
+impl EcLinkState {
+    pub fn ok(&self) -> bool {
+        self.0 == 0
+    }
+
+    ///The slave is not present
+    pub fn not_present(&self) -> bool {
+        self.0.bit(0)
+    }
+
+    ///There is a link but no communication
+    pub fn link_without_communication(&self) -> bool {
+        self.0.bit(1)
+    }
+
+    ///A configured link is missing
+    pub fn missing_link(&self) -> bool {
+        self.0.bit(2)
+    }
+
+    ///There is a link which is not in the configuration
+    pub fn additional_link(&self) -> bool {
+        self.0.bit(3)
+    }
+
+    ///Link on port A, B, C or D (0..3)
+    pub fn port(&self, port: usize) -> bool {
+        port < 4 && self.0.bit(4 + port)
+    }
+}
+
+///State of a slave as read with [EcMasterRequest::SlaveStates]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EcSlaveState {
+    pub device_state: EcDeviceState,
+    pub link_state: EcLinkState,
+}
+
+impl EcSlaveState {
+    ///In the state without error bits and with an ok link
+    pub fn is(&self, state: EcState) -> bool {
+        self.device_state.0 == state.as_u8() && self.link_state.ok()
+    }
+}
+
+impl ReadFrom for EcSlaveState {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        Ok(EcSlaveState {
+            device_state: EcDeviceState(read.read_u8()?),
+            link_state: EcLinkState(read.read_u8()?),
+        })
+    }
+}
+
+impl WriteTo for EcSlaveState {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u8(self.device_state.0)?;
+        wtr.write_u8(self.link_state.0)
+    }
+}
+
+///Requests to the EtherCAT master on [ETHERCAT_MASTER_PORT],
+///except the state change which is sent to the port of the slave.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EcMasterRequest {
+    ///Response data: u16, see [slave_count_from]
+    SlaveCount,
+    ///Response data: u16 per slave, see [slave_addresses_from]
+    SlaveAddresses { count: u16 },
+    ///Response data: [EcSlaveState] per slave, see [slave_states_from]
+    SlaveStates { count: u16 },
+    ///WriteControl to the AMS port of the slave with the requested state as device state
+    RequestSlaveState { address: u16, state: EcState },
+}
+
+impl EcMasterRequest {
+    ///AMS port of the request
+    pub fn port(&self) -> u16 {
+        match self {
+            EcMasterRequest::RequestSlaveState { address, .. } => *address,
+            _ => ETHERCAT_MASTER_PORT,
+        }
+    }
+}
+
+impl From<EcMasterRequest> for Request {
+    fn from(request: EcMasterRequest) -> Self {
+        match request {
+            EcMasterRequest::SlaveCount => {
+                Request::Read(ReadRequest::new(ADSIGRP_ECAT_SLAVE_COUNT.index_group, 0, 2))
+            }
+            EcMasterRequest::SlaveAddresses { count } => Request::Read(ReadRequest::new(
+                ADSIGRP_ECAT_SLAVE_ADDRESSES.index_group,
+                0,
+                count as u32 * 2,
+            )),
+            EcMasterRequest::SlaveStates { count } => Request::Read(ReadRequest::new(
+                ADSIGRP_ECAT_SLAVE_STATES.index_group,
+                0,
+                count as u32 * EC_SLAVE_STATE_LEN,
+            )),
+            EcMasterRequest::RequestSlaveState { state, .. } => {
+                Request::WriteControl(WriteControlRequest::new(
+                    AdsState::AdsStateRun,
+                    state.as_u8() as u16,
+                    0,
+                    Vec::new(),
+                ))
+            }
+        }
+    }
+}
+
+pub fn slave_count_from(mut data: &[u8]) -> io::Result<u16> {
+    data.read_u16::<LittleEndian>()
+}
+
+pub fn slave_addresses_from(mut data: &[u8]) -> io::Result<Vec<u16>> {
+    let mut addresses = Vec::with_capacity(data.len() / 2);
+    while !data.is_empty() {
+        addresses.push(data.read_u16::<LittleEndian>()?);
+    }
+    Ok(addresses)
+}
+
+pub fn slave_states_from(mut data: &[u8]) -> io::Result<Vec<EcSlaveState>> {
+    let mut states = Vec::with_capacity(data.len() / EC_SLAVE_STATE_LEN as usize);
+    while !data.is_empty() {
+        states.push(EcSlaveState::read_from(&mut data)?);
+    }
+    Ok(states)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ec_slave_state_test() {
+        let states = slave_states_from(&[0x08, 0x00, 0x12, 0x01, 0x24, 0x00, 0x84, 0x30]).unwrap();
+        assert_eq!(states.len(), 4);
+        assert!(states[0].is(EcState::Op));
+
+        assert_eq!(states[1].device_state.state(), EcState::PreOp);
+        assert!(states[1].device_state.error());
+        assert!(states[1].link_state.not_present());
+        assert!(!states[1].is(EcState::PreOp));
+
+        assert_eq!(states[2].device_state.state(), EcState::SafeOp);
+        assert!(states[2].device_state.invalid_vprs());
+        assert!(!states[2].device_state.error());
+
+        assert!(states[3].device_state.disabled());
+        assert!(states[3].link_state.port(0));
+        assert!(states[3].link_state.port(1));
+        assert!(!states[3].link_state.port(2));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        states[3].write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, vec![0x84, 0x30]);
+        assert!(slave_states_from(&[0x08]).is_err());
+    }
+
+    #[test]
+    fn ec_master_request_test() {
+        assert_eq!(
+            Request::from(EcMasterRequest::SlaveCount),
+            Request::Read(ReadRequest::new(6, 0, 2))
+        );
+        assert_eq!(
+            Request::from(EcMasterRequest::SlaveStates { count: 3 }),
+            Request::Read(ReadRequest::new(9, 0, 6))
+        );
+        let request = EcMasterRequest::RequestSlaveState {
+            address: 1002,
+            state: EcState::SafeOp,
+        };
+        assert_eq!(request.port(), 1002);
+        assert_eq!(EcMasterRequest::SlaveCount.port(), ETHERCAT_MASTER_PORT);
+        assert_eq!(
+            Request::from(request),
+            Request::WriteControl(WriteControlRequest::new(
+                AdsState::AdsStateRun,
+                4,
+                0,
+                Vec::new()
+            ))
+        );
+
+        assert_eq!(slave_count_from(&[3, 0]).unwrap(), 3);
+        assert_eq!(
+            slave_addresses_from(&[0xE9, 0x03, 0xEA, 0x03]).unwrap(),
+            vec![1001, 1002]
+        );
+        assert_eq!(EcState::from(0x08), EcState::Op);
+        assert_eq!(EcState::from(0x05), EcState::Unknown(5));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::ads_services::system_services::{
+    SYSTEMSERVICE_FCLOSE, SYSTEMSERVICE_FDELETE, SYSTEMSERVICE_FFILEFIND, SYSTEMSERVICE_FOPEN,
+    SYSTEMSERVICE_FREAD, SYSTEMSERVICE_FWRITE, SYSTEMSERVICE_MKDIR, SYSTEMSERVICE_RMDIR,
+};
+use crate::proto::file_time::FileTime;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::request::{ReadWriteRequest, Request};
+use crate::proto::windows_1252;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use std::ops::BitOr;
+
+///Length of the file name in a find entry (MAX_PATH)
+pub const FIND_FILE_NAME_LEN: usize = 260;
+///Length of the alternate (8.3) file name in a find entry
+pub const FIND_ALTERNATE_NAME_LEN: usize = 14;
+///Length of the find entry returned by find first/next
+pub const FIND_ENTRY_LEN: u32 = 324;
+///File attribute of directories
+pub const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+
+///Mode flags to open a file (like the mode of fopen)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OpenMode(pub u32);
+
+impl OpenMode {
+    ///"r" open an existing file for reading
+    pub const READ: OpenMode = OpenMode(0x0001);
+    ///"w" create or truncate a file for writing
+    pub const WRITE: OpenMode = OpenMode(0x0002);
+    ///"a" create or append to a file
+    pub const APPEND: OpenMode = OpenMode(0x0004);
+    ///"+" read and write
+    pub const PLUS: OpenMode = OpenMode(0x0008);
+    ///"b" binary mode
+    pub const BINARY: OpenMode = OpenMode(0x0010);
+    ///"t" text mode
+    pub const TEXT: OpenMode = OpenMode(0x0020);
+
+    pub fn contains(&self, mode: OpenMode) -> bool {
+        self.0 & mode.0 == mode.0
+    }
+}
+
+impl BitOr for OpenMode {
+    type Output = OpenMode;
+
+    fn bitor(self, rhs: OpenMode) -> OpenMode {
+        OpenMode(self.0 | rhs.0)
+    }
+}
+
+///Base directory a relative path is resolved in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasePath {
+    ///Absolute path
+    Generic,
+    ///Boot project directory of the PLC
+    BootProject,
+    ///Boot data directory (persistent data)
+    BootData,
+    ///TwinCAT boot directory
+    BootPath,
+    ///User path 1 to 9 configured in the TwinCAT system
+    UserPath(u8),
+}
+
+impl BasePath {
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            BasePath::Generic => 1,
+            BasePath::BootProject => 2,
+            BasePath::BootData => 3,
+            BasePath::BootPath => 4,
+            BasePath::UserPath(n) => 10 + *n as u32,
+        }
+    }
+
+    ///Index offset with the base path in the high word and the flags in the low word
+    pub fn index_offset(&self, flags: u32) -> u32 {
+        self.as_u32() << 16 | (flags & 0xFFFF)
+    }
+}
+
+///Requests of the file functions of the TwinCAT system service
+///(AMS port [SYSTEM_SERVICE_PORT](crate::ads_services::system_services::SYSTEM_SERVICE_PORT)).
+///All of them are sent as [ReadWriteRequest].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileRequest {
+    ///Response data: file handle (u32)
+    Open {
+        path: String,
+        mode: OpenMode,
+        base: BasePath,
+    },
+    Close {
+        handle: u32,
+    },
+    ///Response data: up to `length` bytes, empty at the end of the file
+    Read {
+        handle: u32,
+        length: u32,
+    },
+    Write {
+        handle: u32,
+        data: Vec<u8>,
+    },
+    Delete {
+        path: String,
+        base: BasePath,
+    },
+    ///Response data: [FileFindEntry] of the first match
+    FindFirst {
+        pattern: String,
+        base: BasePath,
+    },
+    ///Response data: next [FileFindEntry], AdsErrDeviceNotFound if there is none
+    FindNext {
+        find_handle: u32,
+    },
+    MakeDir {
+        path: String,
+        base: BasePath,
+    },
+    RemoveDir {
+        path: String,
+        base: BasePath,
+    },
+}
+
+impl FileRequest {
+    pub fn read_write_request(&self) -> ReadWriteRequest {
+        match self {
+            FileRequest::Open { path, mode, base } => ReadWriteRequest::new(
+                SYSTEMSERVICE_FOPEN.index_group,
+                base.index_offset(mode.0),
+                4,
+                path_data(path),
+            ),
+            FileRequest::Close { handle } => {
+                ReadWriteRequest::new(SYSTEMSERVICE_FCLOSE.index_group, *handle, 0, Vec::new())
+            }
+            FileRequest::Read { handle, length } => ReadWriteRequest::new(
+                SYSTEMSERVICE_FREAD.index_group,
+                *handle,
+                *length,
+                Vec::new(),
+            ),
+            FileRequest::Write { handle, data } => {
+                ReadWriteRequest::new(SYSTEMSERVICE_FWRITE.index_group, *handle, 0, data.clone())
+            }
+            FileRequest::Delete { path, base } => ReadWriteRequest::new(
+                SYSTEMSERVICE_FDELETE.index_group,
+                base.index_offset(0),
+                0,
+                path_data(path),
+            ),
+            FileRequest::FindFirst { pattern, base } => ReadWriteRequest::new(
+                SYSTEMSERVICE_FFILEFIND.index_group,
+                base.index_offset(0),
+                FIND_ENTRY_LEN,
+                path_data(pattern),
+            ),
+            FileRequest::FindNext { find_handle } => ReadWriteRequest::new(
+                SYSTEMSERVICE_FFILEFIND.index_group,
+                *find_handle,
+                FIND_ENTRY_LEN,
+                Vec::new(),
+            ),
+            FileRequest::MakeDir { path, base } => ReadWriteRequest::new(
+                SYSTEMSERVICE_MKDIR.index_group,
+                base.index_offset(0),
+                0,
+                path_data(path),
+            ),
+            FileRequest::RemoveDir { path, base } => ReadWriteRequest::new(
+                SYSTEMSERVICE_RMDIR.index_group,
+                base.index_offset(0),
+                0,
+                path_data(path),
+            ),
+        }
+    }
+}
+
+impl From<FileRequest> for Request {
+    fn from(request: FileRequest) -> Self {
+        Request::ReadWrite(request.read_write_request())
+    }
+}
+
+///Windows-1252 encoded, null terminated path
+fn path_data(path: &str) -> Vec<u8> {
+    let mut data = windows_1252::encode(path);
+    data.push(0);
+    data
+}
+
+///Null terminated, Windows-1252 encoded path of the write data of a [FileRequest]
+pub fn path_from_data(data: &[u8]) -> String {
+    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+    windows_1252::decode(&data[..end])
+}
+
+///Directory entry returned by find first/next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFindEntry {
+    ///Handle for find next
+    pub find_handle: u32,
+    pub attributes: u32,
+    pub creation_time: FileTime,
+    pub last_access_time: FileTime,
+    pub last_write_time: FileTime,
+    pub file_size: u64,
+    pub file_name: String,
+    pub alternate_file_name: String,
+}
+
+impl FileFindEntry {
+    pub fn new(find_handle: u32, file_name: &str, attributes: u32, file_size: u64) -> Self {
+        FileFindEntry {
+            find_handle,
+            attributes,
+            creation_time: FileTime::default(),
+            last_access_time: FileTime::default(),
+            last_write_time: FileTime::default(),
+            file_size,
+            file_name: file_name.to_string(),
+            alternate_file_name: String::new(),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+    }
+}
+
+impl WriteTo for FileFindEntry {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.find_handle)?;
+        wtr.write_u32::<LittleEndian>(self.attributes)?;
+        self.creation_time.write_to(&mut wtr)?;
+        self.last_access_time.write_to(&mut wtr)?;
+        self.last_write_time.write_to(&mut wtr)?;
+        wtr.write_u32::<LittleEndian>((self.file_size >> 32) as u32)?;
+        wtr.write_u32::<LittleEndian>(self.file_size as u32)?;
+        //reserved
+        wtr.write_u64::<LittleEndian>(0)?;
+        write_fixed_str(&mut wtr, &self.file_name, FIND_FILE_NAME_LEN)?;
+        write_fixed_str(&mut wtr, &self.alternate_file_name, FIND_ALTERNATE_NAME_LEN)?;
+        //padding to FIND_ENTRY_LEN
+        wtr.write_u16::<LittleEndian>(0)
+    }
+}
+
+impl ReadFrom for FileFindEntry {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let find_handle = read.read_u32::<LittleEndian>()?;
+        let attributes = read.read_u32::<LittleEndian>()?;
+        let creation_time = FileTime::read_from(read)?;
+        let last_access_time = FileTime::read_from(read)?;
+        let last_write_time = FileTime::read_from(read)?;
+        let size_high = read.read_u32::<LittleEndian>()? as u64;
+        let size_low = read.read_u32::<LittleEndian>()? as u64;
+        read.read_u64::<LittleEndian>()?;
+        let file_name = read_fixed_str(read, FIND_FILE_NAME_LEN)?;
+        let alternate_file_name = read_fixed_str(read, FIND_ALTERNATE_NAME_LEN)?;
+        Ok(FileFindEntry {
+            find_handle,
+            attributes,
+            creation_time,
+            last_access_time,
+            last_write_time,
+            file_size: size_high << 32 | size_low,
+            file_name,
+            alternate_file_name,
+        })
+    }
+}
+
+fn write_fixed_str<W: Write>(wtr: &mut W, value: &str, len: usize) -> io::Result<()> {
+    let mut buffer = windows_1252::encode(value);
+    if buffer.len() >= len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} longer than {} bytes", value, len - 1),
+        ));
+    }
+    buffer.resize(len, 0);
+    wtr.write_all(&buffer)
+}
+
+fn read_fixed_str<R: Read>(read: &mut R, len: usize) -> io::Result<String> {
+    let mut buffer = vec![0; len];
+    read.read_exact(&mut buffer)?;
+    Ok(path_from_data(&buffer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_request_test() {
+        let request = FileRequest::Open {
+            path: "C:\\a.txt".to_string(),
+            mode: OpenMode::WRITE | OpenMode::BINARY,
+            base: BasePath::Generic,
+        }
+        .read_write_request();
+        assert_eq!(request.index_group, 0x78);
+        assert_eq!(request.index_offset, 0x0001_0012);
+        assert_eq!(request.read_length, 4);
+        assert_eq!(request.data, b"C:\\a.txt\0");
+        assert_eq!(path_from_data(&request.data), "C:\\a.txt");
+
+        //Windows-1252 paths
+        let request = FileRequest::Open {
+            path: "C:\\Maße.txt".to_string(),
+            mode: OpenMode::READ,
+            base: BasePath::Generic,
+        }
+        .read_write_request();
+        assert_eq!(request.data, b"C:\\Ma\xDFe.txt\0");
+        assert_eq!(path_from_data(&request.data), "C:\\Maße.txt");
+
+        let request = FileRequest::Delete {
+            path: "a.txt".to_string(),
+            base: BasePath::UserPath(2),
+        }
+        .read_write_request();
+        assert_eq!(
+            (request.index_group, request.index_offset),
+            (0x83, 12 << 16)
+        );
+
+        let request = FileRequest::Read {
+            handle: 5,
+            length: 100,
+        };
+        match Request::from(request) {
+            Request::ReadWrite(r) => {
+                assert_eq!(
+                    (r.index_group, r.index_offset, r.read_length),
+                    (0x7A, 5, 100)
+                );
+                assert_eq!(r.write_length, 0);
+            }
+            r => panic!("unexpected request {:?}", r),
+        }
+        assert!((OpenMode::READ | OpenMode::PLUS).contains(OpenMode::PLUS));
+        assert!(!OpenMode::READ.contains(OpenMode::WRITE));
+    }
+
+    #[test]
+    fn file_find_entry_test() {
+        let mut entry = FileFindEntry::new(3, "Config.xml", 0x20, 0x1_0000_0002);
+        entry.alternate_file_name = "CONFIG~1.XML".to_string();
+        entry.last_write_time = FileTime::new(133_000_000_000_000_000);
+        let mut buffer: Vec<u8> = Vec::new();
+        entry.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), FIND_ENTRY_LEN as usize);
+        //size high, size low
+        assert_eq!(buffer[32..40], [1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(&buffer[48..58], b"Config.xml");
+        assert_eq!(
+            FileFindEntry::read_from(&mut buffer.as_slice()).unwrap(),
+            entry
+        );
+        assert!(!entry.is_dir());
+        assert!(FileFindEntry::new(3, "dir", FILE_ATTRIBUTE_DIRECTORY, 0).is_dir());
+
+        let entry = FileFindEntry::new(4, "Zähler.csv", 0x20, 0);
+        let mut buffer: Vec<u8> = Vec::new();
+        entry.write_to(&mut buffer).unwrap();
+        assert_eq!(&buffer[48..58], b"Z\xE4hler.csv");
+        assert_eq!(
+            FileFindEntry::read_from(&mut buffer.as_slice()).unwrap(),
+            entry
+        );
+
+        let long_name = "a".repeat(FIND_FILE_NAME_LEN);
+        let entry = FileFindEntry::new(3, &long_name, 0, 0);
+        assert!(entry.write_to(&mut Vec::new()).is_err());
+    }
+}
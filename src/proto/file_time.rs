@@ -0,0 +1,259 @@
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+///Windows FILETIME -> 100ns ticks since 1601-01-01 (UTC).
+///Used as time stamp in the [AdsStampHeader](super::response::AdsStampHeader) of device notifications.
+/// ```
+/// use ads_proto::proto::file_time::FileTime;
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+///
+/// let file_time = FileTime::from(UNIX_EPOCH + Duration::from_secs(1));
+/// assert_eq!(file_time.as_u64(), 116_444_736_010_000_000);
+/// assert_eq!(SystemTime::from(file_time), UNIX_EPOCH + Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct FileTime(u64);
+
+impl FileTime {
+    ///FILETIME of 1970-01-01 00:00:00 UTC
+    pub const UNIX_EPOCH: FileTime = FileTime(116_444_736_000_000_000);
+
+    pub fn new(ticks: u64) -> Self {
+        FileTime(ticks)
+    }
+
+    pub fn now() -> Self {
+        FileTime::from(SystemTime::now())
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    pub fn to_system_time(&self) -> SystemTime {
+        SystemTime::from(*self)
+    }
+
+    ///Nanoseconds since 1970-01-01 00:00:00 UTC
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn unix_nanos(&self) -> i128 {
+        (self.0 as i128 - FileTime::UNIX_EPOCH.0 as i128) * 100
+    }
+
+    ///Saturated to 0 before 1601 and to u64::MAX after the year 60056
+    #[cfg(any(feature = "chrono", feature = "time"))]
+    fn from_unix_nanos(nanos: i128) -> Self {
+        let ticks = nanos.div_euclid(100) + FileTime::UNIX_EPOCH.0 as i128;
+        FileTime(ticks.clamp(0, u64::MAX as i128) as u64)
+    }
+}
+
+impl From<u64> for FileTime {
+    fn from(ticks: u64) -> Self {
+        FileTime(ticks)
+    }
+}
+
+impl From<FileTime> for u64 {
+    fn from(file_time: FileTime) -> Self {
+        file_time.0
+    }
+}
+
+///Times which can not be represented are saturated
+impl From<FileTime> for SystemTime {
+    fn from(file_time: FileTime) -> Self {
+        let epoch = FileTime::UNIX_EPOCH.0;
+        if file_time.0 >= epoch {
+            saturating_add(UNIX_EPOCH, ticks_to_duration(file_time.0 - epoch))
+        } else {
+            saturating_sub(UNIX_EPOCH, ticks_to_duration(epoch - file_time.0))
+        }
+    }
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::new(ticks / 10_000_000, (ticks % 10_000_000) as u32 * 100)
+}
+
+fn saturating_add(mut time: SystemTime, mut duration: Duration) -> SystemTime {
+    let mut step = duration;
+    while !step.is_zero() {
+        match time.checked_add(step) {
+            Some(t) if step <= duration => {
+                time = t;
+                duration -= step;
+            }
+            _ => step /= 2,
+        }
+    }
+    time
+}
+
+fn saturating_sub(mut time: SystemTime, mut duration: Duration) -> SystemTime {
+    let mut step = duration;
+    while !step.is_zero() {
+        match time.checked_sub(step) {
+            Some(t) if step <= duration => {
+                time = t;
+                duration -= step;
+            }
+            _ => step /= 2,
+        }
+    }
+    time
+}
+
+///Times before 1601 are saturated to 0
+impl From<SystemTime> for FileTime {
+    fn from(time: SystemTime) -> Self {
+        let epoch = FileTime::UNIX_EPOCH.0;
+        let ticks = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => epoch.saturating_add((d.as_nanos() / 100).min(u64::MAX as u128) as u64),
+            Err(e) => {
+                epoch.saturating_sub((e.duration().as_nanos() / 100).min(u64::MAX as u128) as u64)
+            }
+        };
+        FileTime(ticks)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<FileTime> for chrono::DateTime<chrono::Utc> {
+    fn from(file_time: FileTime) -> Self {
+        let nanos = file_time.unix_nanos();
+        let secs = nanos.div_euclid(1_000_000_000) as i64;
+        let nanos = nanos.rem_euclid(1_000_000_000) as u32;
+        chrono::DateTime::from_timestamp(secs, nanos)
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MAX_UTC)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for FileTime {
+    fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+        let nanos =
+            time.timestamp() as i128 * 1_000_000_000 + time.timestamp_subsec_nanos() as i128;
+        FileTime::from_unix_nanos(nanos)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<FileTime> for time::OffsetDateTime {
+    fn from(file_time: FileTime) -> Self {
+        time::OffsetDateTime::from_unix_timestamp_nanos(file_time.unix_nanos())
+            .unwrap_or(time::PrimitiveDateTime::MAX.assume_utc())
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for FileTime {
+    fn from(time: time::OffsetDateTime) -> Self {
+        FileTime::from_unix_nanos(time.unix_timestamp_nanos())
+    }
+}
+
+impl WriteTo for FileTime {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u64::<LittleEndian>(self.0)
+    }
+}
+
+impl ReadFrom for FileTime {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        Ok(FileTime(read.read_u64::<LittleEndian>()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_time_to_system_time_test() {
+        assert_eq!(SystemTime::from(FileTime::UNIX_EPOCH), UNIX_EPOCH);
+        assert_eq!(
+            SystemTime::from(FileTime::new(116_444_736_000_000_000 + 15)),
+            UNIX_EPOCH + Duration::from_nanos(1500)
+        );
+        assert_eq!(
+            FileTime::new(116_444_735_990_000_000).to_system_time(),
+            UNIX_EPOCH - Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn file_time_to_system_time_max_test() {
+        let time = SystemTime::from(FileTime::new(u64::MAX));
+        assert_eq!(FileTime::from(time), FileTime::new(u64::MAX));
+        assert_eq!(
+            SystemTime::from(FileTime::new(0)),
+            UNIX_EPOCH - Duration::from_secs(11_644_473_600)
+        );
+    }
+
+    #[test]
+    fn file_time_from_system_time_test() {
+        //2020-01-01 00:00:00 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+        assert_eq!(FileTime::from(time).as_u64(), 132_223_104_000_000_000);
+        assert_eq!(SystemTime::from(FileTime::from(time)), time);
+
+        //Before 1601
+        let time = UNIX_EPOCH - Duration::from_secs(12_000_000_000);
+        assert_eq!(FileTime::from(time).as_u64(), 0);
+    }
+
+    #[test]
+    fn file_time_now_test() {
+        let before = SystemTime::now();
+        let now = FileTime::now();
+        assert!(now >= FileTime::from(before));
+    }
+
+    #[test]
+    fn file_time_write_to_test() {
+        let mut buffer: Vec<u8> = Vec::new();
+        FileTime::new(0x0102030405060708)
+            .write_to(&mut buffer)
+            .unwrap();
+        assert_eq!(buffer, [8, 7, 6, 5, 4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn file_time_read_from_test() {
+        let data: Vec<u8> = vec![8, 7, 6, 5, 4, 3, 2, 1];
+        let file_time = FileTime::read_from(&mut data.as_slice()).unwrap();
+        assert_eq!(file_time.as_u64(), 0x0102030405060708);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn file_time_chrono_test() {
+        let date_time: chrono::DateTime<chrono::Utc> =
+            FileTime::new(132_223_104_000_000_000).into();
+        assert_eq!(date_time.timestamp(), 1_577_836_800);
+        assert_eq!(FileTime::from(date_time).as_u64(), 132_223_104_000_000_000);
+
+        let date_time: chrono::DateTime<chrono::Utc> = FileTime::new(u64::MAX).into();
+        assert_eq!(FileTime::from(date_time), FileTime::new(u64::MAX));
+        assert_eq!(
+            FileTime::from(chrono::DateTime::<chrono::Utc>::MIN_UTC).as_u64(),
+            0
+        );
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn file_time_time_test() {
+        let date_time: time::OffsetDateTime = FileTime::new(132_223_104_000_000_000).into();
+        assert_eq!(date_time.unix_timestamp(), 1_577_836_800);
+        assert_eq!(FileTime::from(date_time).as_u64(), 132_223_104_000_000_000);
+
+        //After the year 9999
+        let date_time: time::OffsetDateTime = FileTime::new(u64::MAX).into();
+        assert_eq!(date_time, time::PrimitiveDateTime::MAX.assume_utc());
+    }
+}
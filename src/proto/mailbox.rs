@@ -0,0 +1,456 @@
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///Length of the EtherCAT mailbox header
+pub const MAILBOX_HEADER_LEN: usize = 6;
+///Service of the data of an error reply (type [MailboxType::Error])
+pub const MAILBOX_ERROR_COMMAND: u16 = 1;
+
+///Protocol of the mailbox data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MailboxType {
+    ///Error reply of the slave
+    Error,
+    ///ADS over EtherCAT
+    Aoe,
+    ///Ethernet over EtherCAT
+    Eoe,
+    ///CANopen over EtherCAT
+    Coe,
+    ///File access over EtherCAT
+    Foe,
+    ///Servo drive profile over EtherCAT
+    Soe,
+    ///Vendor specific
+    Voe,
+    Unknown(u8),
+}
+
+impl MailboxType {
+    pub fn as_u8(&self) -> u8 {
+        match self {
+            MailboxType::Error => 0,
+            MailboxType::Aoe => 1,
+            MailboxType::Eoe => 2,
+            MailboxType::Coe => 3,
+            MailboxType::Foe => 4,
+            MailboxType::Soe => 5,
+            MailboxType::Voe => 15,
+            MailboxType::Unknown(value) => *value,
+        }
+    }
+}
+
+impl From<u8> for MailboxType {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => MailboxType::Error,
+            1 => MailboxType::Aoe,
+            2 => MailboxType::Eoe,
+            3 => MailboxType::Coe,
+            4 => MailboxType::Foe,
+            5 => MailboxType::Soe,
+            15 => MailboxType::Voe,
+            value => MailboxType::Unknown(value),
+        }
+    }
+}
+
+///EtherCAT mailbox header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxHeader {
+    ///Length of the mailbox data after the header
+    pub length: u16,
+    ///Station address of the source (master to slave) or destination (slave to master)
+    pub address: u16,
+    ///Channel (6 bit), 0 is the only one in use
+    pub channel: u8,
+    ///Priority (2 bit)
+    pub priority: u8,
+    ///Type (4 bit)
+    pub mailbox_type: MailboxType,
+    ///Counter (3 bit), see [MailboxCounter]
+    pub counter: u8,
+}
+
+impl MailboxHeader {
+    pub fn new(length: u16, address: u16, mailbox_type: MailboxType, counter: u8) -> Self {
+        MailboxHeader {
+            length,
+            address,
+            channel: 0,
+            priority: 0,
+            mailbox_type,
+            counter,
+        }
+    }
+}
+
+impl WriteTo for MailboxHeader {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u16::<LittleEndian>(self.length)?;
+        wtr.write_u16::<LittleEndian>(self.address)?;
+        wtr.write_u8((self.channel & 0x3F) | (self.priority & 0x03) << 6)?;
+        wtr.write_u8((self.mailbox_type.as_u8() & 0x0F) | (self.counter & 0x07) << 4)
+    }
+}
+
+impl ReadFrom for MailboxHeader {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let length = read.read_u16::<LittleEndian>()?;
+        let address = read.read_u16::<LittleEndian>()?;
+        let channel_priority = read.read_u8()?;
+        let type_counter = read.read_u8()?;
+        Ok(MailboxHeader {
+            length,
+            address,
+            channel: channel_priority & 0x3F,
+            priority: channel_priority >> 6,
+            mailbox_type: MailboxType::from(type_counter & 0x0F),
+            counter: (type_counter >> 4) & 0x07,
+        })
+    }
+}
+
+///Counter of the mailbox header. 0 is only used before the first message, 7 is followed by 1.
+///
+///Use one counter for the messages sent and one for the messages received:
+///the slave repeats a message with the same counter if the master requests a repeat,
+///a received message with the counter of the previous one has to be discarded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MailboxCounter {
+    last: u8,
+}
+
+impl MailboxCounter {
+    pub fn new() -> Self {
+        MailboxCounter::default()
+    }
+
+    pub fn last(&self) -> u8 {
+        self.last
+    }
+
+    ///Counter of the next message sent
+    pub fn next_value(&mut self) -> u8 {
+        self.last = self.last % 7 + 1;
+        self.last
+    }
+
+    ///Check the counter of a received message. Returns false if it is a repetition of the previous message.
+    pub fn received(&mut self, counter: u8) -> bool {
+        if counter != 0 && counter == self.last {
+            return false;
+        }
+        self.last = counter;
+        true
+    }
+}
+
+///Error reply of a slave (mailbox type [MailboxType::Error])
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MailboxError {
+    pub detail: u16,
+}
+
+impl MailboxError {
+    pub fn new(detail: u16) -> Self {
+        MailboxError { detail }
+    }
+
+    ///Description of the error detail (ETG.1000.6)
+    pub fn description(&self) -> &'static str {
+        match self.detail {
+            0x01 => "syntax error in the mailbox header",
+            0x02 => "mailbox protocol not supported",
+            0x03 => "invalid channel",
+            0x04 => "service not supported",
+            0x05 => "invalid mailbox header",
+            0x06 => "mailbox data too short",
+            0x07 => "no more memory in the slave",
+            0x08 => "inconsistent data length",
+            _ => "unknown mailbox error",
+        }
+    }
+
+    fn read_data(data: &[u8]) -> io::Result<Self> {
+        let mut data = data;
+        let command = data.read_u16::<LittleEndian>()?;
+        if command != MAILBOX_ERROR_COMMAND {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid mailbox error command {}", command),
+            ));
+        }
+        Ok(MailboxError::new(data.read_u16::<LittleEndian>()?))
+    }
+}
+
+impl From<MailboxError> for io::Error {
+    fn from(error: MailboxError) -> Self {
+        io::Error::other(format!(
+            "mailbox error {:#06x}: {}",
+            error.detail,
+            error.description()
+        ))
+    }
+}
+
+///Header and data of a mailbox of the expected type, the padding after the data is ignored.
+///Error replies of the slave are returned as error.
+pub(crate) fn read_mailbox_data(
+    mailbox: &[u8],
+    mailbox_type: MailboxType,
+) -> io::Result<(MailboxHeader, &[u8])> {
+    let mut data = mailbox;
+    let header = MailboxHeader::read_from(&mut data)?;
+    let data = data.get(..header.length as usize).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!(
+                "mailbox length {} exceeds the {} bytes received",
+                header.length,
+                mailbox.len() - MAILBOX_HEADER_LEN
+            ),
+        )
+    })?;
+    if header.mailbox_type == MailboxType::Error {
+        return Err(MailboxError::read_data(data)?.into());
+    }
+    if header.mailbox_type != mailbox_type {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "expected mailbox type {:?}, received {:?}",
+                mailbox_type, header.mailbox_type
+            ),
+        ));
+    }
+    Ok((header, data))
+}
+
+///Header and data padded with zeros to the size of the mailbox
+pub(crate) fn write_mailbox(
+    header: &MailboxHeader,
+    data: &[u8],
+    mailbox_size: usize,
+) -> io::Result<Vec<u8>> {
+    let len = MAILBOX_HEADER_LEN + data.len();
+    if len > mailbox_size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "mailbox message of {} bytes exceeds the mailbox size {}",
+                len, mailbox_size
+            ),
+        ));
+    }
+    let mut buffer: Vec<u8> = Vec::with_capacity(mailbox_size);
+    header.write_to(&mut buffer)?;
+    buffer.extend_from_slice(data);
+    buffer.resize(mailbox_size, 0);
+    Ok(buffer)
+}
+
+///ADS over EtherCAT: AMS header (with the ADS request or response) in a mailbox message of type AoE
+#[derive(Debug, Clone)]
+pub struct AoeMailbox {
+    pub mailbox_header: MailboxHeader,
+    pub ams_header: AmsHeader,
+}
+
+impl AoeMailbox {
+    ///AoE message to/from the slave with the station address. The length is taken from the AMS header.
+    pub fn new(address: u16, counter: u8, ams_header: AmsHeader) -> io::Result<Self> {
+        let length = u16::try_from(ams_header.to_datagram().len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "AMS header too large for a mailbox",
+            )
+        })?;
+        Ok(AoeMailbox {
+            mailbox_header: MailboxHeader::new(length, address, MailboxType::Aoe, counter),
+            ams_header,
+        })
+    }
+
+    ///Encode padded with zeros to the size of the mailbox (SyncManager length) of the slave
+    pub fn to_mailbox(&self, mailbox_size: usize) -> io::Result<Vec<u8>> {
+        write_mailbox(
+            &self.mailbox_header,
+            &self.ams_header.to_datagram(),
+            mailbox_size,
+        )
+    }
+
+    ///Decode the content of a mailbox. Bytes after the mailbox data (padding) are ignored,
+    ///an error reply of the slave is returned as error with the [MailboxError] description.
+    pub fn from_mailbox(mailbox: &[u8]) -> io::Result<Self> {
+        let (mailbox_header, data) = read_mailbox_data(mailbox, MailboxType::Aoe)?;
+        Ok(AoeMailbox {
+            mailbox_header,
+            ams_header: AmsHeader::from_datagram(data)?,
+        })
+    }
+}
+
+impl WriteTo for AoeMailbox {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        self.mailbox_header.write_to(&mut wtr)?;
+        self.ams_header.write_to(&mut wtr)
+    }
+}
+
+impl ReadFrom for AoeMailbox {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let mailbox_header = MailboxHeader::read_from(read)?;
+        let mut data = vec![0; mailbox_header.length as usize];
+        read.read_exact(&mut data)?;
+        let mut mailbox = Vec::with_capacity(MAILBOX_HEADER_LEN + data.len());
+        mailbox_header.write_to(&mut mailbox)?;
+        mailbox.extend_from_slice(&data);
+        AoeMailbox::from_mailbox(&mailbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AdsError;
+    use crate::proto::ams_address::{AmsAddress, AmsNetId};
+    use crate::proto::request::{ReadDeviceInfoRequest, Request};
+    use crate::proto::response::{ReadDeviceInfoResponse, Response};
+    use crate::proto::state_flags::StateFlags;
+
+    //ReadDeviceInfo request of the master to the slave with station address 1001 (0x03E9)
+    const AOE_REQUEST: [u8; 38] = [
+        0x20, 0x00, 0xE9, 0x03, 0x00,
+        0x11, //mailbox header: length 32, address 1001, AoE, counter 1
+        0x05, 0x14, 0xB4, 0x0A, 0x02, 0x01, 0xE9, 0x03, //target 5.20.180.10.2.1:1001
+        0x05, 0x14, 0xB4, 0x0A, 0x01, 0x01, 0x00, 0x80, //source 5.20.180.10.1.1:32768
+        0x01, 0x00, 0x04, 0x00, //ReadDeviceInfo, request
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //length, error
+        0x07, 0x00, 0x00, 0x00, //invoke id
+    ];
+
+    //Response of the slave, padded to a mailbox of 128 bytes
+    fn aoe_response() -> Vec<u8> {
+        let mut frame = vec![
+            0x38, 0x00, 0xE9, 0x03, 0x00, 0x11, //mailbox header: length 56, AoE, counter 1
+            0x05, 0x14, 0xB4, 0x0A, 0x01, 0x01, 0x00, 0x80, //target 5.20.180.10.1.1:32768
+            0x05, 0x14, 0xB4, 0x0A, 0x02, 0x01, 0xE9, 0x03, //source 5.20.180.10.2.1:1001
+            0x01, 0x00, 0x05, 0x00, //ReadDeviceInfo, response
+            0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, //length 24, error
+            0x07, 0x00, 0x00, 0x00, //invoke id
+            0x00, 0x00, 0x00, 0x00, 0x02, 0x0B, 0xE8, 0x03, //result, version 2.11.1000
+            b'E', b'L', b'6', b'6', b'9', b'5', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, //device name
+        ];
+        frame.resize(128, 0);
+        frame
+    }
+
+    fn slave() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(5, 20, 180, 10, 2, 1), 1001)
+    }
+
+    fn master() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(5, 20, 180, 10, 1, 1), 32768)
+    }
+
+    #[test]
+    fn aoe_mailbox_request_test() {
+        let header = AmsHeader::new(
+            slave(),
+            master(),
+            StateFlags::req_default(),
+            7,
+            Request::ReadDeviceInfo(ReadDeviceInfoRequest::new()),
+        );
+        let mailbox = AoeMailbox::new(1001, 1, header).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        mailbox.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, AOE_REQUEST);
+        assert_eq!(
+            mailbox.to_mailbox(64).unwrap()[..],
+            [&AOE_REQUEST[..], &[0; 26]].concat()[..]
+        );
+        assert!(mailbox.to_mailbox(32).is_err());
+
+        let mut decoded = AoeMailbox::read_from(&mut &AOE_REQUEST[..]).unwrap();
+        assert_eq!(decoded.mailbox_header, mailbox.mailbox_header);
+        assert_eq!(
+            decoded.ams_header.request().unwrap(),
+            Request::ReadDeviceInfo(ReadDeviceInfoRequest::new())
+        );
+    }
+
+    #[test]
+    fn aoe_mailbox_response_test() {
+        let mut mailbox = AoeMailbox::from_mailbox(&aoe_response()).unwrap();
+        assert_eq!(
+            mailbox.mailbox_header,
+            MailboxHeader::new(56, 1001, MailboxType::Aoe, 1)
+        );
+        assert_eq!(mailbox.ams_header.source_address(), &slave());
+        assert_eq!(mailbox.ams_header.invoke_id(), 7);
+        let mut name = [0; 16];
+        name[..6].copy_from_slice(b"EL6695");
+        assert_eq!(
+            mailbox.ams_header.response().unwrap(),
+            Response::ReadDeviceInfo(ReadDeviceInfoResponse::new(
+                AdsError::ErrNoError,
+                2,
+                11,
+                1000,
+                name
+            ))
+        );
+        assert_eq!(mailbox.to_mailbox(128).unwrap(), aoe_response());
+
+        //Truncated mailbox
+        assert!(AoeMailbox::from_mailbox(&aoe_response()[..40]).is_err());
+    }
+
+    #[test]
+    fn mailbox_error_and_type_test() {
+        //Error reply: service not supported
+        let error = [0x04, 0x00, 0xE9, 0x03, 0x00, 0x20, 0x01, 0x00, 0x04, 0x00];
+        let header = MailboxHeader::read_from(&mut &error[..]).unwrap();
+        assert_eq!(header.mailbox_type, MailboxType::Error);
+        assert_eq!(header.counter, 2);
+        let message = AoeMailbox::from_mailbox(&error).unwrap_err().to_string();
+        assert!(message.contains("service not supported"), "{}", message);
+
+        //CoE message
+        let mut coe = AOE_REQUEST;
+        coe[5] = 0x13;
+        assert!(AoeMailbox::from_mailbox(&coe).is_err());
+
+        let header = MailboxHeader {
+            channel: 5,
+            priority: 3,
+            ..MailboxHeader::new(0, 1, MailboxType::Unknown(9), 7)
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        header.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer, [0, 0, 1, 0, 0xC5, 0x79]);
+        assert_eq!(MailboxHeader::read_from(&mut &buffer[..]).unwrap(), header);
+    }
+
+    #[test]
+    fn mailbox_counter_test() {
+        let mut counter = MailboxCounter::new();
+        let sent: Vec<u8> = (0..9).map(|_| counter.next_value()).collect();
+        assert_eq!(sent, [1, 2, 3, 4, 5, 6, 7, 1, 2]);
+
+        let mut received = MailboxCounter::new();
+        assert!(received.received(1));
+        assert!(!received.received(1));
+        assert!(received.received(2));
+        //0 is not counted
+        assert!(received.received(0));
+        assert!(received.received(0));
+    }
+}
@@ -2,10 +2,28 @@
 pub mod ads_state;
 /// enum with different transition modes for device notifications. Used for [AddDeviceNotification](request::AddDeviceNotificationRequest)
 pub mod ads_transition_mode;
+///Trait for fixed size PLC types and the codecs for the primitive types (BOOL, INT, REAL, ...).
+pub mod ads_type;
+///Messages of the TwinCAT UDP system service on port 48899 (discovery, add route).
+pub mod ads_udp;
+///Version of an ADS device (major.minor.build) with ordering, parsing and feature checks.
+pub mod ads_version;
 pub mod ams_address;
 pub mod ams_header;
+///Raw AMS/TCP frames including the router commands (port connect, port close, get local AmsNetId).
+pub mod ams_tcp_frame;
+///CoE objects of EtherCAT slaves, SDO upload/download requests through the EtherCAT master.
+pub mod coe;
 /// enum with commands which can resolve to the command id needed in the AMS header.
 pub mod command_id;
+///EtherCAT slave states and requests to the EtherCAT master device (slave count, addresses, states).
+pub mod ethercat;
+///File functions of the TwinCAT system service (open, read, write, find, mkdir, ...).
+pub mod file_access;
+///Windows FILETIME used as time stamp in device notifications. Conversion from/to SystemTime.
+pub mod file_time;
+///EtherCAT mailbox header and ADS over EtherCAT (AoE) messages wrapping an AMS header.
+pub mod mailbox;
 pub mod proto_traits;
 /// enum containing a specific request and structures holding the data for the specific request (client to server).
 pub mod request;
@@ -15,3 +33,7 @@ pub mod response;
 pub mod state_flags;
 ///Bundle multiple requests or responses to a single read, write or read-write command payload.
 pub mod sumup;
+///Symbol and data type entries of the symbol upload (symbol browsing).
+pub mod symbol;
+///Windows-1252 encoding of the strings in ADS messages (device name, symbol names, file paths).
+pub(crate) mod windows_1252;
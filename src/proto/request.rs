@@ -1,12 +1,15 @@
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{self, Read, Write};
 
-use crate::error::TryIntoError;
+use crate::ads_services::index_group::IndexGroup;
+use crate::error::{NotificationOptionsError, TryIntoError};
 use crate::proto::ads_state::AdsState;
 use crate::proto::ads_transition_mode::AdsTransMode;
+use crate::proto::ads_type::AdsType;
 use crate::proto::command_id::CommandID;
 use crate::proto::proto_traits::{Command, ReadFrom, WriteTo};
 use std::convert::TryInto;
+use std::time::Duration;
 
 /// Each Request enum variant holds the struct with the data needed for a certain command.
 /// The created Request can then be supplied to an [AMS header](super::ams_header).
@@ -332,6 +335,10 @@ impl ReadRequest {
             command_id: CommandID::Read,
         }
     }
+
+    pub fn from_index_group(index_group: IndexGroup, index_offset: u32, length: u32) -> Self {
+        ReadRequest::new(index_group.into(), index_offset, length)
+    }
 }
 
 impl WriteTo for ReadRequest {
@@ -374,6 +381,10 @@ impl WriteRequest {
             command_id: CommandID::Write,
         }
     }
+
+    pub fn from_index_group(index_group: IndexGroup, index_offset: u32, data: Vec<u8>) -> Self {
+        WriteRequest::new(index_group.into(), index_offset, data)
+    }
 }
 
 impl WriteTo for WriteRequest {
@@ -486,6 +497,156 @@ impl AddDeviceNotificationRequest {
             command_id: CommandID::AddDeviceNotification,
         }
     }
+
+    ///Create the request with durations instead of raw 100ns values. See [AddDeviceNotificationBuilder]
+    pub fn builder(index_group: u32, index_offset: u32) -> AddDeviceNotificationBuilder {
+        AddDeviceNotificationBuilder::new(index_group, index_offset)
+    }
+
+    ///max_delay as duration (max_delay is in 100ns units)
+    pub fn max_delay_duration(&self) -> Duration {
+        Duration::from_nanos(self.max_delay as u64 * 100)
+    }
+
+    ///cycle_time as duration (cycle_time is in 100ns units)
+    pub fn cycle_time_duration(&self) -> Duration {
+        Duration::from_nanos(self.cycle_time as u64 * 100)
+    }
+}
+
+///Builder for an AddDeviceNotificationRequest.
+///Takes durations for cycle time and max delay and only allows the transmission modes supported by the ADS server.
+/// ```
+/// use ads_proto::proto::request::AddDeviceNotificationRequest;
+/// use std::time::Duration;
+///
+/// let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+///     .on_change(Duration::from_millis(10))
+///     .max_delay(Duration::from_millis(100))
+///     .build_typed::<u16>()
+///     .unwrap();
+/// assert_eq!(request.length, 2);
+/// assert_eq!(request.cycle_time, 100_000); //100ns units
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddDeviceNotificationBuilder {
+    index_group: u32,
+    index_offset: u32,
+    length: Option<u32>,
+    transmission_mode: Option<AdsTransMode>,
+    max_delay: Duration,
+    cycle_time: Duration,
+}
+
+impl AddDeviceNotificationBuilder {
+    pub fn new(index_group: u32, index_offset: u32) -> Self {
+        AddDeviceNotificationBuilder {
+            index_group,
+            index_offset,
+            length: None,
+            transmission_mode: None,
+            max_delay: Duration::ZERO,
+            cycle_time: Duration::ZERO,
+        }
+    }
+
+    ///Length in bytes of the data to be notified
+    pub fn length(mut self, length: u32) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    ///Notify when the value changed. The value is checked every cycle_time.
+    pub fn on_change(self, cycle_time: Duration) -> Self {
+        self.transmission_mode(AdsTransMode::OnChange, cycle_time)
+    }
+
+    ///Notify every cycle_time
+    pub fn cyclic(self, cycle_time: Duration) -> Self {
+        self.transmission_mode(AdsTransMode::Cyclic, cycle_time)
+    }
+
+    ///Same as on_change but the value is checked in the context of the task
+    pub fn on_change_in_context(self, cycle_time: Duration) -> Self {
+        self.transmission_mode(AdsTransMode::OnChangeInContext, cycle_time)
+    }
+
+    ///Same as cyclic but the value is sampled in the context of the task
+    pub fn cyclic_in_context(self, cycle_time: Duration) -> Self {
+        self.transmission_mode(AdsTransMode::CyclicInContext, cycle_time)
+    }
+
+    pub fn transmission_mode(
+        mut self,
+        transmission_mode: AdsTransMode,
+        cycle_time: Duration,
+    ) -> Self {
+        self.transmission_mode = Some(transmission_mode);
+        self.cycle_time = cycle_time;
+        self
+    }
+
+    ///Samples are sent at the latest after max_delay. Zero sends every sample immediately.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    ///Build the request. The length has to be set, the cyclic modes need a cycle time of at least 100ns.
+    pub fn build(self) -> Result<AddDeviceNotificationRequest, NotificationOptionsError> {
+        let length = self.length.ok_or(NotificationOptionsError::MissingLength)?;
+        let transmission_mode = self
+            .transmission_mode
+            .ok_or(NotificationOptionsError::MissingTransMode)?;
+        if !transmission_mode.is_server_mode() {
+            return Err(NotificationOptionsError::UnsupportedTransMode {
+                mode: transmission_mode,
+            });
+        }
+        let cycle_time = AddDeviceNotificationBuilder::to_100ns(self.cycle_time)?;
+        let cyclic = matches!(
+            transmission_mode,
+            AdsTransMode::Cyclic | AdsTransMode::CyclicInContext
+        );
+        if cyclic && cycle_time == 0 {
+            return Err(NotificationOptionsError::ZeroCycleTime {
+                mode: transmission_mode,
+            });
+        }
+        Ok(AddDeviceNotificationRequest::new(
+            self.index_group,
+            self.index_offset,
+            length,
+            transmission_mode,
+            AddDeviceNotificationBuilder::to_100ns(self.max_delay)?,
+            cycle_time,
+        ))
+    }
+
+    ///Build the request for a value of type T.
+    ///The length is set to the size of T or validated against it if already set.
+    pub fn build_typed<T: AdsType>(
+        mut self,
+    ) -> Result<AddDeviceNotificationRequest, NotificationOptionsError> {
+        match self.length {
+            Some(length) if length != T::SIZE => Err(NotificationOptionsError::LengthMismatch {
+                length,
+                type_size: T::SIZE,
+            }),
+            _ => {
+                self.length = Some(T::SIZE);
+                self.build()
+            }
+        }
+    }
+
+    fn to_100ns(duration: Duration) -> Result<u32, NotificationOptionsError> {
+        let max = Duration::from_nanos(u32::MAX as u64 * 100);
+        if duration > max {
+            return Err(NotificationOptionsError::DurationOutOfRange { duration, max });
+        }
+        Ok((duration.as_nanos() / 100) as u32)
+    }
 }
 
 impl WriteTo for AddDeviceNotificationRequest {
@@ -570,6 +731,15 @@ impl ReadWriteRequest {
             command_id: CommandID::ReadWrite,
         }
     }
+
+    pub fn from_index_group(
+        index_group: IndexGroup,
+        index_offset: u32,
+        read_length: u32,
+        data: Vec<u8>,
+    ) -> Self {
+        ReadWriteRequest::new(index_group.into(), index_offset, read_length, data)
+    }
 }
 
 impl WriteTo for ReadWriteRequest {
@@ -820,6 +990,22 @@ mod tests {
         assert_eq!(compare, buffer);
     }
 
+    #[test]
+    fn request_from_index_group_test() {
+        assert_eq!(
+            ReadRequest::from_index_group(IndexGroup::PlcRwMb, 2, 4),
+            ReadRequest::new(0x4020, 2, 4)
+        );
+        assert_eq!(
+            WriteRequest::from_index_group(IndexGroup::IoImageRwOb, 0, vec![1]),
+            WriteRequest::new(0xF030, 0, vec![1])
+        );
+        assert_eq!(
+            ReadWriteRequest::from_index_group(IndexGroup::SymHndByName, 0, 4, vec![65, 0]),
+            ReadWriteRequest::new(0xF003, 0, 4, vec![65, 0])
+        );
+    }
+
     #[test]
     fn read_request_read_from_test() {
         let reader: Vec<u8> = vec![3, 1, 0, 0, 3, 1, 0, 0, 4, 0, 0, 0];
@@ -1006,6 +1192,98 @@ mod tests {
         );
     }
 
+    #[test]
+    fn add_device_notification_builder_test() {
+        let request = AddDeviceNotificationRequest::builder(259, 259)
+            .cyclic(Duration::from_millis(10))
+            .max_delay(Duration::from_secs(1))
+            .length(4)
+            .build()
+            .unwrap();
+        let compare = AddDeviceNotificationRequest::new(
+            259,
+            259,
+            4,
+            AdsTransMode::Cyclic,
+            10_000_000,
+            100_000,
+        );
+        assert_eq!(request, compare);
+        assert_eq!(request.cycle_time_duration(), Duration::from_millis(10));
+        assert_eq!(request.max_delay_duration(), Duration::from_secs(1));
+
+        let request = AddDeviceNotificationRequest::builder(259, 259)
+            .on_change_in_context(Duration::ZERO)
+            .build_typed::<f64>()
+            .unwrap();
+        assert_eq!(request.length, 8);
+        assert_eq!(request.transmission_mode, AdsTransMode::OnChangeInContext);
+        assert_eq!(request.max_delay, 0);
+    }
+
+    #[test]
+    fn add_device_notification_builder_error_test() {
+        let builder = AddDeviceNotificationRequest::builder(259, 259);
+        assert_eq!(
+            builder.clone().length(4).build(),
+            Err(NotificationOptionsError::MissingTransMode)
+        );
+        assert_eq!(
+            builder.clone().on_change(Duration::ZERO).build(),
+            Err(NotificationOptionsError::MissingLength)
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .transmission_mode(AdsTransMode::ClientOnChange, Duration::ZERO)
+                .length(4)
+                .build(),
+            Err(NotificationOptionsError::UnsupportedTransMode {
+                mode: AdsTransMode::ClientOnChange
+            })
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .on_change(Duration::ZERO)
+                .length(4)
+                .build_typed::<u16>(),
+            Err(NotificationOptionsError::LengthMismatch {
+                length: 4,
+                type_size: 2
+            })
+        );
+        assert_eq!(
+            builder.clone().cyclic(Duration::ZERO).length(4).build(),
+            Err(NotificationOptionsError::ZeroCycleTime {
+                mode: AdsTransMode::Cyclic
+            })
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .cyclic_in_context(Duration::from_nanos(99))
+                .length(4)
+                .build(),
+            Err(NotificationOptionsError::ZeroCycleTime {
+                mode: AdsTransMode::CyclicInContext
+            })
+        );
+        assert!(builder
+            .clone()
+            .on_change(Duration::ZERO)
+            .length(4)
+            .build()
+            .is_ok());
+        assert_eq!(
+            builder.cyclic(Duration::from_secs(430)).length(4).build(),
+            Err(NotificationOptionsError::DurationOutOfRange {
+                duration: Duration::from_secs(430),
+                max: Duration::from_nanos(u32::MAX as u64 * 100)
+            })
+        );
+    }
+
     #[test]
     fn delete_device_notification_request_test() {
         let mut buffer: Vec<u8> = Vec::new();
@@ -1,11 +1,15 @@
 use crate::error::{AdsError, TryIntoError};
 use crate::proto::ads_state::AdsState;
+use crate::proto::ads_version::AdsVersion;
 use crate::proto::command_id::CommandID;
+use crate::proto::file_time::FileTime;
 use crate::proto::proto_traits::{Command, ReadFrom, WriteTo};
+use crate::proto::windows_1252;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::convert::TryInto;
 use std::io::{self, Read, Write};
 use std::string::FromUtf8Error;
+use std::time::SystemTime;
 
 /// Each Response enum variant holds the struct with the data needed for a certain command.
 /// The created Response can then be supplied to an [AMS header](super::ams_header).
@@ -319,26 +323,28 @@ impl ReadDeviceInfoResponse {
         }
     }
 
+    pub fn version(&self) -> AdsVersion {
+        AdsVersion::from(self)
+    }
+
+    ///Device name decoded as Windows-1252 up to the first null byte.
+    ///Every byte is a valid Windows-1252 character, the result is always Ok.
     pub fn get_device_name(&self) -> Result<String, FromUtf8Error> {
-        let name_bytes = self
+        let end = self
             .device_name
-            .to_vec()
             .iter()
-            .filter(|value| **value > 0)
-            .copied()
-            .collect();
-
-        String::from_utf8(name_bytes)
+            .position(|b| *b == 0)
+            .unwrap_or(self.device_name.len());
+        Ok(windows_1252::decode(&self.device_name[..end]))
     }
 
+    ///Windows-1252 encoded device name truncated to 16 bytes.
+    ///Characters which are not in Windows-1252 are replaced with '?'.
     pub fn create_device_name_buf(device_name: &str) -> [u8; 16] {
         let mut device_name_buffer: [u8; 16] = [0; 16];
-        for (n, b) in device_name.as_bytes().iter().enumerate() {
-            if n == device_name_buffer.len() {
-                break;
-            }
-            device_name_buffer[n] = *b;
-        }
+        let encoded = windows_1252::encode(device_name);
+        let length = encoded.len().min(device_name_buffer.len());
+        device_name_buffer[..length].copy_from_slice(&encoded[..length]);
         device_name_buffer
     }
 }
@@ -599,6 +605,30 @@ impl AdsStampHeader {
         }
     }
 
+    ///Create a stamp header with the current time as time stamp
+    pub fn now(notification_samples: Vec<AdsNotificationSample>) -> Self {
+        AdsStampHeader::with_time(FileTime::now(), notification_samples)
+    }
+
+    pub fn with_time(
+        time_stamp: impl Into<FileTime>,
+        notification_samples: Vec<AdsNotificationSample>,
+    ) -> Self {
+        AdsStampHeader {
+            time_stamp: time_stamp.into().as_u64(),
+            samples: notification_samples.len() as u32,
+            notification_samples,
+        }
+    }
+
+    pub fn file_time(&self) -> FileTime {
+        FileTime::new(self.time_stamp)
+    }
+
+    pub fn system_time(&self) -> SystemTime {
+        self.file_time().to_system_time()
+    }
+
     pub fn stamp_len(&self) -> usize {
         let mut len: usize = 0;
         for sample in &self.notification_samples {
@@ -621,13 +651,14 @@ impl ReadFrom for AdsNotificationStream {
     fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
         let length = read.read_u32::<LittleEndian>()?;
         let stamps = read.read_u32::<LittleEndian>()?;
-        let stamp_data_size = ((length - 4) / stamps) as u32; //-4 -> stamps is in length incl. but already read in previous line!
+        //-4 -> stamps is in length incl. but already read in previous line!
+        //The stamp headers can differ in size, read them one after the other
+        let mut buffer: Vec<u8> = vec![0; length.saturating_sub(4) as usize];
+        read.read_exact(&mut buffer)?;
+        let mut buffer = buffer.as_slice();
         let mut ads_stamp_headers: Vec<AdsStampHeader> = Vec::with_capacity(stamps as usize);
-        let mut buffer: Vec<u8> = vec![0; (stamp_data_size) as usize];
         for _ in 0..stamps {
-            read.read_exact(&mut buffer)?;
-            let stamp = AdsStampHeader::read_from(&mut buffer.as_slice())?;
-            ads_stamp_headers.push(stamp);
+            ads_stamp_headers.push(AdsStampHeader::read_from(&mut buffer)?);
         }
 
         Ok(Self {
@@ -661,6 +692,28 @@ impl AdsNotificationStream {
         }
     }
 
+    ///Create the stream and calculate length and stamps from the stamp headers
+    pub fn from_stamp_headers(ads_stamp_headers: Vec<AdsStampHeader>) -> Self {
+        let mut stream =
+            AdsNotificationStream::new(0, ads_stamp_headers.len() as u32, ads_stamp_headers);
+        //length does not include the length field itself
+        stream.length = stream.stream_len() as u32 - 4;
+        stream
+    }
+
+    ///Each sample of the stream with the absolute time stamp of its stamp header
+    pub fn timestamped_samples(
+        &self,
+    ) -> impl Iterator<Item = (SystemTime, &AdsNotificationSample)> + '_ {
+        self.ads_stamp_headers.iter().flat_map(|stamp| {
+            let time = stamp.system_time();
+            stamp
+                .notification_samples
+                .iter()
+                .map(move |sample| (time, sample))
+        })
+    }
+
     pub fn stream_len(&self) -> usize {
         let mut len: usize = 0;
         for stamp in &self.ads_stamp_headers {
@@ -762,6 +815,7 @@ impl ReadWriteResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn response_from_read_device_info() {
@@ -1067,6 +1121,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn read_device_info_device_name_windows_1252_test() {
+        let device_name: [u8; 16] = ReadDeviceInfoResponse::create_device_name_buf("Zähler€ 日");
+        assert_eq!(
+            device_name,
+            [b'Z', 0xE4, b'h', b'l', b'e', b'r', 0x80, b' ', b'?', 0, 0, 0, 0, 0, 0, 0]
+        );
+
+        let device_info_response =
+            ReadDeviceInfoResponse::new(AdsError::ErrNoError, 1, 2, 10, device_name);
+        assert_eq!(device_info_response.get_device_name().unwrap(), "Zähler€ ?");
+    }
+
     #[test]
     fn read_response_test() {
         let response_data: Vec<u8> = vec![4, 0, 0, 0, 2, 0, 0, 0, 255, 2];
@@ -1331,19 +1398,14 @@ mod tests {
         };
 
         //8+4+12+10=34byte
-        let mut notification_samples = Vec::new();
-        notification_samples.push(notification_sample1);
-        notification_samples.push(notification_sample2);
+        let notification_samples = vec![notification_sample1, notification_sample2];
         let stamp_header1 = AdsStampHeader::new(1234567890, 2, notification_samples);
 
         //8+4+16=28byte
-        let mut notification_samples = Vec::new();
-        notification_samples.push(notification_sample3);
+        let notification_samples = vec![notification_sample3];
         let stamp_header2 = AdsStampHeader::new(1234567890, 1, notification_samples);
 
-        let mut stamp_headers = Vec::new();
-        stamp_headers.push(stamp_header1);
-        stamp_headers.push(stamp_header2);
+        let stamp_headers = vec![stamp_header1, stamp_header2];
 
         let mut len: usize = 0;
         for header in &stamp_headers {
@@ -1405,4 +1467,81 @@ mod tests {
 
         assert_eq!(buffer, expected_data, "Data in buffer is not as expected");
     }
+
+    #[test]
+    fn ads_stamp_header_time_test() {
+        let sample = AdsNotificationSample::new(10, vec![1, 0]);
+        let time = UNIX_EPOCH + Duration::from_secs(1_577_836_800);
+        let stamp_header = AdsStampHeader::with_time(time, vec![sample.clone(), sample]);
+
+        assert_eq!(stamp_header.time_stamp, 132_223_104_000_000_000);
+        assert_eq!(stamp_header.samples, 2);
+        assert_eq!(stamp_header.system_time(), time);
+
+        let before = FileTime::now();
+        let stamp_header = AdsStampHeader::now(Vec::new());
+        assert!(stamp_header.file_time() >= before);
+        assert_eq!(stamp_header.samples, 0);
+    }
+
+    #[test]
+    fn ads_notification_stream_different_stamp_size_test() {
+        let stamp_header1 = AdsStampHeader::with_time(
+            FileTime::new(1),
+            vec![
+                AdsNotificationSample::new(1, vec![1, 2]),
+                AdsNotificationSample::new(2, vec![3]),
+            ],
+        );
+        let stamp_header2 = AdsStampHeader::with_time(
+            FileTime::new(2),
+            vec![AdsNotificationSample::new(1, vec![4])],
+        );
+        let stream = AdsNotificationStream::from_stamp_headers(vec![stamp_header1, stamp_header2]);
+        assert_eq!(stream.stamps, 2);
+        assert_eq!(stream.length as usize, stream.stream_len() - 4);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        stream.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), stream.stream_len());
+        let read = AdsNotificationStream::read_from(&mut buffer.as_slice()).unwrap();
+        assert_eq!(read, stream);
+
+        let empty = AdsNotificationStream::from_stamp_headers(Vec::new());
+        let mut buffer: Vec<u8> = Vec::new();
+        empty.write_to(&mut buffer).unwrap();
+        assert_eq!(
+            AdsNotificationStream::read_from(&mut buffer.as_slice()).unwrap(),
+            empty
+        );
+    }
+
+    #[test]
+    fn ads_notification_stream_timestamped_samples_test() {
+        let time_1 = UNIX_EPOCH + Duration::from_secs(10);
+        let time_2 = UNIX_EPOCH + Duration::from_secs(20);
+        let stamp_header1 = AdsStampHeader::with_time(
+            time_1,
+            vec![
+                AdsNotificationSample::new(1, vec![1]),
+                AdsNotificationSample::new(2, vec![2]),
+            ],
+        );
+        let stamp_header2 =
+            AdsStampHeader::with_time(time_2, vec![AdsNotificationSample::new(1, vec![3])]);
+        let stream = AdsNotificationStream::new(0, 2, vec![stamp_header1, stamp_header2]);
+
+        let samples: Vec<(SystemTime, u32, Vec<u8>)> = stream
+            .timestamped_samples()
+            .map(|(time, sample)| (time, sample.notification_handle, sample.data.clone()))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![
+                (time_1, 1, vec![1]),
+                (time_1, 2, vec![2]),
+                (time_2, 1, vec![3])
+            ]
+        );
+    }
 }
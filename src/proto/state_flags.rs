@@ -147,7 +147,7 @@ mod tests {
         let buffer: Vec<u8> = vec![5, 0, 1, 99, 4];
         let state_flags = StateFlags::read_from(&mut buffer.as_slice()).unwrap();
 
-        assert_eq!(state_flags.is_tcp(), true);
+        assert!(state_flags.is_tcp());
     }
 
     #[test]
@@ -155,7 +155,7 @@ mod tests {
         let buffer: Vec<u8> = vec![69, 0, 1, 99, 4];
         let state_flags = StateFlags::read_from(&mut buffer.as_slice()).unwrap();
 
-        assert_eq!(state_flags.is_tcp(), false);
+        assert!(!state_flags.is_tcp());
     }
 
     #[test]
@@ -163,7 +163,7 @@ mod tests {
         let buffer: Vec<u8> = vec![5, 0, 1, 99, 4];
         let state_flags = StateFlags::read_from(&mut buffer.as_slice()).unwrap();
 
-        assert_eq!(state_flags.is_response(), true);
+        assert!(state_flags.is_response());
     }
 
     #[test]
@@ -171,7 +171,7 @@ mod tests {
         let buffer: Vec<u8> = vec![4, 0, 1, 99, 4];
         let state_flags = StateFlags::read_from(&mut buffer.as_slice()).unwrap();
 
-        assert_eq!(state_flags.is_ads_command(), true);
-        assert_eq!(state_flags.is_response(), false);
+        assert!(state_flags.is_ads_command());
+        assert!(!state_flags.is_response());
     }
 }
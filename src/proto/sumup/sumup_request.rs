@@ -1,6 +1,7 @@
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{self, Read, Write};
 
+use crate::ads_services::system_services::ADSIGRP_SUMUP_READEX;
 use crate::proto::command_id::CommandID;
 use crate::proto::proto_traits::{ReadFrom, WriteTo};
 use crate::proto::request::{ReadRequest, ReadWriteRequest, WriteRequest};
@@ -66,6 +67,10 @@ impl SumupReadWriteRequest {
     pub fn request_count(&self) -> u32 {
         self.read_write_requests.len() as u32
     }
+
+    pub fn read_write_requests(&self) -> &[ReadWriteRequest] {
+        &self.read_write_requests
+    }
 }
 
 impl WriteTo for SumupReadWriteRequest {
@@ -100,9 +105,17 @@ impl ReadFrom for SumupReadWriteRequest {
         //Get the access data bytes
         for _ in 0..total_data_len / 16 {
             let access_data = ReadWriteAccessData::read_from(&mut data_buf)?;
-            data_length += access_data.write_length;
-            read_write_access.push(access_data);
             access_data_length += 16;
+            data_length = data_length
+                .checked_add(access_data.write_length)
+                .filter(|l| *l <= total_data_len - access_data_length)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "sumup write length exceeds the request data",
+                    )
+                })?;
+            read_write_access.push(access_data);
             if (total_data_len - data_length - access_data_length) == 0 {
                 break;
             }
@@ -151,6 +164,24 @@ impl SumupReadRequest {
     pub fn request_count(&self) -> u32 {
         self.read_requests.len() as u32
     }
+
+    pub fn read_requests(&self) -> &[ReadRequest] {
+        &self.read_requests
+    }
+
+    ///Create the read write request (ADSIGRP_SUMUP_READEX) which carries this sumup to the device.
+    ///The response data can be parsed with SumupReadResponse::read_from
+    pub fn read_write_request(&self) -> ReadWriteRequest {
+        let mut data: Vec<u8> = Vec::with_capacity(self.read_requests.len() * 12);
+        self.write_to(&mut data)
+            .expect("failed to write sumup request to buffer!");
+        ReadWriteRequest::new(
+            ADSIGRP_SUMUP_READEX.index_group,
+            self.request_count(),
+            self.expected_response_len(),
+            data,
+        )
+    }
 }
 
 impl WriteTo for SumupReadRequest {
@@ -232,6 +263,10 @@ impl SumupWriteRequest {
         self.write_requests.len() as u32
     }
 
+    pub fn write_requests(&self) -> &[WriteRequest] {
+        &self.write_requests
+    }
+
     pub fn expected_response_len(&self) -> u32 {
         self.request_count() * 4
     }
@@ -268,9 +303,17 @@ impl ReadFrom for SumupWriteRequest {
         //Get the access data bytes
         for _ in 0..total_data_len / 12 {
             let access_data = WriteAccessData::read_from(&mut data_buf)?;
-            data_length += access_data.write_length;
-            write_access.push(access_data);
             access_data_length += 12;
+            data_length = data_length
+                .checked_add(access_data.write_length)
+                .filter(|l| *l <= total_data_len - access_data_length)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "sumup write length exceeds the request data",
+                    )
+                })?;
+            write_access.push(access_data);
             if (total_data_len - data_length - access_data_length) == 0 {
                 break;
             }
@@ -453,6 +496,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sumup_read_request_read_write_request_test() {
+        let r_vec = vec![ReadRequest::new(259, 33, 4), ReadRequest::new(260, 22, 2)];
+        let read_write_request = SumupReadRequest::new(r_vec).read_write_request();
+
+        assert_eq!(read_write_request.index_group, 0xF083);
+        assert_eq!(read_write_request.index_offset, 2);
+        assert_eq!(read_write_request.read_length, 22);
+        assert_eq!(
+            read_write_request.data,
+            vec![3, 1, 0, 0, 33, 0, 0, 0, 4, 0, 0, 0, 4, 1, 0, 0, 22, 0, 0, 0, 2, 0, 0, 0]
+        );
+    }
+
     #[test]
     fn sumup_write_request_write_to_test() {
         let mut rw_vec: Vec<WriteRequest> = Vec::new();
@@ -505,4 +562,19 @@ mod tests {
             "comparing sum_read_write_request failed"
         );
     }
+
+    #[test]
+    fn sumup_request_read_from_invalid_length_test() {
+        //Write length larger than the request data
+        let read_data = vec![3, 1, 0, 0, 33, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 7];
+        let error = SumupWriteRequest::read_from(&mut read_data.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let read_data = vec![
+            3, 1, 0, 0, 33, 0, 0, 0, 4, 0, 0, 0, 0xF0, 0xFF, 0xFF, 0xFF, 1, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0x20, 0, 0, 0,
+        ];
+        let error = SumupReadWriteRequest::read_from(&mut read_data.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
 }
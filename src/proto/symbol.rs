@@ -0,0 +1,521 @@
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::windows_1252;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+///Symbol flag: the symbol is persistent
+pub const ADSSYMBOLFLAG_PERSISTENT: u32 = 0x0001;
+///Symbol flag: the symbol is a bit value
+pub const ADSSYMBOLFLAG_BITVALUE: u32 = 0x0002;
+///Data type flag: entry describes a data type
+pub const ADSDATATYPEFLAG_DATATYPE: u32 = 0x0001;
+///Data type flag: entry describes a sub item of a data type
+pub const ADSDATATYPEFLAG_DATAITEM: u32 = 0x0002;
+
+///Base type of a symbol or data type (ADST_*)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AdsDataTypeId {
+    Void,
+    Int16,
+    Int32,
+    Real32,
+    Real64,
+    Int8,
+    UInt8,
+    UInt16,
+    UInt32,
+    Int64,
+    UInt64,
+    String,
+    WString,
+    Real80,
+    Bit,
+    BigType,
+    ///Base type id which is not known
+    Unknown(u32),
+}
+
+impl From<u32> for AdsDataTypeId {
+    fn from(value: u32) -> Self {
+        match value {
+            0 => AdsDataTypeId::Void,
+            2 => AdsDataTypeId::Int16,
+            3 => AdsDataTypeId::Int32,
+            4 => AdsDataTypeId::Real32,
+            5 => AdsDataTypeId::Real64,
+            16 => AdsDataTypeId::Int8,
+            17 => AdsDataTypeId::UInt8,
+            18 => AdsDataTypeId::UInt16,
+            19 => AdsDataTypeId::UInt32,
+            20 => AdsDataTypeId::Int64,
+            21 => AdsDataTypeId::UInt64,
+            30 => AdsDataTypeId::String,
+            31 => AdsDataTypeId::WString,
+            32 => AdsDataTypeId::Real80,
+            33 => AdsDataTypeId::Bit,
+            65 => AdsDataTypeId::BigType,
+            value => AdsDataTypeId::Unknown(value),
+        }
+    }
+}
+
+impl AdsDataTypeId {
+    pub fn as_u32(&self) -> u32 {
+        match self {
+            AdsDataTypeId::Void => 0,
+            AdsDataTypeId::Int16 => 2,
+            AdsDataTypeId::Int32 => 3,
+            AdsDataTypeId::Real32 => 4,
+            AdsDataTypeId::Real64 => 5,
+            AdsDataTypeId::Int8 => 16,
+            AdsDataTypeId::UInt8 => 17,
+            AdsDataTypeId::UInt16 => 18,
+            AdsDataTypeId::UInt32 => 19,
+            AdsDataTypeId::Int64 => 20,
+            AdsDataTypeId::UInt64 => 21,
+            AdsDataTypeId::String => 30,
+            AdsDataTypeId::WString => 31,
+            AdsDataTypeId::Real80 => 32,
+            AdsDataTypeId::Bit => 33,
+            AdsDataTypeId::BigType => 65,
+            AdsDataTypeId::Unknown(value) => *value,
+        }
+    }
+}
+
+///Windows-1252 encoded string with the terminating 0
+fn write_str<W: Write>(wtr: &mut W, value: &str) -> io::Result<()> {
+    wtr.write_all(&windows_1252::encode(value))?;
+    wtr.write_u8(0)
+}
+
+fn read_str<R: Read>(read: &mut R, length: u16) -> io::Result<String> {
+    //+1 for the terminating 0
+    let mut buffer: Vec<u8> = vec![0; length as usize + 1];
+    read.read_exact(&mut buffer)?;
+    buffer.pop();
+    Ok(windows_1252::decode(&buffer))
+}
+
+///Length of the encoded string without the terminating 0
+fn str_len(value: &str) -> usize {
+    windows_1252::encoded_len(value)
+}
+
+///Skip the bytes of an entry not read yet (e.g. extended symbol information)
+fn skip_remaining<R: Read>(read: &mut R, entry_length: u32, read_length: usize) -> io::Result<()> {
+    let remaining = (entry_length as usize).saturating_sub(read_length);
+    io::copy(&mut read.take(remaining as u64), &mut io::sink())?;
+    Ok(())
+}
+
+///Read all entries of an upload (symbol or data type upload)
+pub fn read_entries<T: ReadFrom>(mut data: &[u8]) -> io::Result<Vec<T>> {
+    let mut entries: Vec<T> = Vec::new();
+    while !data.is_empty() {
+        entries.push(T::read_from(&mut data)?);
+    }
+    Ok(entries)
+}
+
+///Symbol entry as returned by the symbol upload (ADSIGRP_SYM_UPLOAD)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdsSymbolEntry {
+    pub index_group: u32,
+    pub index_offset: u32,
+    pub size: u32,
+    pub data_type: AdsDataTypeId,
+    pub flags: u32,
+    pub name: String,
+    pub type_name: String,
+    pub comment: String,
+}
+
+impl AdsSymbolEntry {
+    pub fn new(
+        name: &str,
+        type_name: &str,
+        index_group: u32,
+        index_offset: u32,
+        size: u32,
+        data_type: AdsDataTypeId,
+    ) -> Self {
+        AdsSymbolEntry {
+            index_group,
+            index_offset,
+            size,
+            data_type,
+            flags: 0,
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            comment: String::new(),
+        }
+    }
+
+    ///Length of the entry in bytes
+    pub fn entry_len(&self) -> usize {
+        //6 * u32 + 3 * u16 + 3 terminating 0
+        24 + 6 + str_len(&self.name) + str_len(&self.type_name) + str_len(&self.comment) + 3
+    }
+}
+
+impl WriteTo for AdsSymbolEntry {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.entry_len() as u32)?;
+        wtr.write_u32::<LittleEndian>(self.index_group)?;
+        wtr.write_u32::<LittleEndian>(self.index_offset)?;
+        wtr.write_u32::<LittleEndian>(self.size)?;
+        wtr.write_u32::<LittleEndian>(self.data_type.as_u32())?;
+        wtr.write_u32::<LittleEndian>(self.flags)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.name) as u16)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.type_name) as u16)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.comment) as u16)?;
+        write_str(&mut wtr, &self.name)?;
+        write_str(&mut wtr, &self.type_name)?;
+        write_str(&mut wtr, &self.comment)?;
+        Ok(())
+    }
+}
+
+impl ReadFrom for AdsSymbolEntry {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let entry_length = read.read_u32::<LittleEndian>()?;
+        let index_group = read.read_u32::<LittleEndian>()?;
+        let index_offset = read.read_u32::<LittleEndian>()?;
+        let size = read.read_u32::<LittleEndian>()?;
+        let data_type = AdsDataTypeId::from(read.read_u32::<LittleEndian>()?);
+        let flags = read.read_u32::<LittleEndian>()?;
+        let name_length = read.read_u16::<LittleEndian>()?;
+        let type_length = read.read_u16::<LittleEndian>()?;
+        let comment_length = read.read_u16::<LittleEndian>()?;
+        let entry = AdsSymbolEntry {
+            index_group,
+            index_offset,
+            size,
+            data_type,
+            flags,
+            name: read_str(read, name_length)?,
+            type_name: read_str(read, type_length)?,
+            comment: read_str(read, comment_length)?,
+        };
+        skip_remaining(read, entry_length, entry.entry_len())?;
+        Ok(entry)
+    }
+}
+
+///Array bounds of a data type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdsArrayInfo {
+    pub lower_bound: i32,
+    pub elements: u32,
+}
+
+impl AdsArrayInfo {
+    pub fn new(lower_bound: i32, elements: u32) -> Self {
+        AdsArrayInfo {
+            lower_bound,
+            elements,
+        }
+    }
+}
+
+///Data type entry as returned by the data type upload (ADSIGRP_SYM_DT_UPLOAD).
+///Structures hold their members as sub items, arrays their bounds as array info.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdsDataTypeEntry {
+    pub version: u32,
+    pub size: u32,
+    ///Offset of a sub item within the parent type
+    pub offset: u32,
+    pub data_type: AdsDataTypeId,
+    pub flags: u32,
+    pub name: String,
+    pub type_name: String,
+    pub comment: String,
+    pub array_info: Vec<AdsArrayInfo>,
+    pub sub_items: Vec<AdsDataTypeEntry>,
+}
+
+impl AdsDataTypeEntry {
+    ///Create a data type. type_name is the base type (e.g. "INT" for an ARRAY OF INT or an alias)
+    pub fn new(name: &str, type_name: &str, size: u32, data_type: AdsDataTypeId) -> Self {
+        AdsDataTypeEntry {
+            version: 1,
+            size,
+            offset: 0,
+            data_type,
+            flags: ADSDATATYPEFLAG_DATATYPE,
+            name: name.to_string(),
+            type_name: type_name.to_string(),
+            comment: String::new(),
+            array_info: Vec::new(),
+            sub_items: Vec::new(),
+        }
+    }
+
+    ///Create a member of a structure
+    pub fn sub_item(
+        name: &str,
+        type_name: &str,
+        offset: u32,
+        size: u32,
+        data_type: AdsDataTypeId,
+    ) -> Self {
+        AdsDataTypeEntry {
+            offset,
+            flags: ADSDATATYPEFLAG_DATAITEM,
+            ..AdsDataTypeEntry::new(name, type_name, size, data_type)
+        }
+    }
+
+    ///Length of the entry including the sub items in bytes
+    pub fn entry_len(&self) -> usize {
+        //8 * u32 + 5 * u16 + 3 terminating 0
+        32 + 10
+            + str_len(&self.name)
+            + str_len(&self.type_name)
+            + str_len(&self.comment)
+            + 3
+            + self.array_info.len() * 8
+            + self
+                .sub_items
+                .iter()
+                .map(|item| item.entry_len())
+                .sum::<usize>()
+    }
+}
+
+impl WriteTo for AdsDataTypeEntry {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.entry_len() as u32)?;
+        wtr.write_u32::<LittleEndian>(self.version)?;
+        //hash values are not supported
+        wtr.write_u32::<LittleEndian>(0)?;
+        wtr.write_u32::<LittleEndian>(0)?;
+        wtr.write_u32::<LittleEndian>(self.size)?;
+        wtr.write_u32::<LittleEndian>(self.offset)?;
+        wtr.write_u32::<LittleEndian>(self.data_type.as_u32())?;
+        wtr.write_u32::<LittleEndian>(self.flags)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.name) as u16)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.type_name) as u16)?;
+        wtr.write_u16::<LittleEndian>(str_len(&self.comment) as u16)?;
+        wtr.write_u16::<LittleEndian>(self.array_info.len() as u16)?;
+        wtr.write_u16::<LittleEndian>(self.sub_items.len() as u16)?;
+        write_str(&mut wtr, &self.name)?;
+        write_str(&mut wtr, &self.type_name)?;
+        write_str(&mut wtr, &self.comment)?;
+        for info in &self.array_info {
+            wtr.write_i32::<LittleEndian>(info.lower_bound)?;
+            wtr.write_u32::<LittleEndian>(info.elements)?;
+        }
+        //Write to a buffer first, recursing with &mut W would not terminate for the compiler
+        let mut buffer: Vec<u8> = Vec::new();
+        for item in &self.sub_items {
+            item.write_to(&mut buffer)?;
+        }
+        wtr.write_all(&buffer)
+    }
+}
+
+impl ReadFrom for AdsDataTypeEntry {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        let entry_length = read.read_u32::<LittleEndian>()?;
+        let version = read.read_u32::<LittleEndian>()?;
+        let _hash_value = read.read_u32::<LittleEndian>()?;
+        let _type_hash_value = read.read_u32::<LittleEndian>()?;
+        let size = read.read_u32::<LittleEndian>()?;
+        let offset = read.read_u32::<LittleEndian>()?;
+        let data_type = AdsDataTypeId::from(read.read_u32::<LittleEndian>()?);
+        let flags = read.read_u32::<LittleEndian>()?;
+        let name_length = read.read_u16::<LittleEndian>()?;
+        let type_length = read.read_u16::<LittleEndian>()?;
+        let comment_length = read.read_u16::<LittleEndian>()?;
+        let array_dim = read.read_u16::<LittleEndian>()?;
+        let sub_item_count = read.read_u16::<LittleEndian>()?;
+        let name = read_str(read, name_length)?;
+        let type_name = read_str(read, type_length)?;
+        let comment = read_str(read, comment_length)?;
+        let mut array_info: Vec<AdsArrayInfo> = Vec::with_capacity(array_dim as usize);
+        for _ in 0..array_dim {
+            array_info.push(AdsArrayInfo::new(
+                read.read_i32::<LittleEndian>()?,
+                read.read_u32::<LittleEndian>()?,
+            ));
+        }
+        let mut sub_items: Vec<AdsDataTypeEntry> = Vec::with_capacity(sub_item_count as usize);
+        for _ in 0..sub_item_count {
+            sub_items.push(AdsDataTypeEntry::read_from(read)?);
+        }
+        let entry = AdsDataTypeEntry {
+            version,
+            size,
+            offset,
+            data_type,
+            flags,
+            name,
+            type_name,
+            comment,
+            array_info,
+            sub_items,
+        };
+        skip_remaining(read, entry_length, entry.entry_len())?;
+        Ok(entry)
+    }
+}
+
+///Symbol and data type counts and lengths (ADSIGRP_SYM_UPLOADINFO2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdsSymbolUploadInfo {
+    pub symbol_count: u32,
+    pub symbol_length: u32,
+    pub data_type_count: u32,
+    pub data_type_length: u32,
+    pub extra_count: u32,
+    pub extra_length: u32,
+}
+
+impl WriteTo for AdsSymbolUploadInfo {
+    fn write_to<W: Write>(&self, mut wtr: W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.symbol_count)?;
+        wtr.write_u32::<LittleEndian>(self.symbol_length)?;
+        wtr.write_u32::<LittleEndian>(self.data_type_count)?;
+        wtr.write_u32::<LittleEndian>(self.data_type_length)?;
+        wtr.write_u32::<LittleEndian>(self.extra_count)?;
+        wtr.write_u32::<LittleEndian>(self.extra_length)?;
+        Ok(())
+    }
+}
+
+impl ReadFrom for AdsSymbolUploadInfo {
+    fn read_from<R: Read>(read: &mut R) -> io::Result<Self> {
+        Ok(AdsSymbolUploadInfo {
+            symbol_count: read.read_u32::<LittleEndian>()?,
+            symbol_length: read.read_u32::<LittleEndian>()?,
+            data_type_count: read.read_u32::<LittleEndian>()?,
+            data_type_length: read.read_u32::<LittleEndian>()?,
+            extra_count: read.read_u32::<LittleEndian>()?,
+            extra_length: read.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ads_symbol_entry_write_to_test() {
+        let entry = AdsSymbolEntry::new("MAIN.a", "INT", 0x4020, 4, 2, AdsDataTypeId::Int16);
+        let mut buffer: Vec<u8> = Vec::new();
+        entry.write_to(&mut buffer).unwrap();
+
+        #[rustfmt::skip]
+        let compare: Vec<u8> = vec![
+            //entry length
+            42, 0, 0, 0,
+            //index group, index offset, size
+            32, 64, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0,
+            //data type, flags
+            2, 0, 0, 0, 0, 0, 0, 0,
+            //name, type and comment length
+            6, 0, 3, 0, 0, 0,
+            //MAIN.a\0 INT\0 \0
+            77, 65, 73, 78, 46, 97, 0, 73, 78, 84, 0, 0,
+        ];
+        assert_eq!(buffer, compare);
+        assert_eq!(entry.entry_len(), buffer.len());
+    }
+
+    #[test]
+    fn ads_symbol_entry_read_from_test() {
+        let mut entry = AdsSymbolEntry::new("MAIN.b", "REAL", 0x4020, 8, 4, AdsDataTypeId::Real32);
+        entry.comment = String::from("comment");
+        let mut buffer: Vec<u8> = Vec::new();
+        entry.write_to(&mut buffer).unwrap();
+        //Extended information is skipped
+        buffer[0] += 4;
+        buffer.extend_from_slice(&[1, 2, 3, 4]);
+        let second = AdsSymbolEntry::new("MAIN.c", "BOOL", 0x4020, 12, 1, AdsDataTypeId::Bit);
+        second.write_to(&mut buffer).unwrap();
+
+        let entries: Vec<AdsSymbolEntry> = read_entries(&buffer).unwrap();
+        assert_eq!(entries, vec![entry, second]);
+    }
+
+    #[test]
+    fn ads_symbol_entry_windows_1252_test() {
+        let mut entry =
+            AdsSymbolEntry::new("MAIN.zähler", "UDINT", 0x4020, 0, 4, AdsDataTypeId::UInt32);
+        entry.comment = String::from("Zähler in €");
+        let mut buffer: Vec<u8> = Vec::new();
+        entry.write_to(&mut buffer).unwrap();
+        assert_eq!(entry.entry_len(), buffer.len());
+        //name length and ä as one byte
+        assert_eq!(buffer[24..26], [11, 0]);
+        assert_eq!(buffer[30..41], *b"MAIN.z\xE4hler");
+
+        let entries: Vec<AdsSymbolEntry> = read_entries(&buffer).unwrap();
+        assert_eq!(entries, vec![entry]);
+    }
+
+    #[test]
+    fn ads_data_type_id_test() {
+        assert_eq!(AdsDataTypeId::from(0), AdsDataTypeId::Void);
+        assert_eq!(AdsDataTypeId::from(65), AdsDataTypeId::BigType);
+        assert_eq!(AdsDataTypeId::from(34), AdsDataTypeId::Unknown(34));
+        for value in [0, 2, 19, 33, 34, 65] {
+            assert_eq!(AdsDataTypeId::from(value).as_u32(), value);
+        }
+    }
+
+    #[test]
+    fn ads_data_type_entry_read_write_test() {
+        let mut data_type = AdsDataTypeEntry::new("ST_Test", "", 6, AdsDataTypeId::BigType);
+        data_type.sub_items.push(AdsDataTypeEntry::sub_item(
+            "a",
+            "INT",
+            0,
+            2,
+            AdsDataTypeId::Int16,
+        ));
+        data_type.sub_items.push(AdsDataTypeEntry::sub_item(
+            "b",
+            "REAL",
+            2,
+            4,
+            AdsDataTypeId::Real32,
+        ));
+        let mut array =
+            AdsDataTypeEntry::new("ARRAY [1..3] OF INT", "INT", 6, AdsDataTypeId::Int16);
+        array.array_info.push(AdsArrayInfo::new(1, 3));
+
+        let mut buffer: Vec<u8> = Vec::new();
+        data_type.write_to(&mut buffer).unwrap();
+        assert_eq!(data_type.entry_len(), buffer.len());
+        array.write_to(&mut buffer).unwrap();
+
+        let entries: Vec<AdsDataTypeEntry> = read_entries(&buffer).unwrap();
+        assert_eq!(entries, vec![data_type, array]);
+        assert_eq!(entries[0].sub_items[1].offset, 2);
+        assert_eq!(entries[0].sub_items[1].flags, ADSDATATYPEFLAG_DATAITEM);
+    }
+
+    #[test]
+    fn ads_symbol_upload_info_test() {
+        let info = AdsSymbolUploadInfo {
+            symbol_count: 2,
+            symbol_length: 84,
+            data_type_count: 1,
+            data_type_length: 100,
+            extra_count: 0,
+            extra_length: 0,
+        };
+        let mut buffer: Vec<u8> = Vec::new();
+        info.write_to(&mut buffer).unwrap();
+        assert_eq!(buffer.len(), 24);
+        assert_eq!(
+            AdsSymbolUploadInfo::read_from(&mut buffer.as_slice()).unwrap(),
+            info
+        );
+    }
+}
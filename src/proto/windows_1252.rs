@@ -0,0 +1,57 @@
+use encoding_rs::WINDOWS_1252;
+
+///Decode Windows-1252 bytes. Every byte is a valid character.
+pub(crate) fn decode(bytes: &[u8]) -> String {
+    WINDOWS_1252
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+///Encode as Windows-1252 with one byte per character.
+///Characters which are not in Windows-1252 are replaced with '?'.
+pub(crate) fn encode(value: &str) -> Vec<u8> {
+    let mut utf8 = [0; 4];
+    value
+        .chars()
+        .map(|c| {
+            let (encoded, _, unmappable) = WINDOWS_1252.encode(c.encode_utf8(&mut utf8));
+            if unmappable {
+                b'?'
+            } else {
+                encoded[0]
+            }
+        })
+        .collect()
+}
+
+///Length of the encoded string in bytes
+pub(crate) fn encoded_len(value: &str) -> usize {
+    value.chars().count()
+}
+
+///Compare ignoring the case, also of the non-ASCII letters (e.g. 'Ä' and 'ä')
+pub(crate) fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_1252_test() {
+        let encoded = encode("Zähler€ 日");
+        assert_eq!(
+            encoded,
+            [b'Z', 0xE4, b'h', b'l', b'e', b'r', 0x80, b' ', b'?']
+        );
+        assert_eq!(encoded.len(), encoded_len("Zähler€ 日"));
+        assert_eq!(decode(&encoded), "Zähler€ ?");
+        assert_eq!(decode(&[0x81, 0xFF]), "\u{81}ÿ");
+        assert!(eq_ignore_case("MAIN.Zähler", "main.ZÄHLER"));
+        assert!(!eq_ignore_case("MAIN.Zähler", "MAIN.Zahler"));
+    }
+}
@@ -0,0 +1,811 @@
+use crate::error::AdsError;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::ams_tcp_frame::*;
+use crate::proto::command_id::CommandID;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::state_flags::StateFlags;
+use crate::router::route_table::{Route, RouteTable};
+use crate::server::error_response;
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+///First port assigned to clients requesting any port
+pub const FIRST_DYNAMIC_PORT: u16 = 30000;
+///Timeout to connect to a remote router
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+type SharedWriter = Arc<Mutex<TcpStream>>;
+
+///Connection to a local client or to a remote router
+struct Connection {
+    ///to shut the connection down
+    stream: TcpStream,
+    writer: SharedWriter,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Connection {
+            writer: Arc::new(Mutex::new(stream.try_clone()?)),
+            stream,
+        })
+    }
+}
+
+///Request sent to a remote router and not answered yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct PendingKey {
+    local_port: u16,
+    invoke_id: u32,
+}
+
+#[derive(Debug, Clone)]
+struct PendingRequest {
+    remote: AmsAddress,
+    command_id: CommandID,
+}
+
+struct RouterState {
+    net_id: AmsNetId,
+    routes: Mutex<RouteTable>,
+    ///port -> local connection id
+    ports: Mutex<HashMap<u16, usize>>,
+    local: Mutex<HashMap<usize, Connection>>,
+    upstream: Mutex<HashMap<AmsNetId, Connection>>,
+    pending: Mutex<HashMap<AmsNetId, HashMap<PendingKey, PendingRequest>>>,
+    stop: AtomicBool,
+}
+
+///AMS router for hosts without TwinCAT.
+///Local clients connect over TCP and open a port with the port connect command (0x1000).
+///Frames to the AmsNetId of the router are delivered to the local port,
+///frames to other AmsNetIds are forwarded over one TCP connection per remote AmsNetId (opened on the first frame).
+///Responses and notifications of the remote devices are routed back by the target port (the source port of the request).
+///
+///Requests still pending when a remote connection fails are answered with ErrHostUnreachable,
+///requests to an AmsNetId without route with ErrTargetMachineNotFound.
+/// ```no_run
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// use ads_proto::router::ams_router::AmsRouter;
+///
+/// let router = AmsRouter::new(AmsNetId::new(192, 168, 1, 10, 1, 1));
+/// router.add_route(AmsNetId::new(192, 168, 1, 2, 1, 1), "192.168.1.2:48898".parse().unwrap());
+/// let handle = router.bind("127.0.0.1:48898").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct AmsRouter {
+    state: Arc<RouterState>,
+}
+
+impl AmsRouter {
+    pub fn new(net_id: AmsNetId) -> Self {
+        AmsRouter {
+            state: Arc::new(RouterState {
+                net_id,
+                routes: Mutex::new(RouteTable::new()),
+                ports: Mutex::new(HashMap::new()),
+                local: Mutex::new(HashMap::new()),
+                upstream: Mutex::new(HashMap::new()),
+                pending: Mutex::new(HashMap::new()),
+                stop: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn net_id(&self) -> &AmsNetId {
+        &self.state.net_id
+    }
+
+    ///Add or replace the route to a remote AmsNetId
+    pub fn add_route(&self, net_id: AmsNetId, addr: SocketAddr) {
+        self.state
+            .routes
+            .lock()
+            .expect("router lock poisoned")
+            .add(Route::new(net_id, addr));
+    }
+
+    ///Add all routes of the table. Existing routes with the same AmsNetId are replaced.
+    pub fn add_routes(&self, routes: RouteTable) {
+        self.state
+            .routes
+            .lock()
+            .expect("router lock poisoned")
+            .extend(routes);
+    }
+
+    ///Copy of the current route table
+    pub fn routes(&self) -> RouteTable {
+        self.state
+            .routes
+            .lock()
+            .expect("router lock poisoned")
+            .clone()
+    }
+
+    ///Remove the route and close the connection to the remote router
+    pub fn remove_route(&self, net_id: &AmsNetId) -> bool {
+        let removed = self
+            .state
+            .routes
+            .lock()
+            .expect("router lock poisoned")
+            .remove(net_id)
+            .is_some();
+        self.close_upstream(net_id);
+        removed
+    }
+
+    ///Socket address of the route. Host names are resolved without holding the route table lock.
+    pub fn route(&self, net_id: &AmsNetId) -> Option<SocketAddr> {
+        self.route_entry(net_id)?.resolve().ok()
+    }
+
+    fn route_entry(&self, net_id: &AmsNetId) -> Option<Route> {
+        self.state
+            .routes
+            .lock()
+            .expect("router lock poisoned")
+            .get(net_id)
+            .cloned()
+    }
+
+    ///Ports opened by local clients, sorted ascending
+    pub fn ports(&self) -> Vec<u16> {
+        let mut ports: Vec<u16> = self
+            .state
+            .ports
+            .lock()
+            .expect("router lock poisoned")
+            .keys()
+            .copied()
+            .collect();
+        ports.sort_unstable();
+        ports
+    }
+
+    ///Bind a TcpListener for the local clients. See [AmsRouter::serve]
+    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<RouterHandle> {
+        self.serve(TcpListener::bind(addr)?)
+    }
+
+    ///Accept local clients on a background thread. Every client is served on its own thread.
+    ///The router stops when the returned handle is shut down or dropped.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<RouterHandle> {
+        let local_addr = listener.local_addr()?;
+        let router = self.clone();
+        let thread = thread::spawn(move || {
+            let next_id = AtomicUsize::new(0);
+            for stream in listener.incoming() {
+                if router.state.stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let connection = match stream.and_then(Connection::new) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                let reader = match connection.stream.try_clone() {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
+                let id = next_id.fetch_add(1, Ordering::SeqCst);
+                router
+                    .state
+                    .local
+                    .lock()
+                    .expect("router lock poisoned")
+                    .insert(id, connection);
+                let router = router.clone();
+                thread::spawn(move || {
+                    let _ = router.serve_local(id, reader);
+                    router.remove_local(id);
+                });
+            }
+        });
+        Ok(RouterHandle {
+            local_addr,
+            router: self.clone(),
+            thread: Some(thread),
+        })
+    }
+
+    ///Handle the frames of a local client until the connection is closed
+    fn serve_local(&self, id: usize, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let frame = AmsTcpFrame::read_from(&mut reader)?;
+            match frame.command {
+                AMS_TCP_PORT_AMS_CMD => self.route_frame(frame.ams_header()?),
+                AMS_TCP_PORT_CONNECT => {
+                    let port = self.open_port(id, frame.port()?).ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::AddrInUse, "requested port in use")
+                    })?;
+                    let address = AmsAddress::new(self.state.net_id.clone(), port);
+                    self.send_local(id, &AmsTcpFrame::port_connect_response(&address))?;
+                }
+                AMS_TCP_PORT_CLOSE => {
+                    let port = frame.port()?;
+                    let mut ports = self.state.ports.lock().expect("router lock poisoned");
+                    if ports.get(&port) == Some(&id) {
+                        ports.remove(&port);
+                    }
+                }
+                AMS_TCP_PORT_GET_LOCAL_NETID => {
+                    let frame = AmsTcpFrame::get_local_net_id_response(&self.state.net_id);
+                    self.send_local(id, &frame)?;
+                }
+                _ => (),
+            }
+        }
+    }
+
+    ///Assign the requested port or the first free dynamic port for port 0
+    fn open_port(&self, id: usize, requested: u16) -> Option<u16> {
+        let mut ports = self.state.ports.lock().expect("router lock poisoned");
+        let port = match requested {
+            0 => (FIRST_DYNAMIC_PORT..=u16::MAX).find(|p| !ports.contains_key(p))?,
+            p if ports.contains_key(&p) => return None,
+            p => p,
+        };
+        ports.insert(port, id);
+        Some(port)
+    }
+
+    fn remove_local(&self, id: usize) {
+        self.state
+            .ports
+            .lock()
+            .expect("router lock poisoned")
+            .retain(|_, connection| *connection != id);
+        self.state
+            .local
+            .lock()
+            .expect("router lock poisoned")
+            .remove(&id);
+    }
+
+    fn send_local(&self, id: usize, frame: &AmsTcpFrame) -> io::Result<()> {
+        let writer = self
+            .state
+            .local
+            .lock()
+            .expect("router lock poisoned")
+            .get(&id)
+            .map(|c| c.writer.clone())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "client disconnected"))?;
+        AmsRouter::send(&writer, frame)
+    }
+
+    fn send(writer: &Mutex<TcpStream>, frame: &AmsTcpFrame) -> io::Result<()> {
+        let mut buffer: Vec<u8> = Vec::new();
+        frame.write_to(&mut buffer)?;
+        writer
+            .lock()
+            .expect("router lock poisoned")
+            .write_all(&buffer)
+    }
+
+    ///Deliver a frame of a local client to a local port or forward it to the remote router
+    fn route_frame(&self, header: AmsHeader) {
+        let target = header.target_address().clone();
+        if target.ams_net_id == self.state.net_id {
+            self.deliver_local(header);
+            return;
+        }
+
+        let writer = match self.upstream_writer(&target.ams_net_id) {
+            Ok(w) => w,
+            Err(e) => {
+                self.reject(&header, e);
+                return;
+            }
+        };
+        if !header.state_flags().is_response() {
+            self.state
+                .pending
+                .lock()
+                .expect("router lock poisoned")
+                .entry(target.ams_net_id.clone())
+                .or_default()
+                .insert(
+                    PendingKey {
+                        local_port: header.source_address().port,
+                        invoke_id: header.invoke_id(),
+                    },
+                    PendingRequest {
+                        remote: target.clone(),
+                        command_id: header.command_id(),
+                    },
+                );
+        }
+        if AmsRouter::send(&writer, &AmsTcpFrame::from(header.clone())).is_err() {
+            self.close_upstream_connection(&target.ams_net_id, &writer);
+        }
+    }
+
+    ///Deliver a frame to the local client which opened the target port
+    fn deliver_local(&self, header: AmsHeader) {
+        let target_port = header.target_address().port;
+        let id = self
+            .state
+            .ports
+            .lock()
+            .expect("router lock poisoned")
+            .get(&target_port)
+            .copied();
+        match id {
+            Some(id) => {
+                let _ = self.send_local(id, &AmsTcpFrame::from(header));
+            }
+            None => self.reject(&header, AdsError::ErrTargetPortNotFound),
+        }
+    }
+
+    ///Answer a request which could not be routed with an error response
+    fn reject(&self, header: &AmsHeader, error: AdsError) {
+        if header.state_flags().is_response() {
+            return;
+        }
+        if let Some(response) = error_response(header.command_id(), error) {
+            let response_header = AmsHeader::new(
+                header.source_address().clone(),
+                header.target_address().clone(),
+                StateFlags::resp_default(),
+                header.invoke_id(),
+                response,
+            );
+            //The source of the request is a local client or a remote router
+            if header.source_address().ams_net_id == self.state.net_id {
+                self.deliver_local(response_header);
+            } else if let Some(writer) =
+                self.connected_upstream(&header.source_address().ams_net_id)
+            {
+                //Only over an open connection, an error is not worth a new connection
+                let _ = AmsRouter::send(&writer, &AmsTcpFrame::from(response_header));
+            }
+        }
+    }
+
+    fn connected_upstream(&self, net_id: &AmsNetId) -> Option<SharedWriter> {
+        self.state
+            .upstream
+            .lock()
+            .expect("router lock poisoned")
+            .get(net_id)
+            .map(|c| c.writer.clone())
+    }
+
+    ///Writer of the connection to the remote router. Connects if not connected yet.
+    ///If another client connected to the same router meanwhile, its connection is used.
+    fn upstream_writer(&self, net_id: &AmsNetId) -> Result<SharedWriter, AdsError> {
+        if let Some(writer) = self.connected_upstream(net_id) {
+            return Ok(writer);
+        }
+
+        let addr = self
+            .route_entry(net_id)
+            .ok_or(AdsError::ErrTargetMachineNotFound)?
+            .resolve()
+            .map_err(|_| AdsError::ErrHostUnreachable)?;
+        let connection = TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT)
+            .and_then(Connection::new)
+            .map_err(|_| AdsError::ErrHostUnreachable)?;
+        let reader = connection
+            .stream
+            .try_clone()
+            .map_err(|_| AdsError::ErrHostUnreachable)?;
+        let writer = connection.writer.clone();
+        {
+            let mut upstream = self.state.upstream.lock().expect("router lock poisoned");
+            if let Some(existing) = upstream.get(net_id) {
+                let _ = connection.stream.shutdown(Shutdown::Both);
+                return Ok(existing.writer.clone());
+            }
+            upstream.insert(net_id.clone(), connection);
+        }
+
+        let router = self.clone();
+        let net_id = net_id.clone();
+        let connection_writer = writer.clone();
+        thread::spawn(move || {
+            let _ = router.serve_upstream(reader);
+            router.close_upstream_connection(&net_id, &connection_writer);
+        });
+        Ok(writer)
+    }
+
+    ///Route the frames of a remote router to the local clients until the connection is closed
+    fn serve_upstream(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let frame = AmsTcpFrame::read_from(&mut reader)?;
+            if !frame.is_ams_command() {
+                continue;
+            }
+            let header = frame.ams_header()?;
+            if header.state_flags().is_response() {
+                if let Some(pending) = self
+                    .state
+                    .pending
+                    .lock()
+                    .expect("router lock poisoned")
+                    .get_mut(&header.source_address().ams_net_id)
+                {
+                    pending.remove(&PendingKey {
+                        local_port: header.target_address().port,
+                        invoke_id: header.invoke_id(),
+                    });
+                }
+            }
+            if header.target_address().ams_net_id == self.state.net_id {
+                self.deliver_local(header);
+            }
+        }
+    }
+
+    ///Close the connection if it is still the connection to the remote router.
+    ///A connection replaced meanwhile must not close its successor.
+    fn close_upstream_connection(&self, net_id: &AmsNetId, writer: &SharedWriter) {
+        let connection = {
+            let mut upstream = self.state.upstream.lock().expect("router lock poisoned");
+            match upstream.get(net_id) {
+                Some(c) if Arc::ptr_eq(&c.writer, writer) => upstream.remove(net_id),
+                _ => return,
+            }
+        };
+        if let Some(connection) = connection {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+        self.fail_pending(net_id);
+    }
+
+    ///Close the connection to the remote router and answer its pending requests
+    fn close_upstream(&self, net_id: &AmsNetId) {
+        if let Some(connection) = self
+            .state
+            .upstream
+            .lock()
+            .expect("router lock poisoned")
+            .remove(net_id)
+        {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+        self.fail_pending(net_id);
+    }
+
+    ///Answer the pending requests to the remote router with ErrHostUnreachable
+    fn fail_pending(&self, net_id: &AmsNetId) {
+        let pending = self
+            .state
+            .pending
+            .lock()
+            .expect("router lock poisoned")
+            .remove(net_id)
+            .unwrap_or_default();
+        for (key, request) in pending {
+            if let Some(response) = error_response(request.command_id, AdsError::ErrHostUnreachable)
+            {
+                self.deliver_local(AmsHeader::new(
+                    AmsAddress::new(self.state.net_id.clone(), key.local_port),
+                    request.remote,
+                    StateFlags::resp_default(),
+                    key.invoke_id,
+                    response,
+                ));
+            }
+        }
+    }
+}
+
+///Handle of a running [AmsRouter]. Shuts the router down when dropped.
+pub struct RouterHandle {
+    local_addr: SocketAddr,
+    router: AmsRouter,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RouterHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    ///Stop accepting clients and close all connections
+    pub fn shutdown(mut self) {
+        self.stop_router();
+    }
+
+    fn stop_router(&mut self) {
+        let thread = match self.thread.take() {
+            Some(t) => t,
+            None => return,
+        };
+        let state = &self.router.state;
+        state.stop.store(true, Ordering::SeqCst);
+        //Wake up the blocking accept
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        let _ = TcpStream::connect(wake_addr);
+        let _ = thread.join();
+
+        for connection in state.local.lock().expect("router lock poisoned").values() {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+        for connection in state
+            .upstream
+            .lock()
+            .expect("router lock poisoned")
+            .values()
+        {
+            let _ = connection.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for RouterHandle {
+    fn drop(&mut self) {
+        self.stop_router();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::request::{ReadRequest, Request, WriteRequest};
+    use crate::proto::response::{ReadResponse, Response, WriteResponse};
+    use crate::server::tcp_server::AdsServer;
+    use crate::server::virtual_plc::VirtualPlc;
+
+    fn router_net_id() -> AmsNetId {
+        AmsNetId::new(127, 0, 0, 1, 1, 1)
+    }
+
+    fn plc_net_id() -> AmsNetId {
+        AmsNetId::new(127, 0, 0, 1, 2, 1)
+    }
+
+    fn send_frame(stream: &mut TcpStream, frame: &AmsTcpFrame) {
+        let mut buffer: Vec<u8> = Vec::new();
+        frame.write_to(&mut buffer).unwrap();
+        stream.write_all(&buffer).unwrap();
+    }
+
+    fn connect_port(handle: &RouterHandle, port: u16) -> (TcpStream, AmsAddress) {
+        let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+        send_frame(&mut stream, &AmsTcpFrame::port_connect(port));
+        let response = AmsTcpFrame::read_from(&mut stream).unwrap();
+        assert_eq!(response.command, AMS_TCP_PORT_CONNECT);
+        (stream, response.address().unwrap())
+    }
+
+    fn request(
+        stream: &mut TcpStream,
+        source: &AmsAddress,
+        target: AmsAddress,
+        invoke_id: u32,
+        request: Request,
+    ) -> Response {
+        let header = AmsHeader::new(
+            target,
+            source.clone(),
+            StateFlags::req_default(),
+            invoke_id,
+            request,
+        );
+        send_frame(stream, &AmsTcpFrame::from(header));
+        let mut response = AmsTcpFrame::read_from(stream)
+            .unwrap()
+            .ams_header()
+            .unwrap();
+        assert_eq!(response.invoke_id(), invoke_id);
+        assert_eq!(response.target_address(), source);
+        response.response().unwrap()
+    }
+
+    #[test]
+    fn ams_router_port_connect_test() {
+        let router = AmsRouter::new(router_net_id());
+        let handle = router.bind("127.0.0.1:0").unwrap();
+
+        let (mut stream, address) = connect_port(&handle, 0);
+        assert_eq!(
+            address,
+            AmsAddress::new(router_net_id(), FIRST_DYNAMIC_PORT)
+        );
+        let (_other, address) = connect_port(&handle, 0);
+        assert_eq!(address.port, FIRST_DYNAMIC_PORT + 1);
+        let (_fixed, address) = connect_port(&handle, 851);
+        assert_eq!(address.port, 851);
+        assert_eq!(router.ports(), vec![851, 30000, 30001]);
+
+        send_frame(&mut stream, &AmsTcpFrame::get_local_net_id());
+        let response = AmsTcpFrame::read_from(&mut stream).unwrap();
+        assert_eq!(response.data, router_net_id().net_id().to_vec());
+
+        //Port in use closes the connection
+        let mut duplicate = TcpStream::connect(handle.local_addr()).unwrap();
+        send_frame(&mut duplicate, &AmsTcpFrame::port_connect(851));
+        assert!(AmsTcpFrame::read_from(&mut duplicate).is_err());
+
+        //Ports are released on port close and on disconnect
+        send_frame(&mut stream, &AmsTcpFrame::port_close(FIRST_DYNAMIC_PORT));
+        send_frame(&mut stream, &AmsTcpFrame::get_local_net_id());
+        AmsTcpFrame::read_from(&mut stream).unwrap();
+        assert_eq!(router.ports(), vec![851, 30001]);
+        drop(_fixed);
+        let start = std::time::Instant::now();
+        while router.ports() != vec![30001] {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+        handle.shutdown();
+    }
+
+    #[test]
+    fn ams_router_local_test() {
+        let router = AmsRouter::new(router_net_id());
+        let handle = router.bind("127.0.0.1:0").unwrap();
+        let (mut client, client_address) = connect_port(&handle, 0);
+        let (mut device, device_address) = connect_port(&handle, 851);
+
+        let header = AmsHeader::new(
+            device_address.clone(),
+            client_address.clone(),
+            StateFlags::req_default(),
+            3,
+            Request::Read(ReadRequest::new(0x4020, 0, 1)),
+        );
+        send_frame(&mut client, &AmsTcpFrame::from(header.clone()));
+        let mut received = AmsTcpFrame::read_from(&mut device)
+            .unwrap()
+            .ams_header()
+            .unwrap();
+        assert_eq!(received.source_address(), &client_address);
+        received
+            .update_command(
+                Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![9])),
+                StateFlags::resp_default(),
+            )
+            .unwrap();
+        received.swap_address();
+        send_frame(&mut device, &AmsTcpFrame::from(received));
+        let mut response = AmsTcpFrame::read_from(&mut client)
+            .unwrap()
+            .ams_header()
+            .unwrap();
+        assert_eq!(
+            response.response().unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![9]))
+        );
+
+        //Unknown local port
+        let response = request(
+            &mut client,
+            &client_address,
+            AmsAddress::new(router_net_id(), 852),
+            4,
+            Request::Read(ReadRequest::new(0x4020, 0, 1)),
+        );
+        assert_eq!(
+            response,
+            Response::Read(ReadResponse::new(
+                AdsError::ErrTargetPortNotFound,
+                Vec::new()
+            ))
+        );
+    }
+
+    #[test]
+    fn ams_router_remote_test() {
+        let server = AdsServer::new(plc_net_id());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        let server_handle = server.bind("127.0.0.1:0").unwrap();
+
+        let router = AmsRouter::new(router_net_id());
+        router.add_routes(
+            vec![Route::new(plc_net_id(), server_handle.local_addr())]
+                .into_iter()
+                .collect(),
+        );
+        assert_eq!(router.routes().len(), 1);
+        assert_eq!(
+            router.route(&plc_net_id()),
+            Some(server_handle.local_addr())
+        );
+        let handle = router.bind("127.0.0.1:0").unwrap();
+        let (mut client, address) = connect_port(&handle, 0);
+
+        let plc = AmsAddress::new(plc_net_id(), 851);
+        let response = request(
+            &mut client,
+            &address,
+            plc.clone(),
+            1,
+            Request::Write(WriteRequest::new(0x4020, 0, vec![1, 2])),
+        );
+        assert_eq!(
+            response,
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        let response = request(
+            &mut client,
+            &address,
+            plc.clone(),
+            2,
+            Request::Read(ReadRequest::new(0x4020, 0, 2)),
+        );
+        assert_eq!(
+            response,
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![1, 2]))
+        );
+
+        //No route
+        let response = request(
+            &mut client,
+            &address,
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 851),
+            3,
+            Request::Read(ReadRequest::new(0x4020, 0, 2)),
+        );
+        assert_eq!(
+            response,
+            Response::Read(ReadResponse::new(
+                AdsError::ErrTargetMachineNotFound,
+                Vec::new()
+            ))
+        );
+
+        //Remote router not reachable
+        server_handle.shutdown();
+        assert!(router.remove_route(&plc_net_id()));
+        let unused = TcpListener::bind("127.0.0.1:0").unwrap();
+        router.add_route(plc_net_id(), unused.local_addr().unwrap());
+        drop(unused);
+        let response = request(
+            &mut client,
+            &address,
+            plc,
+            4,
+            Request::Read(ReadRequest::new(0x4020, 0, 2)),
+        );
+        assert_eq!(
+            response,
+            Response::Read(ReadResponse::new(AdsError::ErrHostUnreachable, Vec::new()))
+        );
+        handle.shutdown();
+    }
+
+    #[test]
+    fn ams_router_concurrent_connect_test() {
+        let server = AdsServer::new(plc_net_id());
+        let server_handle = server.bind("127.0.0.1:0").unwrap();
+        let router = AmsRouter::new(router_net_id());
+        router.add_route(plc_net_id(), server_handle.local_addr());
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let router = router.clone();
+                thread::spawn(move || router.upstream_writer(&plc_net_id()).unwrap())
+            })
+            .collect();
+        let writers: Vec<SharedWriter> = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        let current = router.connected_upstream(&plc_net_id()).unwrap();
+        assert!(writers.iter().all(|w| Arc::ptr_eq(w, &current)));
+
+        //A stale connection does not close the current one
+        let stale = Arc::new(Mutex::new(
+            TcpStream::connect(server_handle.local_addr()).unwrap(),
+        ));
+        router.close_upstream_connection(&plc_net_id(), &stale);
+        assert!(router.connected_upstream(&plc_net_id()).is_some());
+        router.close_upstream_connection(&plc_net_id(), &current);
+        assert!(router.connected_upstream(&plc_net_id()).is_none());
+    }
+}
@@ -0,0 +1,2 @@
+pub mod ams_router;
+pub mod route_table;
@@ -0,0 +1,636 @@
+use crate::error::{AmsAddressError, RouteTableError};
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::server::tcp_server::ADS_TCP_PORT;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::Path;
+use std::str::FromStr;
+
+///Address of a remote AMS router. Host names are kept and only resolved by [RouteAddress::resolve].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteAddress {
+    Socket(SocketAddr),
+    Host { host: String, port: u16 },
+}
+
+impl RouteAddress {
+    ///Parse "ip", "ip:port", "host" or "host:port" without resolving. The port defaults to 48898.
+    pub fn parse(address: &str) -> Result<Self, RouteTableError> {
+        if let Ok(addr) = address.parse::<SocketAddr>() {
+            return Ok(RouteAddress::Socket(addr));
+        }
+        if let Ok(ip) = address.parse::<IpAddr>() {
+            return Ok(RouteAddress::Socket(SocketAddr::new(ip, ADS_TCP_PORT)));
+        }
+        let invalid = || RouteTableError::InvalidAddress {
+            address: address.to_string(),
+        };
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().map_err(|_| invalid())?),
+            None => (address, ADS_TCP_PORT),
+        };
+        let valid_host = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_'));
+        if !valid_host {
+            return Err(invalid());
+        }
+        Ok(RouteAddress::Host {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    ///Socket address, host names are resolved (blocking) on every call
+    pub fn resolve(&self) -> Result<SocketAddr, RouteTableError> {
+        match self {
+            RouteAddress::Socket(addr) => Ok(*addr),
+            RouteAddress::Host { host, port } => (host.as_str(), *port)
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| RouteTableError::InvalidAddress {
+                    address: format!("{}:{}", host, port),
+                }),
+        }
+    }
+}
+
+impl From<SocketAddr> for RouteAddress {
+    fn from(addr: SocketAddr) -> Self {
+        RouteAddress::Socket(addr)
+    }
+}
+
+///Route to a remote AMS router
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Route {
+    pub name: Option<String>,
+    pub net_id: AmsNetId,
+    pub address: RouteAddress,
+}
+
+impl Route {
+    pub fn new(net_id: AmsNetId, addr: SocketAddr) -> Self {
+        Route {
+            name: None,
+            net_id,
+            address: RouteAddress::Socket(addr),
+        }
+    }
+
+    pub fn with_name(net_id: AmsNetId, addr: SocketAddr, name: &str) -> Self {
+        Route {
+            name: Some(name.to_string()),
+            net_id,
+            address: RouteAddress::Socket(addr),
+        }
+    }
+
+    ///Parse a route from strings as used in route files.
+    ///The address is an ip address or host name with an optional port (default 48898).
+    ///Host names are not resolved here, see [RouteAddress::resolve].
+    pub fn parse(name: Option<&str>, net_id: &str, address: &str) -> Result<Self, RouteTableError> {
+        let net_id =
+            AmsNetId::from_str(net_id.trim()).map_err(|e| RouteTableError::InvalidNetId {
+                net_id: net_id.to_string(),
+                source: e,
+            })?;
+        Ok(Route {
+            name: name.map(|n| n.to_string()),
+            net_id,
+            address: RouteAddress::parse(address.trim())?,
+        })
+    }
+
+    ///Socket address of the route, host names are resolved (blocking)
+    pub fn resolve(&self) -> Result<SocketAddr, RouteTableError> {
+        self.address.resolve()
+    }
+}
+
+///Static routes: AmsNetId -> socket address of the remote AMS router.
+///Can be loaded from a JSON (feature json) or TOML (feature toml) config file
+///and from a TwinCAT StaticRoutes.xml (feature xml).
+///
+///Config file format (TOML, JSON uses the same structure):
+///```toml
+///[[route]]
+///name = "PLC1"               #optional
+///net_id = "192.168.1.2.1.1"
+///address = "192.168.1.2"     #ip or host name, optional port (default 48898)
+///```
+/// ```
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// use ads_proto::router::route_table::{Route, RouteTable};
+///
+/// let mut table = RouteTable::new();
+/// let net_id = AmsNetId::new(192, 168, 1, 2, 1, 1);
+/// table.add(Route::new(net_id.clone(), "192.168.1.2:48898".parse().unwrap()));
+/// assert_eq!(table.addr(&net_id), Some("192.168.1.2:48898".parse().unwrap()));
+/// assert_eq!(table.net_id(&"192.168.1.2:50000".parse().unwrap()), Some(&net_id));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RouteTable {
+    routes: HashMap<AmsNetId, Route>,
+    ///Socket addresses of the routes with a host name, see [RouteTable::resolve_hosts]
+    resolved: HashMap<AmsNetId, SocketAddr>,
+}
+
+impl RouteTable {
+    pub fn new() -> Self {
+        RouteTable::default()
+    }
+
+    ///Add a route. Returns the replaced route with the same AmsNetId.
+    pub fn add(&mut self, route: Route) -> Option<Route> {
+        self.resolved.remove(&route.net_id);
+        self.routes.insert(route.net_id.clone(), route)
+    }
+
+    pub fn remove(&mut self, net_id: &AmsNetId) -> Option<Route> {
+        self.resolved.remove(net_id);
+        self.routes.remove(net_id)
+    }
+
+    ///Add all routes of another table. Routes with the same AmsNetId are replaced.
+    pub fn extend(&mut self, other: RouteTable) {
+        for route in other.routes.into_values() {
+            self.add(route);
+        }
+        self.resolved.extend(other.resolved);
+    }
+
+    pub fn get(&self, net_id: &AmsNetId) -> Option<&Route> {
+        self.routes.get(net_id)
+    }
+
+    ///Socket address of the route, host names are resolved (blocking)
+    pub fn addr(&self, net_id: &AmsNetId) -> Option<SocketAddr> {
+        self.get(net_id).and_then(|r| r.resolve().ok())
+    }
+
+    ///Route by name (case-insensitive)
+    pub fn by_name(&self, name: &str) -> Option<&Route> {
+        self.routes.values().find(|r| {
+            r.name
+                .as_deref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        })
+    }
+
+    ///Resolve the host names of the routes (blocking) for the reverse lookup.
+    ///Call it again to pick up changed addresses of the hosts.
+    pub fn resolve_hosts(&mut self) {
+        self.resolved = self
+            .routes
+            .values()
+            .filter(|r| matches!(r.address, RouteAddress::Host { .. }))
+            .filter_map(|r| r.resolve().ok().map(|addr| (r.net_id.clone(), addr)))
+            .collect();
+    }
+
+    ///Reverse lookup. A route with the same socket address is preferred,
+    ///otherwise the ip address is compared only (connections from the remote router use another port).
+    ///If several routes match, the one with the lowest AmsNetId is taken.
+    ///Host names are not resolved here, routes with a host name only match after [RouteTable::resolve_hosts].
+    pub fn net_id(&self, addr: &SocketAddr) -> Option<&AmsNetId> {
+        let routes: Vec<(&Route, SocketAddr)> = self
+            .routes()
+            .into_iter()
+            .filter_map(|r| match r.address {
+                RouteAddress::Socket(route_addr) => Some((r, route_addr)),
+                RouteAddress::Host { .. } => self.resolved.get(&r.net_id).map(|a| (r, *a)),
+            })
+            .collect();
+        routes
+            .iter()
+            .find(|(_, route_addr)| route_addr == addr)
+            .or_else(|| {
+                routes
+                    .iter()
+                    .find(|(_, route_addr)| route_addr.ip() == addr.ip())
+            })
+            .map(|(r, _)| &r.net_id)
+    }
+
+    ///AmsAddress of a connection from `addr` with the port of the socket address as AMS port.
+    ///Without a route the IPv4 address with the suffix .1.1 is taken as AmsNetId,
+    ///IPv6 addresses are only accepted if they are IPv4 mapped.
+    pub fn ams_address(&self, addr: &SocketAddr) -> Result<AmsAddress, AmsAddressError> {
+        let net_id = match self.net_id(addr) {
+            Some(net_id) => net_id.clone(),
+            None => AmsNetId::from_ip(addr.ip(), AmsNetId::DEFAULT_SUFFIX)?,
+        };
+        Ok(AmsAddress::new(net_id, addr.port()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    ///Routes sorted by AmsNetId
+    pub fn routes(&self) -> Vec<&Route> {
+        let mut routes: Vec<&Route> = self.routes.values().collect();
+        routes.sort_by_key(|r| r.net_id.net_id());
+        routes
+    }
+
+    ///Load a route file. The format is selected by the file extension (json, toml or xml).
+    #[cfg_attr(
+        not(any(feature = "json", feature = "toml", feature = "xml")),
+        allow(unused_variables)
+    )]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RouteTableError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| RouteTableError::Io {
+            message: format!("{}: {}", path.display(), e),
+        })?;
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            #[cfg(feature = "json")]
+            "json" => RouteTable::from_json(&content),
+            #[cfg(feature = "toml")]
+            "toml" => RouteTable::from_toml(&content),
+            #[cfg(feature = "xml")]
+            "xml" => RouteTable::from_static_routes_xml(&content),
+            _ => Err(RouteTableError::Parse {
+                message: format!("unsupported route file {}", path.display()),
+            }),
+        }
+    }
+
+    #[cfg(any(feature = "json", feature = "toml"))]
+    fn from_config(config: RouteConfig) -> Result<Self, RouteTableError> {
+        let mut table = RouteTable::new();
+        for route in config.route {
+            table.add(Route::parse(
+                route.name.as_deref(),
+                &route.net_id,
+                &route.address,
+            )?);
+        }
+        Ok(table)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, RouteTableError> {
+        let config: RouteConfig =
+            serde_json::from_str(json).map_err(|e| RouteTableError::Parse {
+                message: e.to_string(),
+            })?;
+        RouteTable::from_config(config)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Self, RouteTableError> {
+        let config: RouteConfig = toml::from_str(toml).map_err(|e| RouteTableError::Parse {
+            message: e.to_string(),
+        })?;
+        RouteTable::from_config(config)
+    }
+
+    ///Load the routes of a TwinCAT StaticRoutes.xml (TcConfig/RemoteConnections/Route)
+    #[cfg(feature = "xml")]
+    pub fn from_static_routes_xml(xml: &str) -> Result<Self, RouteTableError> {
+        use quick_xml::events::Event;
+        use quick_xml::Reader;
+
+        let parse_error = |e: &dyn std::fmt::Display| RouteTableError::Parse {
+            message: e.to_string(),
+        };
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+        //Self-closing elements like <Route/> or <Name/> as start and end event
+        reader.config_mut().expand_empty_elements = true;
+
+        let mut table = RouteTable::new();
+        let mut path: Vec<String> = Vec::new();
+        //Name, Address, NetId of the current route
+        let mut fields: [Option<String>; 3] = Default::default();
+        let mut index = 0;
+        loop {
+            match reader.read_event().map_err(|e| parse_error(&e))? {
+                Event::Start(e) => {
+                    path.push(String::from_utf8_lossy(e.local_name().as_ref()).into_owned());
+                    if path.ends_with(&["RemoteConnections".into(), "Route".into()]) {
+                        fields = Default::default();
+                    }
+                }
+                Event::Text(text) => {
+                    let in_route = path.len() >= 2 && path[path.len() - 2] == "Route";
+                    let field = match path.last().map(|p| p.as_str()) {
+                        Some("Name") => 0,
+                        Some("Address") => 1,
+                        Some("NetId") => 2,
+                        _ => continue,
+                    };
+                    if in_route {
+                        let text = text.unescape().map_err(|e| parse_error(&e))?;
+                        fields[field] = Some(text.into_owned());
+                    }
+                }
+                Event::End(_) => {
+                    if path.ends_with(&["RemoteConnections".into(), "Route".into()]) {
+                        let [name, address, net_id] = std::mem::take(&mut fields);
+                        let address = address.ok_or(RouteTableError::MissingField {
+                            route: index,
+                            field: "Address",
+                        })?;
+                        let net_id = net_id.ok_or(RouteTableError::MissingField {
+                            route: index,
+                            field: "NetId",
+                        })?;
+                        table.add(Route::parse(name.as_deref(), &net_id, &address)?);
+                        index += 1;
+                    }
+                    path.pop();
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        Ok(table)
+    }
+}
+
+///Same socket address first, then the same ip address
+impl FromIterator<Route> for RouteTable {
+    fn from_iter<I: IntoIterator<Item = Route>>(iter: I) -> Self {
+        let mut table = RouteTable::new();
+        for route in iter {
+            table.add(route);
+        }
+        table
+    }
+}
+
+///Structure of the JSON and TOML route files
+#[cfg(any(feature = "json", feature = "toml"))]
+#[derive(serde::Deserialize)]
+struct RouteConfig {
+    #[serde(default)]
+    route: Vec<RouteConfigEntry>,
+}
+
+#[cfg(any(feature = "json", feature = "toml"))]
+#[derive(serde::Deserialize)]
+struct RouteConfigEntry {
+    name: Option<String>,
+    net_id: String,
+    address: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plc() -> AmsNetId {
+        AmsNetId::new(192, 168, 1, 2, 1, 1)
+    }
+
+    #[test]
+    fn route_table_lookup_test() {
+        let mut table: RouteTable = vec![
+            Route::with_name(plc(), "192.168.1.2:48898".parse().unwrap(), "PLC1"),
+            Route::new(
+                AmsNetId::new(10, 0, 0, 5, 1, 1),
+                "10.0.0.5:48898".parse().unwrap(),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(table.len(), 2);
+        assert_eq!(
+            table.addr(&plc()),
+            Some("192.168.1.2:48898".parse().unwrap())
+        );
+        assert_eq!(table.by_name("plc1").unwrap().net_id, plc());
+        assert!(table.by_name("PLC2").is_none());
+
+        //Reverse lookup by socket address or ip
+        assert_eq!(
+            table.net_id(&"192.168.1.2:48898".parse().unwrap()),
+            Some(&plc())
+        );
+        assert_eq!(
+            table.net_id(&"192.168.1.2:51234".parse().unwrap()),
+            Some(&plc())
+        );
+        assert_eq!(table.net_id(&"192.168.1.3:48898".parse().unwrap()), None);
+        assert_eq!(
+            table.ams_address(&"192.168.1.2:851".parse().unwrap()),
+            Ok(AmsAddress::new(plc(), 851))
+        );
+        //Without a route
+        assert_eq!(
+            table.ams_address(&"192.168.1.3:30000".parse().unwrap()),
+            Ok(AmsAddress::new(AmsNetId::new(192, 168, 1, 3, 1, 1), 30000))
+        );
+        assert!(table
+            .ams_address(&"[fe80::1]:48898".parse().unwrap())
+            .is_err());
+
+        //Several routes to the same ip, the lowest AmsNetId is taken
+        let cx = AmsNetId::new(192, 168, 1, 2, 2, 1);
+        table.add(Route::new(cx.clone(), "192.168.1.2:48899".parse().unwrap()));
+        assert_eq!(
+            table.net_id(&"192.168.1.2:51234".parse().unwrap()),
+            Some(&plc())
+        );
+        assert_eq!(
+            table.net_id(&"192.168.1.2:48899".parse().unwrap()),
+            Some(&cx)
+        );
+        table.remove(&cx);
+
+        assert_eq!(
+            table
+                .routes()
+                .iter()
+                .map(|r| r.net_id.clone())
+                .collect::<Vec<_>>(),
+            vec![AmsNetId::new(10, 0, 0, 5, 1, 1), plc()]
+        );
+        assert!(table.remove(&plc()).is_some());
+        assert!(table.get(&plc()).is_none());
+    }
+
+    #[test]
+    fn route_parse_test() {
+        let route = Route::parse(None, "192.168.1.2.1.1", "192.168.1.2").unwrap();
+        assert_eq!(
+            route.resolve().unwrap(),
+            "192.168.1.2:48898".parse().unwrap()
+        );
+        let route = Route::parse(Some("PLC"), "192.168.1.2.1.1", "127.0.0.1:10000").unwrap();
+        assert_eq!(route.resolve().unwrap(), "127.0.0.1:10000".parse().unwrap());
+        assert_eq!(route.name.as_deref(), Some("PLC"));
+        let route = Route::parse(None, "192.168.1.2.1.1", "[::1]:48898").unwrap();
+        assert!(route.resolve().unwrap().is_ipv6());
+
+        //Host names are kept and resolved on use
+        let route = Route::parse(None, "192.168.1.2.1.1", "plc-1.example.invalid:851").unwrap();
+        assert_eq!(
+            route.address,
+            RouteAddress::Host {
+                host: "plc-1.example.invalid".to_string(),
+                port: 851
+            }
+        );
+        let route = Route::parse(None, "192.168.1.2.1.1", "localhost").unwrap();
+        assert_eq!(route.resolve().unwrap().port(), 48898);
+        assert!(route.resolve().unwrap().ip().is_loopback());
+
+        assert!(matches!(
+            Route::parse(None, "192.168.1", "192.168.1.2"),
+            Err(RouteTableError::InvalidNetId { .. })
+        ));
+        assert!(matches!(
+            Route::parse(None, "192.168.1.2.1.1", "not a host name!"),
+            Err(RouteTableError::InvalidAddress { .. })
+        ));
+        assert!(matches!(
+            Route::parse(None, "192.168.1.2.1.1", "plc:port"),
+            Err(RouteTableError::InvalidAddress { .. })
+        ));
+    }
+
+    #[test]
+    fn route_table_host_name_lookup_test() {
+        let mut table = RouteTable::new();
+        table.add(Route::parse(None, "192.168.1.2.1.1", "localhost:851").unwrap());
+        assert!(table.addr(&plc()).unwrap().ip().is_loopback());
+        //Not resolved by the reverse lookup
+        assert_eq!(table.net_id(&table.addr(&plc()).unwrap()), None);
+        table.resolve_hosts();
+        assert_eq!(table.net_id(&table.addr(&plc()).unwrap()), Some(&plc()));
+        assert_eq!(table.net_id(&"10.0.0.5:48898".parse().unwrap()), None);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn route_table_json_test() {
+        let table = RouteTable::from_json(
+            r#"{"route": [
+                {"name": "PLC1", "net_id": "192.168.1.2.1.1", "address": "192.168.1.2"},
+                {"net_id": "10.0.0.5.1.1", "address": "10.0.0.5:48899"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(&plc()).unwrap().name.as_deref(), Some("PLC1"));
+        assert_eq!(
+            table.addr(&AmsNetId::new(10, 0, 0, 5, 1, 1)),
+            Some("10.0.0.5:48899".parse().unwrap())
+        );
+        assert!(matches!(
+            RouteTable::from_json(r#"{"route": [{"name": "PLC1"}]}"#),
+            Err(RouteTableError::Parse { .. })
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn route_table_toml_test() {
+        let table = RouteTable::from_toml(
+            r#"
+            [[route]]
+            name = "PLC1"
+            net_id = "192.168.1.2.1.1"
+            address = "192.168.1.2"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            table.addr(&plc()),
+            Some("192.168.1.2:48898".parse().unwrap())
+        );
+        assert!(RouteTable::from_toml("").unwrap().is_empty());
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn route_table_static_routes_xml_test() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <TcConfig xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <Local><Name>HOST</Name></Local>
+                <RemoteConnections>
+                    <Route>
+                        <Name>PLC1</Name>
+                        <Address>192.168.1.2</Address>
+                        <NetId>192.168.1.2.1.1</NetId>
+                        <Type>TCP_IP</Type>
+                    </Route>
+                    <Route>
+                        <Name>CX &amp; Co</Name>
+                        <Address>10.0.0.5</Address>
+                        <NetId RegQuery="1">10.0.0.5.1.1</NetId>
+                        <Flags>64</Flags>
+                    </Route>
+                </RemoteConnections>
+            </TcConfig>"#;
+        let table = RouteTable::from_static_routes_xml(xml).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.by_name("PLC1").unwrap().net_id, plc());
+        assert_eq!(
+            table.by_name("CX & Co").unwrap().resolve().unwrap(),
+            "10.0.0.5:48898".parse().unwrap()
+        );
+
+        //Self-closing elements
+        let xml = r#"<TcConfig>
+                <Local/>
+                <RemoteConnections>
+                    <Route>
+                        <Name/>
+                        <Address>192.168.1.2</Address>
+                        <NetId>192.168.1.2.1.1</NetId>
+                        <Flags/>
+                    </Route>
+                </RemoteConnections>
+            </TcConfig>"#;
+        let table = RouteTable::from_static_routes_xml(xml).unwrap();
+        assert_eq!(table.get(&plc()).unwrap().name, None);
+        assert!(
+            RouteTable::from_static_routes_xml("<TcConfig><RemoteConnections/></TcConfig>")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            RouteTable::from_static_routes_xml(
+                "<TcConfig><RemoteConnections><Route/></RemoteConnections></TcConfig>"
+            ),
+            Err(RouteTableError::MissingField {
+                route: 0,
+                field: "Address"
+            })
+        );
+
+        let xml = "<TcConfig><RemoteConnections><Route><Name>X</Name><Address>10.0.0.5</Address></Route></RemoteConnections></TcConfig>";
+        assert_eq!(
+            RouteTable::from_static_routes_xml(xml),
+            Err(RouteTableError::MissingField {
+                route: 0,
+                field: "NetId"
+            })
+        );
+    }
+
+    #[test]
+    fn route_table_load_test() {
+        assert!(matches!(
+            RouteTable::load("does/not/exist.toml"),
+            Err(RouteTableError::Io { .. })
+        ));
+    }
+}
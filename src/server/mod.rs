@@ -0,0 +1,247 @@
+#[cfg(feature = "mqtt")]
+pub mod mqtt_server;
+pub mod notification_engine;
+pub mod sumup_dispatch;
+pub mod symbol_server;
+pub mod tcp_server;
+#[cfg(feature = "tls")]
+pub mod tls_server;
+pub mod udp_server;
+pub mod virtual_plc;
+
+use crate::error::AdsError;
+use crate::proto::ads_state::AdsState;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::command_id::CommandID;
+use crate::proto::request::*;
+use crate::proto::response::*;
+use std::time::Instant;
+
+///An ADS device answering the requests sent to its AMS port.
+///Every command has a default implementation returning AdsErrDeviceSrvNotSupp, implement the ones the device supports.
+///`source` is the AmsAddress of the client which sent the request.
+/// ```
+/// use ads_proto::error::AdsError;
+/// use ads_proto::proto::ams_address::AmsAddress;
+/// use ads_proto::proto::request::ReadRequest;
+/// use ads_proto::proto::response::ReadResponse;
+/// use ads_proto::server::AdsDevice;
+///
+/// struct Counter(u32);
+///
+/// impl AdsDevice for Counter {
+///     fn read(&mut self, _source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+///         self.0 += 1;
+///         ReadResponse::new(AdsError::ErrNoError, self.0.to_le_bytes().to_vec())
+///     }
+/// }
+/// ```
+pub trait AdsDevice: Send {
+    fn read_device_info(&mut self, _source: &AmsAddress) -> ReadDeviceInfoResponse {
+        ReadDeviceInfoResponse::new(AdsError::AdsErrDeviceSrvNotSupp, 0, 0, 0, [0; 16])
+    }
+
+    fn read_state(&mut self, _source: &AmsAddress) -> ReadStateResponse {
+        ReadStateResponse::new(
+            AdsError::AdsErrDeviceSrvNotSupp,
+            AdsState::AdsStateInvalid,
+            0,
+        )
+    }
+
+    fn read(&mut self, _source: &AmsAddress, _request: &ReadRequest) -> ReadResponse {
+        ReadResponse::new(AdsError::AdsErrDeviceSrvNotSupp, Vec::new())
+    }
+
+    fn write(&mut self, _source: &AmsAddress, _request: &WriteRequest) -> WriteResponse {
+        WriteResponse::new(AdsError::AdsErrDeviceSrvNotSupp)
+    }
+
+    fn read_write(
+        &mut self,
+        _source: &AmsAddress,
+        _request: &ReadWriteRequest,
+    ) -> ReadWriteResponse {
+        ReadWriteResponse::new(AdsError::AdsErrDeviceSrvNotSupp, Vec::new())
+    }
+
+    fn write_control(
+        &mut self,
+        _source: &AmsAddress,
+        _request: &WriteControlRequest,
+    ) -> WriteControlResponse {
+        WriteControlResponse::new(AdsError::AdsErrDeviceSrvNotSupp)
+    }
+
+    fn add_device_notification(
+        &mut self,
+        _source: &AmsAddress,
+        _request: &AddDeviceNotificationRequest,
+    ) -> AddDeviceNotificationResponse {
+        AddDeviceNotificationResponse::new(AdsError::AdsErrDeviceSrvNotSupp, 0)
+    }
+
+    fn delete_device_notification(
+        &mut self,
+        _source: &AmsAddress,
+        _request: &DeleteDeviceNotificationRequest,
+    ) -> DeleteDeviceNotificationResponse {
+        DeleteDeviceNotificationResponse::new(AdsError::AdsErrDeviceSrvNotSupp)
+    }
+
+    ///Called by the server when a notification is due (see [AdsDevice::next_notification]).
+    ///Returns the notification streams to send with the address of the client to send them to.
+    fn poll_notifications(&mut self, _now: Instant) -> Vec<(AmsAddress, AdsNotificationStream)> {
+        Vec::new()
+    }
+
+    ///Time the next notification is due, None without notifications.
+    ///Devices returning None are still polled after every request and at least every
+    ///[NOTIFICATION_IDLE_INTERVAL](tcp_server::NOTIFICATION_IDLE_INTERVAL).
+    fn next_notification(&self) -> Option<Instant> {
+        None
+    }
+
+    ///Called by the server when the connection of a client is closed
+    fn client_disconnected(&mut self, _source: &AmsAddress) {}
+}
+
+///Call the device method matching the request.
+///Sumup requests are unpacked and each sub-command is passed to the device, see [sumup_dispatch::handle_sumup].
+///Returns None for requests which are not answered (invalid requests and device notifications).
+pub fn handle_request<D: AdsDevice + ?Sized>(
+    device: &mut D,
+    source: &AmsAddress,
+    request: &Request,
+) -> Option<Response> {
+    match request {
+        Request::Invalid(_) => None,
+        Request::DeviceNotification(_) => None,
+        Request::ReadDeviceInfo(_) => Some(device.read_device_info(source).into()),
+        Request::ReadState(_) => Some(device.read_state(source).into()),
+        Request::Read(r) => Some(device.read(source, r).into()),
+        Request::Write(r) => Some(device.write(source, r).into()),
+        Request::ReadWrite(r) => match sumup_dispatch::handle_sumup(device, source, r) {
+            Some(response) => Some(response.into()),
+            None => Some(device.read_write(source, r).into()),
+        },
+        Request::WriteControl(r) => Some(device.write_control(source, r).into()),
+        Request::AddDeviceNotification(r) => Some(device.add_device_notification(source, r).into()),
+        Request::DeleteDeviceNotification(r) => {
+            Some(device.delete_device_notification(source, r).into())
+        }
+    }
+}
+
+///Create the response for a command with only the result set.
+///Returns None for commands without response.
+pub fn error_response(command_id: CommandID, result: AdsError) -> Option<Response> {
+    match command_id {
+        CommandID::Invalid => None,
+        CommandID::DeviceNotification => None,
+        CommandID::ReadDeviceInfo => {
+            Some(ReadDeviceInfoResponse::new(result, 0, 0, 0, [0; 16]).into())
+        }
+        CommandID::ReadState => {
+            Some(ReadStateResponse::new(result, AdsState::AdsStateInvalid, 0).into())
+        }
+        CommandID::Read => Some(ReadResponse::new(result, Vec::new()).into()),
+        CommandID::Write => Some(WriteResponse::new(result).into()),
+        CommandID::ReadWrite => Some(ReadWriteResponse::new(result, Vec::new()).into()),
+        CommandID::WriteControl => Some(WriteControlResponse::new(result).into()),
+        CommandID::AddDeviceNotification => {
+            Some(AddDeviceNotificationResponse::new(result, 0).into())
+        }
+        CommandID::DeleteDeviceNotification => {
+            Some(DeleteDeviceNotificationResponse::new(result).into())
+        }
+    }
+}
+
+///Fixtures shared by the server tests
+#[cfg(test)]
+pub(crate) mod test_util {
+    use crate::proto::ams_address::{AmsAddress, AmsNetId};
+
+    ///AmsNetId of the server
+    pub(crate) fn net_id() -> AmsNetId {
+        AmsNetId::new(127, 0, 0, 1, 1, 1)
+    }
+
+    pub(crate) fn client_address() -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(127, 0, 0, 1, 2, 1), 30000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::proto_traits::Command;
+    use crate::server::test_util::client_address;
+
+    struct TestDevice;
+
+    impl AdsDevice for TestDevice {
+        fn read(&mut self, _source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+            ReadResponse::new(AdsError::ErrNoError, vec![1; request.length as usize])
+        }
+    }
+
+    #[test]
+    fn handle_request_test() {
+        let mut device = TestDevice;
+        let response = handle_request(
+            &mut device,
+            &client_address(),
+            &Request::Read(ReadRequest::new(0x4020, 0, 2)),
+        );
+        assert_eq!(
+            response,
+            Some(Response::Read(ReadResponse::new(
+                AdsError::ErrNoError,
+                vec![1, 1]
+            )))
+        );
+
+        let response = handle_request(
+            &mut device,
+            &client_address(),
+            &Request::Write(WriteRequest::new(0x4020, 0, vec![1])),
+        );
+        assert_eq!(
+            response,
+            Some(Response::Write(WriteResponse::new(
+                AdsError::AdsErrDeviceSrvNotSupp
+            )))
+        );
+
+        let response = handle_request(
+            &mut device,
+            &client_address(),
+            &Request::DeviceNotification(DeviceNotificationRequest::new()),
+        );
+        assert_eq!(response, None);
+    }
+
+    #[test]
+    fn error_response_test() {
+        let commands = [
+            CommandID::ReadDeviceInfo,
+            CommandID::ReadState,
+            CommandID::Read,
+            CommandID::Write,
+            CommandID::ReadWrite,
+            CommandID::WriteControl,
+            CommandID::AddDeviceNotification,
+            CommandID::DeleteDeviceNotification,
+        ];
+        for command_id in commands {
+            let response = error_response(command_id, AdsError::ErrTargetPortNotFound).unwrap();
+            assert_eq!(response.command_id(), command_id);
+        }
+        assert_eq!(
+            error_response(CommandID::DeviceNotification, AdsError::ErrNoError),
+            None
+        );
+    }
+}
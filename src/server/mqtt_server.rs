@@ -0,0 +1,305 @@
+use crate::mqtt::ads_mqtt::{header_from_payload, AdsMqttTopics, MqttRouterInfo};
+use crate::mqtt::connection::MqttConnection;
+use crate::mqtt::packet::MqttConnect;
+use crate::server::tcp_server::{AdsServer, NOTIFICATION_IDLE_INTERVAL};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+///Interval of the attempts to reconnect to the broker after the connection is lost
+pub const MQTT_RECONNECT_INTERVAL: Duration = Duration::from_millis(500);
+
+impl AdsServer {
+    ///ADS over MQTT: answer the requests published to the ams topic of the AmsNetId of the server
+    ///(see [AdsMqttTopics]) on a background thread.
+    ///
+    ///The server is announced as online with `name` on its info topic, the will of the connection
+    ///sets it offline if the connection is lost. The responses are published to the response topic of the client,
+    ///device notifications to its ams topic. There is no connection per client,
+    ///the devices are not informed about clients which are gone.
+    ///
+    ///If the connection to the broker is lost the server reconnects every [MQTT_RECONNECT_INTERVAL],
+    ///see [MqttServerHandle::is_connected].
+    pub fn serve_mqtt<A: ToSocketAddrs>(
+        &self,
+        broker: A,
+        mut connect: MqttConnect,
+        topics: AdsMqttTopics,
+        name: &str,
+    ) -> io::Result<MqttServerHandle> {
+        let info = MqttRouterInfo::new(self.net_id().clone(), name, true);
+        let offline = MqttRouterInfo {
+            online: false,
+            ..info.clone()
+        };
+        connect.will = Some(offline.publish(&topics));
+        let broker: Vec<SocketAddr> = broker.to_socket_addrs()?.collect();
+        let mut connection = self.connect_mqtt(&broker, &connect, &topics, &info)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let connected = Arc::new(AtomicBool::new(true));
+        let server = self.clone();
+        let thread_stop = stop.clone();
+        let thread_connected = connected.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::SeqCst) {
+                let timeout = server.notification_timeout(NOTIFICATION_IDLE_INTERVAL);
+                let publish = match connection.receive(timeout) {
+                    Ok(publish) => publish,
+                    Err(_) => {
+                        thread_connected.store(false, Ordering::SeqCst);
+                        thread::sleep(MQTT_RECONNECT_INTERVAL);
+                        if let Ok(c) = server.connect_mqtt(&broker, &connect, &topics, &info) {
+                            connection = c;
+                            thread_connected.store(true, Ordering::SeqCst);
+                        }
+                        continue;
+                    }
+                };
+                let request = publish.and_then(|p| header_from_payload(&p.payload).ok());
+                if let Some(response) = request.and_then(|r| server.handle(r)) {
+                    if let Ok(publish) = topics.publish(response) {
+                        let _ = connection.publish(publish);
+                    }
+                }
+
+                let now = Instant::now();
+                if !server.notification_due(now) {
+                    continue;
+                }
+                for header in server.notifications(now) {
+                    if let Ok(publish) = topics.publish(header) {
+                        let _ = connection.publish(publish);
+                    }
+                }
+            }
+            let _ = connection.publish(offline.publish(&topics));
+        });
+
+        Ok(MqttServerHandle {
+            stop,
+            connected,
+            thread: Some(thread),
+        })
+    }
+
+    ///Connect, subscribe the ams topic of the server and publish the online info
+    fn connect_mqtt(
+        &self,
+        broker: &[SocketAddr],
+        connect: &MqttConnect,
+        topics: &AdsMqttTopics,
+        info: &MqttRouterInfo,
+    ) -> io::Result<MqttConnection> {
+        let mut connection = MqttConnection::connect(broker, connect.clone())?;
+        connection.subscribe(&topics.ams(self.net_id()))?;
+        connection.publish(info.publish(topics))?;
+        Ok(connection)
+    }
+}
+
+///Handle of an [AdsServer] serving ADS over MQTT. Publishes the offline info and disconnects when dropped.
+pub struct MqttServerHandle {
+    stop: Arc<AtomicBool>,
+    connected: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MqttServerHandle {
+    ///False while the connection to the broker is lost and not reestablished yet
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop_server();
+    }
+
+    fn stop_server(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MqttServerHandle {
+    fn drop(&mut self) {
+        self.stop_server();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mqtt_client::{discover, MqttClient};
+    use crate::client::AdsClient;
+    use crate::error::AdsError;
+    use crate::mqtt::broker::MqttBroker;
+    use crate::mqtt::connection::MqttConnection;
+    use crate::proto::ams_address::AmsAddress;
+    use crate::proto::request::{AddDeviceNotificationRequest, ReadRequest, Request, WriteRequest};
+    use crate::proto::response::{ReadResponse, Response, WriteResponse};
+    use crate::server::notification_engine::NotificationEngine;
+    use crate::server::test_util::{client_address, net_id};
+    use crate::server::virtual_plc::VirtualPlc;
+    use std::time::Duration;
+
+    #[test]
+    fn ads_server_mqtt_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let topics = AdsMqttTopics::default();
+        let server = AdsServer::new(net_id());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        let handle = server
+            .serve_mqtt(
+                broker.local_addr(),
+                MqttConnect::new("plc", 30),
+                topics.clone(),
+                "PLC",
+            )
+            .unwrap();
+
+        let mut client = MqttClient::connect(
+            broker.local_addr(),
+            MqttConnect::new("client", 30),
+            topics.clone(),
+            client_address(),
+        )
+        .unwrap();
+        let plc = AmsAddress::new(net_id(), 851);
+        assert_eq!(
+            client
+                .request(
+                    &plc,
+                    Request::Write(WriteRequest::new(0x4020, 0, vec![3, 4]))
+                )
+                .unwrap(),
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        assert_eq!(
+            client
+                .request(&plc, Request::Read(ReadRequest::new(0x4020, 0, 2)))
+                .unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![3, 4]))
+        );
+
+        let mut routers = discover(
+            broker.local_addr(),
+            MqttConnect::new("discover", 30),
+            &topics,
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        routers.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(routers.len(), 2);
+        assert_eq!(routers[0], MqttRouterInfo::new(net_id(), "PLC", true));
+
+        handle.shutdown();
+        let info = broker.retained(&topics.info(&net_id())).unwrap();
+        assert!(!MqttRouterInfo::from_publish(&topics, &info).unwrap().online);
+        broker.shutdown();
+    }
+
+    #[test]
+    fn ads_server_mqtt_notification_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let topics = AdsMqttTopics::new("test");
+        let server = AdsServer::new(net_id());
+        server.add_device(851, NotificationEngine::new(VirtualPlc::new(4, 0, 0)));
+        let _handle = server
+            .serve_mqtt(
+                broker.local_addr(),
+                MqttConnect::new("plc", 30),
+                topics.clone(),
+                "PLC",
+            )
+            .unwrap();
+
+        let mut connection =
+            MqttConnection::connect(broker.local_addr(), MqttConnect::new("client", 30)).unwrap();
+        connection
+            .subscribe(&topics.ams_filter(&client_address().ams_net_id))
+            .unwrap();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .on_change(Duration::ZERO)
+            .length(1)
+            .build()
+            .unwrap();
+        let header = crate::proto::ams_header::AmsHeader::new(
+            AmsAddress::new(net_id(), 851),
+            client_address(),
+            crate::proto::state_flags::StateFlags::req_default(),
+            1,
+            Request::AddDeviceNotification(request),
+        );
+        connection.publish(topics.publish(header).unwrap()).unwrap();
+
+        let client_net_id = client_address().ams_net_id;
+        let mut topics_received = Vec::new();
+        while topics_received.len() < 2 {
+            let publish = connection.receive(Duration::from_secs(5)).unwrap().unwrap();
+            let mut header = header_from_payload(&publish.payload).unwrap();
+            match header.response().unwrap() {
+                Response::AddDeviceNotification(r) => assert_eq!(r.result, AdsError::ErrNoError),
+                Response::DeviceNotification(_) => {}
+                r => panic!("unexpected response {:?}", r),
+            }
+            topics_received.push(publish.topic);
+        }
+        topics_received.sort();
+        assert_eq!(
+            topics_received,
+            vec![
+                topics.ams(&client_net_id),
+                topics.ams_response(&client_net_id)
+            ]
+        );
+    }
+
+    #[test]
+    fn ads_server_mqtt_reconnect_test() {
+        let broker = MqttBroker::bind("127.0.0.1:0").unwrap();
+        let addr = broker.local_addr();
+        let topics = AdsMqttTopics::default();
+        let server = AdsServer::new(net_id());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        let handle = server
+            .serve_mqtt(addr, MqttConnect::new("plc", 30), topics.clone(), "PLC")
+            .unwrap();
+        assert!(handle.is_connected());
+
+        broker.shutdown();
+        let start = Instant::now();
+        while handle.is_connected() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        let broker = MqttBroker::bind(addr).unwrap();
+        while !handle.is_connected() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+        let mut client = MqttClient::connect(
+            broker.local_addr(),
+            MqttConnect::new("client", 30),
+            topics.clone(),
+            client_address(),
+        )
+        .unwrap();
+        assert_eq!(
+            client
+                .request(
+                    &AmsAddress::new(net_id(), 851),
+                    Request::Read(ReadRequest::new(0x4020, 0, 2))
+                )
+                .unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![0, 0]))
+        );
+        handle.shutdown();
+    }
+}
@@ -0,0 +1,530 @@
+use crate::error::AdsError;
+use crate::proto::ads_transition_mode::AdsTransMode;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::file_time::FileTime;
+use crate::proto::request::*;
+use crate::proto::response::*;
+use crate::server::AdsDevice;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+///Pending samples of a notification which are sent without waiting for max_delay
+pub const MAX_PENDING_SAMPLES: usize = 1000;
+
+///Notification registered by a client
+#[derive(Debug, Clone)]
+struct ServerSubscription {
+    client: AmsAddress,
+    request: AddDeviceNotificationRequest,
+    next_sample: Instant,
+    last_value: Option<Vec<u8>>,
+    pending: Vec<(FileTime, Vec<u8>)>,
+    pending_since: Option<Instant>,
+}
+
+impl ServerSubscription {
+    fn is_cyclic(&self) -> bool {
+        matches!(
+            self.request.transmission_mode,
+            AdsTransMode::Cyclic | AdsTransMode::CyclicInContext
+        )
+    }
+
+    ///Schedule the next sample. Missed cycles are skipped.
+    fn schedule(&mut self, now: Instant) {
+        let cycle_time = self.request.cycle_time_duration();
+        if cycle_time.is_zero() {
+            self.next_sample = now;
+            return;
+        }
+        if self.next_sample <= now {
+            let missed = (now - self.next_sample).as_nanos() / cycle_time.as_nanos() + 1;
+            self.next_sample += Duration::from_nanos((missed * cycle_time.as_nanos()) as u64);
+        }
+    }
+
+    fn sample(&mut self, time_stamp: FileTime, value: Vec<u8>, now: Instant) {
+        if self.is_cyclic() || self.last_value.as_ref() != Some(&value) {
+            self.pending.push((time_stamp, value.clone()));
+            self.pending_since.get_or_insert(now);
+        }
+        self.last_value = Some(value);
+    }
+
+    fn is_full(&self) -> bool {
+        self.pending.len() >= MAX_PENDING_SAMPLES
+    }
+
+    ///Time of the next sample or of sending the pending samples
+    fn next_due(&self) -> Instant {
+        match self.pending_since {
+            Some(since) if self.is_full() => since,
+            Some(since) => self
+                .next_sample
+                .min(since + self.request.max_delay_duration()),
+            None => self.next_sample,
+        }
+    }
+
+    ///Take the pending samples if the oldest one waited for max_delay or too many are pending
+    fn take_due(&mut self, now: Instant) -> Vec<(FileTime, Vec<u8>)> {
+        match self.pending_since {
+            Some(since) if now >= since + self.request.max_delay_duration() || self.is_full() => {
+                self.pending_since = None;
+                std::mem::take(&mut self.pending)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+///Device notifications on top of another device.
+///Handles AddDeviceNotification and DeleteDeviceNotification and samples the values with read requests to the wrapped device:
+///- Cyclic: a sample every cycle_time
+///- OnChange: checked every cycle_time, a sample if the value changed
+///
+///The in-context modes behave like the modes above. The first sample is taken on the next poll after the registration.
+///Samples are held back up to max_delay and then sent together,
+///earlier if [MAX_PENDING_SAMPLES] samples of a notification are pending.
+///The samples due for a client are packed into one AdsNotificationStream with one AdsStampHeader per time stamp.
+pub struct NotificationEngine<D: AdsDevice> {
+    device: D,
+    subscriptions: BTreeMap<u32, ServerSubscription>,
+    next_handle: u32,
+}
+
+impl<D: AdsDevice> NotificationEngine<D> {
+    pub fn new(device: D) -> Self {
+        NotificationEngine {
+            device,
+            subscriptions: BTreeMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    ///Number of registered notifications of all clients
+    pub fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.is_empty()
+    }
+
+    ///Read the current value of a notification from the wrapped device
+    fn read_value(
+        &mut self,
+        client: &AmsAddress,
+        request: &AddDeviceNotificationRequest,
+    ) -> Result<Vec<u8>, AdsError> {
+        let read_request =
+            ReadRequest::new(request.index_group, request.index_offset, request.length);
+        let response = self.device.read(client, &read_request);
+        match response.result {
+            AdsError::ErrNoError => Ok(response.data),
+            e => Err(e),
+        }
+    }
+
+    fn next_handle(&mut self) -> u32 {
+        while self.next_handle == 0 || self.subscriptions.contains_key(&self.next_handle) {
+            self.next_handle = self.next_handle.wrapping_add(1);
+        }
+        let handle = self.next_handle;
+        self.next_handle = self.next_handle.wrapping_add(1);
+        handle
+    }
+}
+
+impl<D: AdsDevice> AdsDevice for NotificationEngine<D> {
+    fn read_device_info(&mut self, source: &AmsAddress) -> ReadDeviceInfoResponse {
+        self.device.read_device_info(source)
+    }
+
+    fn read_state(&mut self, source: &AmsAddress) -> ReadStateResponse {
+        self.device.read_state(source)
+    }
+
+    fn read(&mut self, source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+        self.device.read(source, request)
+    }
+
+    fn write(&mut self, source: &AmsAddress, request: &WriteRequest) -> WriteResponse {
+        self.device.write(source, request)
+    }
+
+    fn read_write(&mut self, source: &AmsAddress, request: &ReadWriteRequest) -> ReadWriteResponse {
+        self.device.read_write(source, request)
+    }
+
+    fn write_control(
+        &mut self,
+        source: &AmsAddress,
+        request: &WriteControlRequest,
+    ) -> WriteControlResponse {
+        self.device.write_control(source, request)
+    }
+
+    ///The value is read once to check index group, offset and length
+    fn add_device_notification(
+        &mut self,
+        source: &AmsAddress,
+        request: &AddDeviceNotificationRequest,
+    ) -> AddDeviceNotificationResponse {
+        if !request.transmission_mode.is_server_mode() {
+            return AddDeviceNotificationResponse::new(AdsError::AdsErrDeviceTransModeNotSupp, 0);
+        }
+        if let Err(e) = self.read_value(source, request) {
+            return AddDeviceNotificationResponse::new(e, 0);
+        }
+        let handle = self.next_handle();
+        self.subscriptions.insert(
+            handle,
+            ServerSubscription {
+                client: source.clone(),
+                request: request.clone(),
+                next_sample: Instant::now(),
+                last_value: None,
+                pending: Vec::new(),
+                pending_since: None,
+            },
+        );
+        AddDeviceNotificationResponse::new(AdsError::ErrNoError, handle)
+    }
+
+    fn delete_device_notification(
+        &mut self,
+        source: &AmsAddress,
+        request: &DeleteDeviceNotificationRequest,
+    ) -> DeleteDeviceNotificationResponse {
+        match self.subscriptions.get(&request.handle) {
+            Some(subscription) if &subscription.client == source => {
+                self.subscriptions.remove(&request.handle);
+                DeleteDeviceNotificationResponse::new(AdsError::ErrNoError)
+            }
+            _ => DeleteDeviceNotificationResponse::new(AdsError::AdsErrDeviceNotifyHndInvalid),
+        }
+    }
+
+    fn poll_notifications(&mut self, now: Instant) -> Vec<(AmsAddress, AdsNotificationStream)> {
+        //All samples of this poll share the time stamp
+        let time_stamp = FileTime::now();
+        let mut due: HashMap<AmsAddress, BTreeMap<FileTime, Vec<AdsNotificationSample>>> =
+            HashMap::new();
+
+        let handles: Vec<u32> = self.subscriptions.keys().copied().collect();
+        for handle in handles {
+            let (client, request, sample_due) = {
+                let subscription = &self.subscriptions[&handle];
+                (
+                    subscription.client.clone(),
+                    subscription.request.clone(),
+                    now >= subscription.next_sample,
+                )
+            };
+            let value = match sample_due {
+                true => Some(self.read_value(&client, &request)),
+                false => None,
+            };
+
+            let subscription = self
+                .subscriptions
+                .get_mut(&handle)
+                .expect("subscription removed while polling");
+            if let Some(value) = value {
+                //A failed read is skipped, the next cycle tries again
+                if let Ok(value) = value {
+                    subscription.sample(time_stamp, value, now);
+                }
+                subscription.schedule(now);
+            }
+            for (time_stamp, data) in subscription.take_due(now) {
+                due.entry(client.clone())
+                    .or_default()
+                    .entry(time_stamp)
+                    .or_default()
+                    .push(AdsNotificationSample::new(handle, data));
+            }
+        }
+
+        let mut streams: Vec<(AmsAddress, AdsNotificationStream)> = due
+            .into_iter()
+            .map(|(client, stamps)| {
+                let stamp_headers = stamps
+                    .into_iter()
+                    .map(|(time_stamp, samples)| AdsStampHeader::with_time(time_stamp, samples))
+                    .collect();
+                (
+                    client,
+                    AdsNotificationStream::from_stamp_headers(stamp_headers),
+                )
+            })
+            .collect();
+        streams.extend(self.device.poll_notifications(now));
+        streams
+    }
+
+    fn next_notification(&self) -> Option<Instant> {
+        self.subscriptions
+            .values()
+            .map(|s| s.next_due())
+            .chain(self.device.next_notification())
+            .min()
+    }
+
+    fn client_disconnected(&mut self, source: &AmsAddress) {
+        self.subscriptions
+            .retain(|_, subscription| &subscription.client != source);
+        self.device.client_disconnected(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::ams_address::AmsNetId;
+    use crate::server::virtual_plc::{ProcessImage, VirtualPlc};
+
+    fn client(port: u16) -> AmsAddress {
+        AmsAddress::new(AmsNetId::new(192, 168, 1, 3, 1, 1), port)
+    }
+
+    fn engine() -> NotificationEngine<VirtualPlc> {
+        NotificationEngine::new(VirtualPlc::new(16, 0, 0))
+    }
+
+    fn add(
+        engine: &mut NotificationEngine<VirtualPlc>,
+        client: &AmsAddress,
+        request: AddDeviceNotificationRequest,
+    ) -> u32 {
+        let response = engine.add_device_notification(client, &request);
+        assert_eq!(response.result, AdsError::ErrNoError);
+        response.notification_handle
+    }
+
+    fn samples(streams: &[(AmsAddress, AdsNotificationStream)]) -> Vec<(u32, Vec<u8>)> {
+        streams
+            .iter()
+            .flat_map(|(_, stream)| stream.timestamped_samples())
+            .map(|(_, sample)| (sample.notification_handle, sample.data.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn notification_engine_cyclic_test() {
+        let mut engine = engine();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .cyclic(Duration::from_millis(10))
+            .length(2)
+            .build()
+            .unwrap();
+        let handle = add(&mut engine, &client(30000), request.clone());
+        let start = Instant::now();
+
+        let streams = engine.poll_notifications(start);
+        assert_eq!(streams.len(), 1);
+        assert_eq!(streams[0].0, client(30000));
+        assert_eq!(samples(&streams), vec![(handle, vec![0, 0])]);
+
+        //Not due yet
+        assert!(engine
+            .poll_notifications(start + Duration::from_millis(5))
+            .is_empty());
+
+        //Same value is sent again
+        let streams = engine.poll_notifications(start + request.cycle_time_duration());
+        assert_eq!(samples(&streams), vec![(handle, vec![0, 0])]);
+        //Missed cycles are skipped
+        let streams = engine.poll_notifications(start + Duration::from_millis(45));
+        assert_eq!(samples(&streams), vec![(handle, vec![0, 0])]);
+        let next = engine.next_notification().unwrap();
+        assert!(next > start + Duration::from_millis(45));
+        assert!(next <= start + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn notification_engine_next_notification_test() {
+        let mut engine = engine();
+        assert_eq!(engine.next_notification(), None);
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .cyclic(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(4))
+            .length(2)
+            .build()
+            .unwrap();
+        add(&mut engine, &client(30000), request);
+        let start = Instant::now();
+        assert!(engine.next_notification().unwrap() <= start);
+
+        //Sampled, sent after max_delay
+        assert!(engine.poll_notifications(start).is_empty());
+        assert_eq!(
+            engine.next_notification(),
+            Some(start + Duration::from_millis(4))
+        );
+        assert_eq!(
+            samples(&engine.poll_notifications(start + Duration::from_millis(4))).len(),
+            1
+        );
+        //Next cycle, scheduled from the registration
+        let next = engine.next_notification().unwrap();
+        assert!(next > start + Duration::from_millis(4));
+        assert!(next <= start + Duration::from_millis(10));
+    }
+
+    #[test]
+    fn notification_engine_on_change_test() {
+        let mut engine = engine();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .on_change(Duration::ZERO)
+            .length(1)
+            .build()
+            .unwrap();
+        let handle = add(&mut engine, &client(30000), request);
+        let now = Instant::now();
+
+        //Initial value
+        assert_eq!(
+            samples(&engine.poll_notifications(now)),
+            vec![(handle, vec![0])]
+        );
+        assert!(engine.poll_notifications(now).is_empty());
+
+        engine.device_mut().image_mut(ProcessImage::Memory)[0] = 5;
+        assert_eq!(
+            samples(&engine.poll_notifications(now)),
+            vec![(handle, vec![5])]
+        );
+        assert!(engine.poll_notifications(now).is_empty());
+    }
+
+    #[test]
+    fn notification_engine_max_delay_test() {
+        let mut engine = engine();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .cyclic(Duration::from_millis(10))
+            .max_delay(Duration::from_millis(25))
+            .length(1)
+            .build()
+            .unwrap();
+        let handle = add(&mut engine, &client(30000), request);
+        let start = Instant::now();
+
+        assert!(engine.poll_notifications(start).is_empty());
+        assert!(engine
+            .poll_notifications(start + Duration::from_millis(10))
+            .is_empty());
+        assert!(engine
+            .poll_notifications(start + Duration::from_millis(20))
+            .is_empty());
+        //Samples of four cycles batched in one stream
+        let streams = engine.poll_notifications(start + Duration::from_millis(30));
+        assert_eq!(streams.len(), 1);
+        assert_eq!(samples(&streams).len(), 4);
+        assert!(samples(&streams).iter().all(|(h, _)| *h == handle));
+    }
+
+    #[test]
+    fn notification_engine_max_pending_test() {
+        let mut engine = engine();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .cyclic(Duration::from_millis(1))
+            .max_delay(Duration::from_secs(60))
+            .length(1)
+            .build()
+            .unwrap();
+        add(&mut engine, &client(30000), request);
+        let start = Instant::now();
+
+        for n in 0..MAX_PENDING_SAMPLES - 1 {
+            let now = start + Duration::from_millis(n as u64);
+            assert!(engine.poll_notifications(now).is_empty());
+        }
+        //Sent when the queue is full, long before max_delay
+        let now = start + Duration::from_millis(MAX_PENDING_SAMPLES as u64);
+        let streams = engine.poll_notifications(now);
+        assert_eq!(samples(&streams).len(), MAX_PENDING_SAMPLES);
+        assert!(engine.next_notification().unwrap() > now);
+    }
+
+    #[test]
+    fn notification_engine_pack_clients_test() {
+        let mut engine = engine();
+        let request = |offset| {
+            AddDeviceNotificationRequest::builder(0x4020, offset)
+                .cyclic(Duration::from_millis(1))
+                .length(1)
+                .build()
+                .unwrap()
+        };
+        let handle_1 = add(&mut engine, &client(30000), request(0));
+        let handle_2 = add(&mut engine, &client(30000), request(1));
+        let handle_3 = add(&mut engine, &client(30001), request(2));
+
+        let mut streams = engine.poll_notifications(Instant::now());
+        streams.sort_by_key(|(client, _)| client.port);
+        assert_eq!(streams.len(), 2);
+        //Both samples of the first client in the same stamp header
+        assert_eq!(streams[0].1.stamps, 1);
+        assert_eq!(
+            samples(&streams[..1]),
+            vec![(handle_1, vec![0]), (handle_2, vec![0])]
+        );
+        assert_eq!(samples(&streams[1..]), vec![(handle_3, vec![0])]);
+    }
+
+    #[test]
+    fn notification_engine_delete_test() {
+        let mut engine = engine();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .on_change(Duration::ZERO)
+            .length(1)
+            .build()
+            .unwrap();
+        let handle = add(&mut engine, &client(30000), request.clone());
+        add(&mut engine, &client(30001), request.clone());
+        add(&mut engine, &client(30001), request);
+        assert_eq!(engine.len(), 3);
+
+        //Handle of another client
+        let response = engine.delete_device_notification(
+            &client(30001),
+            &DeleteDeviceNotificationRequest::new(handle),
+        );
+        assert_eq!(response.result, AdsError::AdsErrDeviceNotifyHndInvalid);
+
+        let response = engine.delete_device_notification(
+            &client(30000),
+            &DeleteDeviceNotificationRequest::new(handle),
+        );
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(engine.len(), 2);
+
+        engine.client_disconnected(&client(30001));
+        assert!(engine.is_empty());
+    }
+
+    #[test]
+    fn notification_engine_invalid_request_test() {
+        let mut engine = engine();
+        let mut request =
+            AddDeviceNotificationRequest::new(0x4020, 0, 1, AdsTransMode::ClientOnChange, 0, 0);
+        let response = engine.add_device_notification(&client(30000), &request);
+        assert_eq!(response.result, AdsError::AdsErrDeviceTransModeNotSupp);
+
+        request.transmission_mode = AdsTransMode::OnChange;
+        request.index_offset = 16;
+        let response = engine.add_device_notification(&client(30000), &request);
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidOffset);
+        assert!(engine.is_empty());
+    }
+}
@@ -0,0 +1,328 @@
+use crate::ads_services::system_services::{
+    ADSIGRP_SUMUP_READ, ADSIGRP_SUMUP_READEX, ADSIGRP_SUMUP_READEX2, ADSIGRP_SUMUP_READWRITE,
+    ADSIGRP_SUMUP_WRITE,
+};
+use crate::error::AdsError;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::request::{ReadRequest, ReadWriteRequest};
+use crate::proto::response::{ReadResponse, ReadWriteResponse};
+use crate::proto::sumup::sumup_request::{
+    SumupReadRequest, SumupReadWriteRequest, SumupWriteRequest,
+};
+use crate::proto::sumup::sumup_response::{
+    SumupReadResponse, SumupReadWriteResponse, SumupWriteResponse,
+};
+use crate::server::AdsDevice;
+use byteorder::{LittleEndian, WriteBytesExt};
+
+///Max number of sub-commands in a sumup request
+pub const MAX_SUMUP_COMMANDS: u32 = 500;
+
+///Returns true if the index group is one of the sumup index groups
+pub fn is_sumup(index_group: u32) -> bool {
+    [
+        ADSIGRP_SUMUP_READ.index_group,
+        ADSIGRP_SUMUP_WRITE.index_group,
+        ADSIGRP_SUMUP_READWRITE.index_group,
+        ADSIGRP_SUMUP_READEX.index_group,
+        ADSIGRP_SUMUP_READEX2.index_group,
+    ]
+    .contains(&index_group)
+}
+
+///Unpack a sumup request, pass each sub-command to the device and pack the responses.
+///Returns None if the request is not a sumup request.
+///The result of the whole request is only an error if the sumup can't be parsed,
+///errors of the sub-commands are returned per sub-command.
+pub fn handle_sumup<D: AdsDevice + ?Sized>(
+    device: &mut D,
+    source: &AmsAddress,
+    request: &ReadWriteRequest,
+) -> Option<ReadWriteResponse> {
+    if !is_sumup(request.index_group) {
+        return None;
+    }
+    let response = match dispatch(device, source, request) {
+        Ok(data) if data.len() > request.read_length as usize => {
+            ReadWriteResponse::new(AdsError::AdsErrDeviceInvalidSize, Vec::new())
+        }
+        Ok(data) => ReadWriteResponse::new(AdsError::ErrNoError, data),
+        Err(e) => ReadWriteResponse::new(e, Vec::new()),
+    };
+    Some(response)
+}
+
+fn dispatch<D: AdsDevice + ?Sized>(
+    device: &mut D,
+    source: &AmsAddress,
+    request: &ReadWriteRequest,
+) -> Result<Vec<u8>, AdsError> {
+    //Index offset is the number of sub-commands
+    let count = request.index_offset;
+    if count == 0 || count > MAX_SUMUP_COMMANDS {
+        return Err(AdsError::AdsErrDeviceInvalidParm);
+    }
+    let invalid = |_| AdsError::AdsErrDeviceInvalidData;
+    let check_count = |parsed: u32| match parsed == count {
+        true => Ok(()),
+        false => Err(AdsError::AdsErrDeviceInvalidSize),
+    };
+    let mut data: Vec<u8> = Vec::new();
+
+    //Lengths are chosen by the client, check the response fits before calling the device
+    let fits = |len: Option<u32>| match len {
+        Some(len) if len <= request.read_length => Ok(()),
+        _ => Err(AdsError::AdsErrDeviceInvalidSize),
+    };
+    let read_len = |requests: &[ReadRequest], header: u32| {
+        requests.iter().try_fold(0u32, |len, r| {
+            len.checked_add(r.length)?.checked_add(header)
+        })
+    };
+
+    match request.index_group {
+        ig if ig == ADSIGRP_SUMUP_WRITE.index_group => {
+            let sumup =
+                SumupWriteRequest::read_from(&mut request.data.as_slice()).map_err(invalid)?;
+            check_count(sumup.request_count())?;
+            fits(count.checked_mul(4))?;
+            let responses = sumup
+                .write_requests()
+                .iter()
+                .map(|r| device.write(source, r))
+                .collect();
+            SumupWriteResponse::new(responses).write_to(&mut data)
+        }
+        ig if ig == ADSIGRP_SUMUP_READWRITE.index_group => {
+            let sumup =
+                SumupReadWriteRequest::read_from(&mut request.data.as_slice()).map_err(invalid)?;
+            check_count(sumup.request_count())?;
+            fits(sumup.read_write_requests().iter().try_fold(0u32, |len, r| {
+                len.checked_add(r.read_length)?.checked_add(8)
+            }))?;
+            let responses = sumup
+                .read_write_requests()
+                .iter()
+                .map(|r| device.read_write(source, r))
+                .collect();
+            SumupReadWriteResponse::new(responses).write_to(&mut data)
+        }
+        ig if ig == ADSIGRP_SUMUP_READ.index_group => {
+            let sumup =
+                SumupReadRequest::read_from(&mut request.data.as_slice()).map_err(invalid)?;
+            check_count(sumup.request_count())?;
+            fits(read_len(sumup.read_requests(), 4))?;
+            let responses: Vec<ReadResponse> = sumup
+                .read_requests()
+                .iter()
+                .map(|r| device.read(source, r))
+                .collect();
+            //All results first, then the data with the requested length of each sub-command
+            let mut values: Vec<u8> = Vec::new();
+            for (request, response) in sumup.read_requests().iter().zip(&responses) {
+                data.write_u32::<LittleEndian>(response.result.as_u32())
+                    .map_err(invalid)?;
+                let mut value = response.data.clone();
+                value.resize(request.length as usize, 0);
+                values.append(&mut value);
+            }
+            data.append(&mut values);
+            Ok(())
+        }
+        //READEX and READEX2
+        _ => {
+            let sumup =
+                SumupReadRequest::read_from(&mut request.data.as_slice()).map_err(invalid)?;
+            check_count(sumup.request_count())?;
+            fits(read_len(sumup.read_requests(), 8))?;
+            let responses = sumup
+                .read_requests()
+                .iter()
+                .map(|r| device.read(source, r))
+                .collect();
+            SumupReadResponse::new(responses).write_to(&mut data)
+        }
+    }
+    .map_err(invalid)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::request::WriteRequest;
+    use crate::proto::response::WriteResponse;
+    use crate::server::test_util::client_address;
+    use crate::server::virtual_plc::VirtualPlc;
+
+    fn plc() -> VirtualPlc {
+        let mut plc = VirtualPlc::new(4, 0, 0);
+        plc.write(
+            &client_address(),
+            &WriteRequest::new(0x4020, 0, vec![1, 2, 3, 4]),
+        );
+        plc
+    }
+
+    fn sumup_request(index_group: u32, count: u32, data: Vec<u8>) -> ReadWriteRequest {
+        ReadWriteRequest::new(index_group, count, 1024, data)
+    }
+
+    #[test]
+    fn sumup_dispatch_readex_test() {
+        let mut plc = plc();
+        let sumup = SumupReadRequest::new(vec![
+            ReadRequest::new(0x4020, 0, 2),
+            ReadRequest::new(0x4020, 4, 1),
+            ReadRequest::new(0x4020, 3, 1),
+        ]);
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup.read_write_request()).unwrap();
+        assert_eq!(response.result, AdsError::ErrNoError);
+
+        let responses = SumupReadResponse::read_from(&mut response.data.as_slice()).unwrap();
+        assert_eq!(
+            responses.read_responses,
+            vec![
+                ReadResponse::new(AdsError::ErrNoError, vec![1, 2]),
+                ReadResponse::new(AdsError::AdsErrDeviceInvalidOffset, Vec::new()),
+                ReadResponse::new(AdsError::ErrNoError, vec![4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sumup_dispatch_read_test() {
+        let mut plc = plc();
+        let sumup = SumupReadRequest::new(vec![
+            ReadRequest::new(0x4020, 1, 2),
+            ReadRequest::new(0x4040, 0, 1),
+        ]);
+        let mut data: Vec<u8> = Vec::new();
+        sumup.write_to(&mut data).unwrap();
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup_request(0xF080, 2, data)).unwrap();
+        #[rustfmt::skip]
+        let compare: Vec<u8> = vec![
+            //results
+            0, 0, 0, 0, 0x02, 0x07, 0, 0,
+            //data with requested length
+            2, 3, 0,
+        ];
+        assert_eq!(response.data, compare);
+    }
+
+    #[test]
+    fn sumup_dispatch_write_test() {
+        let mut plc = plc();
+        let sumup = SumupWriteRequest::new(vec![
+            WriteRequest::new(0x4020, 0, vec![9]),
+            WriteRequest::new(0x4020, 3, vec![8, 8]),
+        ]);
+        let mut data: Vec<u8> = Vec::new();
+        sumup.write_to(&mut data).unwrap();
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup_request(0xF081, 2, data)).unwrap();
+        let responses = SumupWriteResponse::read_from(&mut response.data.as_slice()).unwrap();
+        assert_eq!(
+            responses.write_responses,
+            vec![
+                WriteResponse::new(AdsError::ErrNoError),
+                WriteResponse::new(AdsError::AdsErrDeviceInvalidSize),
+            ]
+        );
+        let response = plc.read(&client_address(), &ReadRequest::new(0x4020, 0, 1));
+        assert_eq!(response.data, vec![9]);
+    }
+
+    #[test]
+    fn sumup_dispatch_read_write_test() {
+        let mut plc = plc();
+        let sumup = SumupReadWriteRequest::new(vec![
+            ReadWriteRequest::new(0x4020, 1, 2, vec![7]),
+            ReadWriteRequest::new(0x4020, 2, 2, vec![6]),
+        ]);
+        let mut data: Vec<u8> = Vec::new();
+        sumup.write_to(&mut data).unwrap();
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup_request(0xF082, 2, data)).unwrap();
+        let responses = SumupReadWriteResponse::read_from(&mut response.data.as_slice()).unwrap();
+        assert_eq!(
+            responses.read_write_responses,
+            vec![
+                ReadWriteResponse::new(AdsError::ErrNoError, vec![7, 3]),
+                ReadWriteResponse::new(AdsError::ErrNoError, vec![6, 4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sumup_dispatch_invalid_test() {
+        let mut plc = plc();
+        assert!(handle_sumup(
+            &mut plc,
+            &client_address(),
+            &sumup_request(0x4020, 1, Vec::new())
+        )
+        .is_none());
+
+        let sumup = SumupReadRequest::new(vec![ReadRequest::new(0x4020, 0, 1)]);
+        let mut data: Vec<u8> = Vec::new();
+        sumup.write_to(&mut data).unwrap();
+        let response = handle_sumup(
+            &mut plc,
+            &client_address(),
+            &sumup_request(0xF083, 2, data.clone()),
+        )
+        .unwrap();
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+        let response = handle_sumup(
+            &mut plc,
+            &client_address(),
+            &sumup_request(0xF083, 501, data.clone()),
+        )
+        .unwrap();
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidParm);
+
+        //Response does not fit into the read length
+        let request = ReadWriteRequest::new(0xF083, 1, 4, data);
+        let response = handle_sumup(&mut plc, &client_address(), &request).unwrap();
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+    }
+
+    #[test]
+    fn sumup_dispatch_length_test() {
+        let mut plc = plc();
+        //Claimed write length larger than the payload
+        let data = vec![0x20, 0x40, 0, 0, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 1];
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup_request(0xF081, 1, data)).unwrap();
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidData);
+
+        //Read length larger than the read length of the sumup, rejected before reading
+        for index_group in [0xF080, 0xF083] {
+            let sumup = SumupReadRequest::new(vec![
+                ReadRequest::new(0x4020, 0, 1),
+                ReadRequest::new(0x4020, 0, u32::MAX),
+            ]);
+            let mut data: Vec<u8> = Vec::new();
+            sumup.write_to(&mut data).unwrap();
+            let response = handle_sumup(
+                &mut plc,
+                &client_address(),
+                &sumup_request(index_group, 2, data),
+            )
+            .unwrap();
+            assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+        }
+
+        let sumup =
+            SumupReadWriteRequest::new(vec![ReadWriteRequest::new(0x4020, 0, 2000, vec![])]);
+        let mut data: Vec<u8> = Vec::new();
+        sumup.write_to(&mut data).unwrap();
+        let response =
+            handle_sumup(&mut plc, &client_address(), &sumup_request(0xF082, 1, data)).unwrap();
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+    }
+}
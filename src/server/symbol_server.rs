@@ -0,0 +1,451 @@
+use crate::ads_services::system_services::{
+    ADSIGRP_SYM_DT_UPLOAD, ADSIGRP_SYM_UPLOAD, ADSIGRP_SYM_UPLOADINFO, ADSIGRP_SYM_UPLOADINFO2,
+    GET_SYMHANDLE_BY_NAME, READ_WRITE_SYMVAL_BY_HANDLE, RELEASE_SYMHANDLE,
+};
+use crate::error::AdsError;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::proto_traits::WriteTo;
+use crate::proto::request::*;
+use crate::proto::response::*;
+use crate::proto::symbol::{AdsDataTypeEntry, AdsSymbolEntry, AdsSymbolUploadInfo};
+use crate::proto::windows_1252;
+use crate::server::AdsDevice;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::time::Instant;
+
+///Symbol layer on top of another device (e.g. a [VirtualPlc](super::virtual_plc::VirtualPlc)).
+///Answers the symbol handle services and the symbol and data type upload from the declared symbols and data types.
+///The symbol values are read from and written to the wrapped device at the index group and offset of the symbol.
+///All other requests are passed to the wrapped device.
+/// ```
+/// use ads_proto::proto::symbol::{AdsDataTypeId, AdsSymbolEntry};
+/// use ads_proto::server::symbol_server::SymbolServer;
+/// use ads_proto::server::virtual_plc::VirtualPlc;
+///
+/// let mut server = SymbolServer::new(VirtualPlc::default());
+/// server.add_symbol(AdsSymbolEntry::new("MAIN.counter", "UDINT", 0x4020, 0, 4, AdsDataTypeId::UInt32));
+/// ```
+pub struct SymbolServer<D: AdsDevice> {
+    device: D,
+    symbols: Vec<AdsSymbolEntry>,
+    data_types: Vec<AdsDataTypeEntry>,
+    handles: HashMap<u32, usize>,
+    next_handle: u32,
+}
+
+impl<D: AdsDevice> SymbolServer<D> {
+    pub fn new(device: D) -> Self {
+        SymbolServer {
+            device,
+            symbols: Vec::new(),
+            data_types: Vec::new(),
+            handles: HashMap::new(),
+            next_handle: 1,
+        }
+    }
+
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    pub fn device_mut(&mut self) -> &mut D {
+        &mut self.device
+    }
+
+    pub fn add_symbol(&mut self, symbol: AdsSymbolEntry) {
+        self.symbols.push(symbol);
+    }
+
+    pub fn add_data_type(&mut self, data_type: AdsDataTypeEntry) {
+        self.data_types.push(data_type);
+    }
+
+    pub fn symbols(&self) -> &[AdsSymbolEntry] {
+        &self.symbols
+    }
+
+    pub fn data_types(&self) -> &[AdsDataTypeEntry] {
+        &self.data_types
+    }
+
+    ///Number of handles not released yet
+    pub fn handle_count(&self) -> usize {
+        self.handles.len()
+    }
+
+    ///Symbol names are case insensitive
+    pub fn symbol(&self, name: &str) -> Option<&AdsSymbolEntry> {
+        self.symbols
+            .iter()
+            .find(|s| windows_1252::eq_ignore_case(&s.name, name))
+    }
+
+    pub fn upload_info(&self) -> AdsSymbolUploadInfo {
+        AdsSymbolUploadInfo {
+            symbol_count: self.symbols.len() as u32,
+            symbol_length: self.symbols.iter().map(|s| s.entry_len() as u32).sum(),
+            data_type_count: self.data_types.len() as u32,
+            data_type_length: self.data_types.iter().map(|d| d.entry_len() as u32).sum(),
+            extra_count: 0,
+            extra_length: 0,
+        }
+    }
+
+    fn serialize<T: WriteTo>(entries: &[T]) -> Vec<u8> {
+        let mut buffer: Vec<u8> = Vec::new();
+        for entry in entries {
+            entry
+                .write_to(&mut buffer)
+                .expect("failed to write entry to buffer!");
+        }
+        buffer
+    }
+
+    fn handle_symbol(&self, handle: u32, length: u32) -> Result<&AdsSymbolEntry, AdsError> {
+        let symbol = self
+            .handles
+            .get(&handle)
+            .map(|index| &self.symbols[*index])
+            .ok_or(AdsError::AdsErrDeviceNotifyHndInvalid)?;
+        if length > symbol.size {
+            return Err(AdsError::AdsErrDeviceInvalidSize);
+        }
+        Ok(symbol)
+    }
+
+    fn get_handle(&mut self, request: &ReadWriteRequest) -> Result<Vec<u8>, AdsError> {
+        if request.read_length < 4 {
+            return Err(AdsError::AdsErrDeviceInvalidSize);
+        }
+        let name = windows_1252::decode(&request.data);
+        let name = name.trim_end_matches('\0');
+        let index = self
+            .symbols
+            .iter()
+            .position(|s| windows_1252::eq_ignore_case(&s.name, name))
+            .ok_or(AdsError::AdsErrDeviceSymbolNotFound)?;
+        let handle = self.next_handle;
+        self.next_handle = self.next_handle.wrapping_add(1).max(1);
+        self.handles.insert(handle, index);
+        Ok(handle.to_le_bytes().to_vec())
+    }
+
+    fn release_handle(&mut self, data: &[u8]) -> Result<(), AdsError> {
+        let handle: [u8; 4] = data
+            .get(..4)
+            .and_then(|d| d.try_into().ok())
+            .ok_or(AdsError::AdsErrDeviceInvalidSize)?;
+        self.handles
+            .remove(&u32::from_le_bytes(handle))
+            .map(|_| ())
+            .ok_or(AdsError::AdsErrDeviceNotifyHndInvalid)
+    }
+}
+
+impl<D: AdsDevice> AdsDevice for SymbolServer<D> {
+    fn read_device_info(&mut self, source: &AmsAddress) -> ReadDeviceInfoResponse {
+        self.device.read_device_info(source)
+    }
+
+    fn read_state(&mut self, source: &AmsAddress) -> ReadStateResponse {
+        self.device.read_state(source)
+    }
+
+    fn read(&mut self, source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+        //The client reads the lengths of the upload info first, a shorter read is an error
+        let upload = |data: Vec<u8>| {
+            if data.len() > request.length as usize {
+                return ReadResponse::new(AdsError::AdsErrDeviceInvalidSize, Vec::new());
+            }
+            ReadResponse::new(AdsError::ErrNoError, data)
+        };
+        match request.index_group {
+            ig if ig == ADSIGRP_SYM_UPLOAD.index_group => {
+                upload(SymbolServer::<D>::serialize(&self.symbols))
+            }
+            ig if ig == ADSIGRP_SYM_DT_UPLOAD.index_group => {
+                upload(SymbolServer::<D>::serialize(&self.data_types))
+            }
+            ig if ig == ADSIGRP_SYM_UPLOADINFO.index_group => {
+                let info = self.upload_info();
+                let mut data = info.symbol_count.to_le_bytes().to_vec();
+                data.extend_from_slice(&info.symbol_length.to_le_bytes());
+                upload(data)
+            }
+            ig if ig == ADSIGRP_SYM_UPLOADINFO2.index_group => {
+                upload(SymbolServer::<D>::serialize(&[self.upload_info()]))
+            }
+            ig if ig == READ_WRITE_SYMVAL_BY_HANDLE.index_group => {
+                match self.handle_symbol(request.index_offset, request.length) {
+                    Ok(symbol) => {
+                        let request = ReadRequest::new(
+                            symbol.index_group,
+                            symbol.index_offset,
+                            request.length,
+                        );
+                        self.device.read(source, &request)
+                    }
+                    Err(e) => ReadResponse::new(e, Vec::new()),
+                }
+            }
+            _ => self.device.read(source, request),
+        }
+    }
+
+    fn write(&mut self, source: &AmsAddress, request: &WriteRequest) -> WriteResponse {
+        match request.index_group {
+            ig if ig == READ_WRITE_SYMVAL_BY_HANDLE.index_group => {
+                match self.handle_symbol(request.index_offset, request.data.len() as u32) {
+                    Ok(symbol) => {
+                        let request = WriteRequest::new(
+                            symbol.index_group,
+                            symbol.index_offset,
+                            request.data.clone(),
+                        );
+                        self.device.write(source, &request)
+                    }
+                    Err(e) => WriteResponse::new(e),
+                }
+            }
+            ig if ig == RELEASE_SYMHANDLE.index_group => match self.release_handle(&request.data) {
+                Ok(()) => WriteResponse::new(AdsError::ErrNoError),
+                Err(e) => WriteResponse::new(e),
+            },
+            _ => self.device.write(source, request),
+        }
+    }
+
+    fn read_write(&mut self, source: &AmsAddress, request: &ReadWriteRequest) -> ReadWriteResponse {
+        if request.index_group == GET_SYMHANDLE_BY_NAME.index_group {
+            return match self.get_handle(request) {
+                Ok(data) => ReadWriteResponse::new(AdsError::ErrNoError, data),
+                Err(e) => ReadWriteResponse::new(e, Vec::new()),
+            };
+        }
+        self.device.read_write(source, request)
+    }
+
+    fn write_control(
+        &mut self,
+        source: &AmsAddress,
+        request: &WriteControlRequest,
+    ) -> WriteControlResponse {
+        self.device.write_control(source, request)
+    }
+
+    fn add_device_notification(
+        &mut self,
+        source: &AmsAddress,
+        request: &AddDeviceNotificationRequest,
+    ) -> AddDeviceNotificationResponse {
+        self.device.add_device_notification(source, request)
+    }
+
+    fn delete_device_notification(
+        &mut self,
+        source: &AmsAddress,
+        request: &DeleteDeviceNotificationRequest,
+    ) -> DeleteDeviceNotificationResponse {
+        self.device.delete_device_notification(source, request)
+    }
+
+    fn poll_notifications(&mut self, now: Instant) -> Vec<(AmsAddress, AdsNotificationStream)> {
+        self.device.poll_notifications(now)
+    }
+
+    fn next_notification(&self) -> Option<Instant> {
+        self.device.next_notification()
+    }
+
+    ///Handles are not bound to a client and stay valid
+    fn client_disconnected(&mut self, source: &AmsAddress) {
+        self.device.client_disconnected(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::proto_traits::ReadFrom;
+    use crate::proto::symbol::{read_entries, AdsDataTypeId};
+    use crate::server::test_util::client_address;
+    use crate::server::virtual_plc::{ProcessImage, VirtualPlc};
+
+    fn server() -> SymbolServer<VirtualPlc> {
+        let mut server = SymbolServer::new(VirtualPlc::new(16, 0, 0));
+        server.add_symbol(AdsSymbolEntry::new(
+            "MAIN.counter",
+            "UDINT",
+            0x4020,
+            4,
+            4,
+            AdsDataTypeId::UInt32,
+        ));
+        server.add_symbol(AdsSymbolEntry::new(
+            "MAIN.test",
+            "ST_Test",
+            0x4020,
+            8,
+            6,
+            AdsDataTypeId::BigType,
+        ));
+        let mut data_type = AdsDataTypeEntry::new("ST_Test", "", 6, AdsDataTypeId::BigType);
+        data_type.sub_items.push(AdsDataTypeEntry::sub_item(
+            "a",
+            "INT",
+            0,
+            2,
+            AdsDataTypeId::Int16,
+        ));
+        server.add_data_type(data_type);
+        server
+    }
+
+    fn get_handle(server: &mut SymbolServer<VirtualPlc>, name: &str) -> ReadWriteResponse {
+        server.read_write(
+            &client_address(),
+            &ReadWriteRequest::new(
+                GET_SYMHANDLE_BY_NAME.index_group,
+                0,
+                4,
+                windows_1252::encode(name),
+            ),
+        )
+    }
+
+    #[test]
+    fn symbol_server_handle_test() {
+        let mut server = server();
+        let response = get_handle(&mut server, "main.COUNTER\0");
+        assert_eq!(response.result, AdsError::ErrNoError);
+        let handle = u32::from_le_bytes(response.data.try_into().unwrap());
+        assert_eq!(server.handle_count(), 1);
+
+        let response = server.write(
+            &client_address(),
+            &WriteRequest::new(0xF005, handle, 42u32.to_le_bytes().to_vec()),
+        );
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(
+            server.device().image(ProcessImage::Memory)[4..8],
+            [42, 0, 0, 0]
+        );
+
+        let response = server.read(&client_address(), &ReadRequest::new(0xF005, handle, 4));
+        assert_eq!(response.data, vec![42, 0, 0, 0]);
+        let response = server.read(&client_address(), &ReadRequest::new(0xF005, handle, 8));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+
+        let response = server.write(
+            &client_address(),
+            &WriteRequest::new(0xF006, 0, handle.to_le_bytes().to_vec()),
+        );
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(server.handle_count(), 0);
+
+        //Released handle
+        let response = server.read(&client_address(), &ReadRequest::new(0xF005, handle, 4));
+        assert_eq!(response.result, AdsError::AdsErrDeviceNotifyHndInvalid);
+        let response = server.write(
+            &client_address(),
+            &WriteRequest::new(0xF006, 0, handle.to_le_bytes().to_vec()),
+        );
+        assert_eq!(response.result, AdsError::AdsErrDeviceNotifyHndInvalid);
+    }
+
+    #[test]
+    fn symbol_server_umlaut_test() {
+        let mut server = server();
+        server.add_symbol(AdsSymbolEntry::new(
+            "MAIN.zähler",
+            "UDINT",
+            0x4020,
+            0,
+            4,
+            AdsDataTypeId::UInt32,
+        ));
+        assert_eq!(server.symbol("MAIN.ZÄHLER").unwrap().name, "MAIN.zähler");
+
+        let response = get_handle(&mut server, "main.ZÄHLER\0");
+        assert_eq!(response.result, AdsError::ErrNoError);
+        let handle = u32::from_le_bytes(response.data.try_into().unwrap());
+        let response = server.write(
+            &client_address(),
+            &WriteRequest::new(0xF005, handle, 7u32.to_le_bytes().to_vec()),
+        );
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(
+            server.device().image(ProcessImage::Memory)[0..4],
+            [7, 0, 0, 0]
+        );
+
+        let response = server.read(&client_address(), &ReadRequest::new(0xF00C, 0, 8));
+        let symbol_length = u32::from_le_bytes(response.data[4..].try_into().unwrap());
+        let response = server.read(
+            &client_address(),
+            &ReadRequest::new(0xF00B, 0, symbol_length),
+        );
+        let symbols: Vec<AdsSymbolEntry> = read_entries(&response.data).unwrap();
+        assert_eq!(symbols[2].name, "MAIN.zähler");
+    }
+
+    #[test]
+    fn symbol_server_not_found_test() {
+        let mut server = server();
+        let response = get_handle(&mut server, "MAIN.unknown");
+        assert_eq!(response.result, AdsError::AdsErrDeviceSymbolNotFound);
+        assert_eq!(server.handle_count(), 0);
+    }
+
+    #[test]
+    fn symbol_server_upload_test() {
+        let mut server = server();
+        let response = server.read(&client_address(), &ReadRequest::new(0xF00F, 0, 24));
+        let info = AdsSymbolUploadInfo::read_from(&mut response.data.as_slice()).unwrap();
+        assert_eq!(info.symbol_count, 2);
+        assert_eq!(info.data_type_count, 1);
+
+        let response = server.read(&client_address(), &ReadRequest::new(0xF00C, 0, 8));
+        assert_eq!(response.data[..4], [2, 0, 0, 0]);
+        assert_eq!(response.data[4..], info.symbol_length.to_le_bytes());
+
+        let response = server.read(
+            &client_address(),
+            &ReadRequest::new(0xF00B, 0, info.symbol_length),
+        );
+        let symbols: Vec<AdsSymbolEntry> = read_entries(&response.data).unwrap();
+        assert_eq!(symbols, server.symbols());
+
+        let response = server.read(
+            &client_address(),
+            &ReadRequest::new(0xF00E, 0, info.data_type_length),
+        );
+        let data_types: Vec<AdsDataTypeEntry> = read_entries(&response.data).unwrap();
+        assert_eq!(data_types, server.data_types());
+
+        //Too short for the upload
+        let response = server.read(
+            &client_address(),
+            &ReadRequest::new(0xF00B, 0, info.symbol_length - 1),
+        );
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+        assert!(response.data.is_empty());
+    }
+
+    #[test]
+    fn symbol_server_pass_through_test() {
+        let mut server = server();
+        let response = server.write(&client_address(), &WriteRequest::new(0x4020, 0, vec![1]));
+        assert_eq!(response.result, AdsError::ErrNoError);
+        let response = server.read(&client_address(), &ReadRequest::new(0x4020, 0, 1));
+        assert_eq!(response.data, vec![1]);
+        assert_eq!(
+            server
+                .read_device_info(&client_address())
+                .get_device_name()
+                .unwrap(),
+            "Virtual PLC"
+        );
+    }
+}
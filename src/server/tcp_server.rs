@@ -0,0 +1,732 @@
+use crate::error::AdsError;
+use crate::proto::ams_address::{AmsAddress, AmsNetId};
+use crate::proto::ams_header::{AmsHeader, AmsTcpHeader};
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::proto::response::Response;
+use crate::proto::state_flags::{NetProto, StateFlags};
+use crate::server::{error_response, handle_request, AdsDevice};
+use std::collections::HashMap;
+use std::io::{self, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+///Default TCP port of the ADS router
+pub const ADS_TCP_PORT: u16 = 48898;
+
+///Shortest interval in which the device notifications are polled
+pub const NOTIFICATION_INTERVAL: Duration = Duration::from_millis(1);
+///Longest interval in which the device notifications are polled
+pub const NOTIFICATION_IDLE_INTERVAL: Duration = Duration::from_millis(100);
+///First delay after a failed accept, doubled up to [MAX_ACCEPT_BACKOFF] while accept fails
+pub const ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+///Longest delay after a failed accept
+pub const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+pub type SharedDevice = Arc<Mutex<dyn AdsDevice>>;
+type SharedWriter = Arc<Mutex<TcpStream>>;
+
+///Hosts [AdsDevice]s on AMS ports of a single AmsNetId.
+///Requests to an unknown port are answered with ErrTargetPortNotFound, requests to another AmsNetId with ErrTargetMachineNotFound.
+///The server can be cloned, devices added or removed on a clone are visible to the running server.
+/// ```no_run
+/// use ads_proto::proto::ams_address::AmsNetId;
+/// use ads_proto::server::tcp_server::AdsServer;
+/// use ads_proto::server::AdsDevice;
+///
+/// struct Dummy;
+/// impl AdsDevice for Dummy {}
+///
+/// let server = AdsServer::new(AmsNetId::new(127, 0, 0, 1, 1, 1));
+/// server.add_device(851, Dummy);
+/// let handle = server.bind("127.0.0.1:48898").unwrap();
+/// //...
+/// handle.shutdown();
+/// ```
+#[derive(Clone)]
+pub struct AdsServer {
+    net_id: AmsNetId,
+    devices: Arc<Mutex<HashMap<u16, SharedDevice>>>,
+    ///Set to wake the notification thread
+    notification_wake: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl AdsServer {
+    pub fn new(net_id: AmsNetId) -> Self {
+        AdsServer {
+            net_id,
+            devices: Arc::new(Mutex::new(HashMap::new())),
+            notification_wake: Arc::new((Mutex::new(false), Condvar::new())),
+        }
+    }
+
+    pub fn net_id(&self) -> &AmsNetId {
+        &self.net_id
+    }
+
+    ///Host the device on the AMS port. A device already registered on this port is replaced.
+    ///Returns the shared device to access it while the server is running.
+    pub fn add_device<D: AdsDevice + 'static>(&self, port: u16, device: D) -> Arc<Mutex<D>> {
+        let device = Arc::new(Mutex::new(device));
+        self.add_shared_device(port, device.clone());
+        device
+    }
+
+    pub fn add_shared_device(&self, port: u16, device: SharedDevice) {
+        self.devices
+            .lock()
+            .expect("device lock poisoned")
+            .insert(port, device);
+    }
+
+    pub fn remove_device(&self, port: u16) -> bool {
+        self.devices
+            .lock()
+            .expect("device lock poisoned")
+            .remove(&port)
+            .is_some()
+    }
+
+    pub fn device(&self, port: u16) -> Option<SharedDevice> {
+        self.devices
+            .lock()
+            .expect("device lock poisoned")
+            .get(&port)
+            .cloned()
+    }
+
+    ///AMS ports with a device, sorted ascending
+    pub fn ports(&self) -> Vec<u16> {
+        let mut ports: Vec<u16> = self
+            .devices
+            .lock()
+            .expect("device lock poisoned")
+            .keys()
+            .copied()
+            .collect();
+        ports.sort_unstable();
+        ports
+    }
+
+    ///Handle a received request and return the AmsHeader to send back.
+    ///Returns None for responses and requests without response.
+    ///Independent of the transport the header was received from.
+    pub fn handle(&self, mut header: AmsHeader) -> Option<AmsHeader> {
+        if header.state_flags().is_response() {
+            return None;
+        }
+        let net_proto = match header.state_flags().is_tcp() {
+            true => NetProto::Tcp,
+            false => NetProto::Udp,
+        };
+        let command_id = header.command_id();
+        let source = header.source_address().clone();
+        let target = header.target_address().clone();
+
+        let response = if target.ams_net_id != self.net_id {
+            error_response(command_id, AdsError::ErrTargetMachineNotFound)
+        } else if let Some(device) = self.device(target.port) {
+            match header.request() {
+                Ok(request) => handle_request(
+                    &mut *device.lock().expect("device lock poisoned"),
+                    &source,
+                    &request,
+                ),
+                Err(_) => error_response(command_id, AdsError::AdsErrDeviceInvalidData),
+            }
+        } else {
+            error_response(command_id, AdsError::ErrTargetPortNotFound)
+        }?;
+
+        //The request may have added a notification
+        self.wake_notifications();
+        header
+            .update_command(response, StateFlags::new(true, true, net_proto))
+            .ok()?;
+        header.swap_address();
+        Some(header)
+    }
+
+    ///Poll the notifications of all devices.
+    ///Returns the AmsHeaders with the notification streams to send to the clients.
+    pub fn notifications(&self, now: Instant) -> Vec<AmsHeader> {
+        let devices: Vec<(u16, SharedDevice)> = self
+            .devices
+            .lock()
+            .expect("device lock poisoned")
+            .iter()
+            .map(|(port, device)| (*port, device.clone()))
+            .collect();
+
+        let mut headers: Vec<AmsHeader> = Vec::new();
+        for (port, device) in devices {
+            let streams = device
+                .lock()
+                .expect("device lock poisoned")
+                .poll_notifications(now);
+            for (client, stream) in streams {
+                headers.push(AmsHeader::new(
+                    client,
+                    AmsAddress::new(self.net_id.clone(), port),
+                    StateFlags::req_default(),
+                    0,
+                    Response::DeviceNotification(stream),
+                ));
+            }
+        }
+        headers
+    }
+
+    ///Time the next notification of the devices is due
+    pub fn next_notification(&self) -> Option<Instant> {
+        let devices: Vec<SharedDevice> = self
+            .devices
+            .lock()
+            .expect("device lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        devices
+            .iter()
+            .filter_map(|d| d.lock().expect("device lock poisoned").next_notification())
+            .min()
+    }
+
+    fn wake_notifications(&self) {
+        let (woken, condvar) = &*self.notification_wake;
+        *woken.lock().expect("notification lock poisoned") = true;
+        condvar.notify_all();
+    }
+
+    ///True if a notification of the devices is due at `now`
+    pub(crate) fn notification_due(&self, now: Instant) -> bool {
+        self.next_notification().is_some_and(|due| due <= now)
+    }
+
+    ///Time until the next notification is due, at least [NOTIFICATION_INTERVAL] and at most `max`
+    pub(crate) fn notification_timeout(&self, max: Duration) -> Duration {
+        self.next_notification()
+            .map(|due| due.saturating_duration_since(Instant::now()))
+            .unwrap_or(max)
+            .clamp(NOTIFICATION_INTERVAL, max)
+    }
+
+    ///Wait until the next notification is due, a request was handled or `max` elapsed.
+    ///Waits at least [NOTIFICATION_INTERVAL] unless woken.
+    fn wait_notifications(&self, max: Duration) {
+        let timeout = self.notification_timeout(max);
+        let (woken, condvar) = &*self.notification_wake;
+        let guard = woken.lock().expect("notification lock poisoned");
+        let (mut guard, _) = condvar
+            .wait_timeout_while(guard, timeout, |woken| !*woken)
+            .expect("notification lock poisoned");
+        *guard = false;
+    }
+
+    ///Inform all devices that a client is gone (e.g. to remove its notifications)
+    pub fn client_disconnected(&self, client: &AmsAddress) {
+        let devices: Vec<SharedDevice> = self
+            .devices
+            .lock()
+            .expect("device lock poisoned")
+            .values()
+            .cloned()
+            .collect();
+        for device in devices {
+            device
+                .lock()
+                .expect("device lock poisoned")
+                .client_disconnected(client);
+        }
+    }
+
+    ///Bind a TcpListener and serve the devices. See [AdsServer::serve]
+    pub fn bind<A: ToSocketAddrs>(&self, addr: A) -> io::Result<ServerHandle> {
+        self.serve(TcpListener::bind(addr)?)
+    }
+
+    ///Accept connections on a background thread. Every connection is served on its own thread.
+    ///Device notifications are polled when they are due and sent over the connection the client used last.
+    ///When a connection is closed the devices are informed about the clients of this connection.
+    ///The server stops when the returned handle is shut down or dropped.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<ServerHandle> {
+        self.serve_transport(listener, TcpTransport)
+    }
+
+    ///Accept loop and notification thread shared by the transports over TCP
+    pub(crate) fn serve_transport<T: Transport>(
+        &self,
+        listener: TcpListener,
+        transport: T,
+    ) -> io::Result<ServerHandle> {
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let connections: Arc<Connections<T::Sink>> = Arc::new(Connections::default());
+        let transport = Arc::new(transport);
+
+        let server = self.clone();
+        let accept_stop = stop.clone();
+        let accept_connections = connections.clone();
+        let accept_thread = thread::spawn(move || {
+            let next_id = AtomicUsize::new(0);
+            let mut backoff = ACCEPT_BACKOFF;
+            for stream in listener.incoming() {
+                if accept_stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                //e.g. out of file descriptors, wait instead of spinning
+                let stream = match stream {
+                    Ok(s) => {
+                        backoff = ACCEPT_BACKOFF;
+                        s
+                    }
+                    Err(_) => {
+                        thread::sleep(backoff);
+                        backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                        continue;
+                    }
+                };
+                let id = next_id.fetch_add(1, Ordering::SeqCst);
+                let (sink, connection) = match transport.open(&stream) {
+                    Ok(c) => c,
+                    Err(_) => continue,
+                };
+                if let Ok(s) = stream.try_clone() {
+                    accept_connections.insert(id, s, sink);
+                }
+                let server = server.clone();
+                let transport = transport.clone();
+                let connections = accept_connections.clone();
+                let stop = accept_stop.clone();
+                thread::spawn(move || {
+                    let _ = transport.serve(&server, id, stream, connection, &connections, &stop);
+                    for client in connections.remove(id) {
+                        server.client_disconnected(&client);
+                    }
+                });
+            }
+        });
+
+        let server = self.clone();
+        let notification_stop = stop.clone();
+        let notification_connections = connections.clone();
+        let notification_thread = thread::spawn(move || {
+            while !notification_stop.load(Ordering::SeqCst) {
+                for header in server.notifications(Instant::now()) {
+                    if let Some(sink) = notification_connections.sink(header.target_address()) {
+                        let _ = T::send(&sink, header);
+                    }
+                }
+                server.wait_notifications(NOTIFICATION_IDLE_INTERVAL);
+            }
+        });
+
+        Ok(ServerHandle {
+            server: self.clone(),
+            local_addr,
+            stop,
+            connections,
+            threads: vec![accept_thread, notification_thread],
+        })
+    }
+}
+
+///Transport of the connections accepted by [AdsServer::serve_transport]
+pub(crate) trait Transport: Send + Sync + 'static {
+    ///Sends the notifications to a connection, shared with the notification thread
+    type Sink: Clone + Send + 'static;
+    ///State of the connection passed to [Transport::serve]
+    type Connection: Send + 'static;
+
+    fn open(&self, stream: &TcpStream) -> io::Result<(Self::Sink, Self::Connection)>;
+
+    ///Answer the requests of a connection until it is closed or the server is stopped
+    fn serve(
+        &self,
+        server: &AdsServer,
+        id: usize,
+        stream: TcpStream,
+        connection: Self::Connection,
+        connections: &Connections<Self::Sink>,
+        stop: &AtomicBool,
+    ) -> io::Result<()>;
+
+    fn send(sink: &Self::Sink, header: AmsHeader) -> io::Result<()>;
+}
+
+///Plain AMS/TCP, notifications are written by the notification thread
+struct TcpTransport;
+
+impl Transport for TcpTransport {
+    type Sink = SharedWriter;
+    type Connection = SharedWriter;
+
+    fn open(&self, stream: &TcpStream) -> io::Result<(SharedWriter, SharedWriter)> {
+        let writer = Arc::new(Mutex::new(stream.try_clone()?));
+        Ok((writer.clone(), writer))
+    }
+
+    fn serve(
+        &self,
+        server: &AdsServer,
+        id: usize,
+        stream: TcpStream,
+        writer: SharedWriter,
+        connections: &Connections<SharedWriter>,
+        _stop: &AtomicBool,
+    ) -> io::Result<()> {
+        let mut reader = BufReader::new(stream);
+        loop {
+            let request = AmsTcpHeader::read_from(&mut reader)?;
+            connections.add_client(request.ams_header.source_address(), id);
+            if let Some(response) = server.handle(request.ams_header) {
+                TcpTransport::send(&writer, response)?;
+            }
+        }
+    }
+
+    fn send(writer: &SharedWriter, header: AmsHeader) -> io::Result<()> {
+        let mut buffer: Vec<u8> = Vec::new();
+        AmsTcpHeader::from(header).write_to(&mut buffer)?;
+        writer
+            .lock()
+            .expect("connection lock poisoned")
+            .write_all(&buffer)
+    }
+}
+
+///Open connections and the clients (AmsAddress) which sent requests over them
+pub(crate) struct Connections<S> {
+    ///stream to shut the connection down and the sink for the notifications of the connection
+    streams: Mutex<HashMap<usize, (TcpStream, S)>>,
+    clients: Mutex<HashMap<AmsAddress, usize>>,
+}
+
+impl<S> Default for Connections<S> {
+    fn default() -> Self {
+        Connections {
+            streams: Mutex::new(HashMap::new()),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S: Clone> Connections<S> {
+    fn insert(&self, id: usize, stream: TcpStream, sink: S) {
+        self.streams
+            .lock()
+            .expect("connection lock poisoned")
+            .insert(id, (stream, sink));
+    }
+
+    pub(crate) fn add_client(&self, client: &AmsAddress, id: usize) {
+        let mut clients = self.clients.lock().expect("connection lock poisoned");
+        if clients.get(client) != Some(&id) {
+            clients.insert(client.clone(), id);
+        }
+    }
+
+    fn sink(&self, client: &AmsAddress) -> Option<S> {
+        let id = *self
+            .clients
+            .lock()
+            .expect("connection lock poisoned")
+            .get(client)?;
+        self.streams
+            .lock()
+            .expect("connection lock poisoned")
+            .get(&id)
+            .map(|(_, sink)| sink.clone())
+    }
+
+    ///Remove the connection and return its clients
+    fn remove(&self, id: usize) -> Vec<AmsAddress> {
+        self.streams
+            .lock()
+            .expect("connection lock poisoned")
+            .remove(&id);
+        let mut clients = self.clients.lock().expect("connection lock poisoned");
+        let removed: Vec<AmsAddress> = clients
+            .iter()
+            .filter(|(_, connection)| **connection == id)
+            .map(|(client, _)| client.clone())
+            .collect();
+        for client in &removed {
+            clients.remove(client);
+        }
+        removed
+    }
+}
+
+///Shut down the open connections independent of the transport
+trait ShutdownConnections: Send + Sync {
+    fn shutdown_all(&self);
+}
+
+impl<S: Send> ShutdownConnections for Connections<S> {
+    fn shutdown_all(&self) {
+        for (stream, _) in self
+            .streams
+            .lock()
+            .expect("connection lock poisoned")
+            .values()
+        {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+///Handle of a running [AdsServer]. Shuts the server down when dropped.
+pub struct ServerHandle {
+    server: AdsServer,
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    connections: Arc<dyn ShutdownConnections>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl ServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    ///Stop accepting connections and close the open connections
+    pub fn shutdown(mut self) {
+        self.stop_server();
+    }
+
+    fn stop_server(&mut self) {
+        if self.threads.is_empty() {
+            return;
+        }
+        self.stop.store(true, Ordering::SeqCst);
+        self.server.wake_notifications();
+        //Wake up the blocking accept
+        let mut wake_addr = self.local_addr;
+        if wake_addr.ip().is_unspecified() {
+            wake_addr.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        }
+        let _ = TcpStream::connect(wake_addr);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+        self.connections.shutdown_all();
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        self.stop_server();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::command_id::CommandID;
+    use crate::proto::request::{AddDeviceNotificationRequest, ReadRequest, Request, WriteRequest};
+    use crate::proto::response::{
+        AddDeviceNotificationResponse, AdsNotificationStream, ReadResponse, WriteResponse,
+    };
+    use crate::server::notification_engine::NotificationEngine;
+    use crate::server::test_util::{client_address, net_id};
+    use crate::server::virtual_plc::{ProcessImage, VirtualPlc};
+    use std::convert::TryInto;
+
+    #[derive(Default)]
+    struct Memory {
+        data: Vec<u8>,
+    }
+
+    impl AdsDevice for Memory {
+        fn read(&mut self, _source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+            let data = self
+                .data
+                .iter()
+                .take(request.length as usize)
+                .copied()
+                .collect();
+            ReadResponse::new(AdsError::ErrNoError, data)
+        }
+
+        fn write(&mut self, _source: &AmsAddress, request: &WriteRequest) -> WriteResponse {
+            self.data = request.data.clone();
+            WriteResponse::new(AdsError::ErrNoError)
+        }
+    }
+
+    fn request_header(target: AmsAddress, request: Request) -> AmsHeader {
+        AmsHeader::new(
+            target,
+            client_address(),
+            StateFlags::req_default(),
+            7,
+            request,
+        )
+    }
+
+    #[test]
+    fn ads_server_handle_test() {
+        let server = AdsServer::new(net_id());
+        let memory = server.add_device(851, Memory::default());
+        assert_eq!(server.ports(), vec![851]);
+
+        let header = request_header(
+            AmsAddress::new(net_id(), 851),
+            Request::Write(WriteRequest::new(0x4020, 0, vec![1, 2, 3])),
+        );
+        let mut response = server.handle(header).unwrap();
+        assert_eq!(response.invoke_id(), 7);
+        assert!(response.state_flags().is_response());
+        assert_eq!(response.target_address(), &client_address());
+        assert_eq!(
+            response.response().unwrap(),
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        assert_eq!(memory.lock().unwrap().data, vec![1, 2, 3]);
+
+        //Unknown port
+        let header = request_header(
+            AmsAddress::new(net_id(), 852),
+            Request::Read(ReadRequest::new(0x4020, 0, 1)),
+        );
+        let mut response = server.handle(header).unwrap();
+        assert_eq!(response.command_id(), CommandID::Read);
+        assert_eq!(
+            response.response().unwrap(),
+            Response::Read(ReadResponse::new(
+                AdsError::ErrTargetPortNotFound,
+                Vec::new()
+            ))
+        );
+
+        //Unknown net id
+        let header = request_header(
+            AmsAddress::new(AmsNetId::new(10, 0, 0, 1, 1, 1), 851),
+            Request::Read(ReadRequest::new(0x4020, 0, 1)),
+        );
+        let response = server.handle(header).unwrap();
+        assert_eq!(
+            response.response_result(),
+            Some(AdsError::ErrTargetMachineNotFound)
+        );
+
+        //Responses are not answered
+        assert!(server.handle(response).is_none());
+
+        assert!(server.remove_device(851));
+        assert!(server.ports().is_empty());
+    }
+
+    #[test]
+    fn ads_server_tcp_test() {
+        let server = AdsServer::new(net_id());
+        server.add_device(851, Memory::default());
+        let handle = server.bind("127.0.0.1:0").unwrap();
+
+        let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+        let target = AmsAddress::new(net_id(), 851);
+        for request in [
+            Request::Write(WriteRequest::new(0x4020, 0, vec![4, 5])),
+            Request::Read(ReadRequest::new(0x4020, 0, 2)),
+        ] {
+            let mut buffer: Vec<u8> = Vec::new();
+            AmsTcpHeader::from(request_header(target.clone(), request))
+                .write_to(&mut buffer)
+                .unwrap();
+            stream.write_all(&buffer).unwrap();
+        }
+
+        let mut write_response = AmsTcpHeader::read_from(&mut stream).unwrap();
+        assert_eq!(
+            write_response.ams_header.response().unwrap(),
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        let mut read_response = AmsTcpHeader::read_from(&mut stream).unwrap();
+        assert_eq!(
+            read_response.ams_header.response().unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![4, 5]))
+        );
+
+        handle.shutdown();
+        //Connection is closed by the server
+        assert!(AmsTcpHeader::read_from(&mut stream).is_err());
+    }
+
+    #[test]
+    fn ads_server_tcp_notification_test() {
+        let server = AdsServer::new(net_id());
+        let engine = server.add_device(851, NotificationEngine::new(VirtualPlc::new(4, 0, 0)));
+        let handle = server.bind("127.0.0.1:0").unwrap();
+
+        let mut stream = TcpStream::connect(handle.local_addr()).unwrap();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .on_change(Duration::ZERO)
+            .length(1)
+            .build()
+            .unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        AmsTcpHeader::from(request_header(
+            AmsAddress::new(net_id(), 851),
+            Request::AddDeviceNotification(request),
+        ))
+        .write_to(&mut buffer)
+        .unwrap();
+        stream.write_all(&buffer).unwrap();
+
+        //The initial value can be sent before the response
+        let mut headers = [
+            AmsTcpHeader::read_from(&mut stream).unwrap(),
+            AmsTcpHeader::read_from(&mut stream).unwrap(),
+        ];
+        headers.sort_by_key(|h| h.ams_header.command_id() != CommandID::AddDeviceNotification);
+        let [mut response, mut notification] = headers;
+        let response: AddDeviceNotificationResponse =
+            response.ams_header.response().unwrap().try_into().unwrap();
+        assert_eq!(response.result, AdsError::ErrNoError);
+
+        assert_eq!(notification.ams_header.target_address(), &client_address());
+        assert_eq!(
+            notification.ams_header.source_address(),
+            &AmsAddress::new(net_id(), 851)
+        );
+        let notification: AdsNotificationStream = notification
+            .ams_header
+            .response()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let sample = &notification.ads_stamp_headers[0].notification_samples[0];
+        assert_eq!(sample.notification_handle, response.notification_handle);
+        assert_eq!(sample.data, vec![0]);
+
+        //Changed value
+        engine
+            .lock()
+            .unwrap()
+            .device_mut()
+            .image_mut(ProcessImage::Memory)[0] = 3;
+        let mut notification = AmsTcpHeader::read_from(&mut stream).unwrap();
+        let notification: AdsNotificationStream = notification
+            .ams_header
+            .response()
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(
+            notification.ads_stamp_headers[0].notification_samples[0].data,
+            vec![3]
+        );
+
+        //Notifications are removed when the client disconnects
+        drop(stream);
+        let start = Instant::now();
+        while !engine.lock().unwrap().is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(1));
+        }
+        handle.shutdown();
+    }
+}
@@ -0,0 +1,316 @@
+use crate::proto::ams_header::{AmsHeader, AmsTcpHeader};
+use crate::proto::ams_tcp_frame::AMS_TCP_MAX_FRAME_LEN;
+use crate::proto::proto_traits::{ReadFrom, WriteTo};
+use crate::server::tcp_server::{
+    AdsServer, Connections, ServerHandle, Transport, NOTIFICATION_INTERVAL,
+};
+use crate::tls::handshake_error;
+use openssl::ssl::{HandshakeError, SslAcceptor};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+///Length of the AmsTcpHeader prefix (reserved, length)
+const AMS_TCP_PREFIX_LEN: usize = 6;
+///Longest time a rejected connection is kept open for the client to receive the alert
+const REJECT_LINGER: Duration = Duration::from_secs(1);
+
+impl AdsServer {
+    ///Bind a TcpListener and serve the devices over TLS. See [AdsServer::serve_tls]
+    pub fn bind_tls<A: ToSocketAddrs>(
+        &self,
+        addr: A,
+        config: SslAcceptor,
+    ) -> io::Result<ServerHandle> {
+        self.serve_tls(TcpListener::bind(addr)?, config)
+    }
+
+    ///Secure ADS: same as [AdsServer::serve] but the AMS/TCP frames are sent over TLS.
+    ///Create the config with [server_config](crate::tls::server_config).
+    ///Connections failing the handshake (e.g. client certificate or PSK not accepted) are closed.
+    pub fn serve_tls(
+        &self,
+        listener: TcpListener,
+        config: SslAcceptor,
+    ) -> io::Result<ServerHandle> {
+        self.serve_transport(listener, TlsTransport { config })
+    }
+}
+
+///AMS/TCP over TLS. The TLS stream can not be shared,
+///the notifications are passed to the connection thread which sends them.
+struct TlsTransport {
+    config: SslAcceptor,
+}
+
+impl Transport for TlsTransport {
+    type Sink = Sender<AmsHeader>;
+    type Connection = Receiver<AmsHeader>;
+
+    fn open(&self, _stream: &TcpStream) -> io::Result<(Sender<AmsHeader>, Receiver<AmsHeader>)> {
+        Ok(channel())
+    }
+
+    ///Answer the requests of a TLS connection and send the notifications of its clients until it is closed.
+    ///The socket is read with a short timeout to send the notifications from the same thread.
+    fn serve(
+        &self,
+        server: &AdsServer,
+        id: usize,
+        stream: TcpStream,
+        notifications: Receiver<AmsHeader>,
+        connections: &Connections<Sender<AmsHeader>>,
+        stop: &AtomicBool,
+    ) -> io::Result<()> {
+        stream.set_read_timeout(Some(NOTIFICATION_INTERVAL))?;
+        let mut handshake = self.config.accept(stream);
+        let mut tls = loop {
+            match handshake {
+                Ok(tls) => break tls,
+                Err(HandshakeError::WouldBlock(mid)) if !stop.load(Ordering::SeqCst) => {
+                    handshake = mid.handshake();
+                }
+                Err(HandshakeError::Failure(mid)) => {
+                    close_rejected(mid.get_ref());
+                    return Err(handshake_error(HandshakeError::Failure(mid)));
+                }
+                Err(e) => return Err(handshake_error(e)),
+            }
+        };
+        let mut received: Vec<u8> = Vec::new();
+        let mut buffer = [0; 4096];
+        while !stop.load(Ordering::SeqCst) {
+            match tls.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n) => received.extend_from_slice(&buffer[..n]),
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+
+            while let Some(frame_len) = complete_frame_len(&received)? {
+                let frame: Vec<u8> = received.drain(..frame_len).collect();
+                let request = AmsTcpHeader::read_from(&mut frame.as_slice())?;
+                connections.add_client(request.ams_header.source_address(), id);
+                if let Some(response) = server.handle(request.ams_header) {
+                    send_tls(&mut tls, response)?;
+                }
+            }
+            while let Ok(header) = notifications.try_recv() {
+                send_tls(&mut tls, header)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn send(sink: &Sender<AmsHeader>, header: AmsHeader) -> io::Result<()> {
+        sink.send(header)
+            .map_err(|_| io::Error::new(io::ErrorKind::NotConnected, "connection closed"))
+    }
+}
+
+///Close a connection which failed the handshake without resetting it.
+///Data of the client which is not read yet would reset the connection and the client
+///could miss the alert, so it is read until the client closes the connection or [REJECT_LINGER] elapsed.
+fn close_rejected(mut stream: &TcpStream) {
+    let _ = stream.shutdown(Shutdown::Write);
+    let deadline = Instant::now() + REJECT_LINGER;
+    let mut buffer = [0; 4096];
+    while Instant::now() < deadline {
+        match stream.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+///Length of the first AMS/TCP frame if it was received completely.
+///Frames longer than [AMS_TCP_MAX_FRAME_LEN] are rejected before they are buffered.
+fn complete_frame_len(received: &[u8]) -> io::Result<Option<usize>> {
+    let length = match received.get(2..AMS_TCP_PREFIX_LEN) {
+        Some(length) => u32::from_le_bytes(length.try_into().expect("4 bytes")),
+        None => return Ok(None),
+    };
+    if length > AMS_TCP_MAX_FRAME_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "AMS/TCP frame length {} exceeds {}",
+                length, AMS_TCP_MAX_FRAME_LEN
+            ),
+        ));
+    }
+    let frame_len = AMS_TCP_PREFIX_LEN + length as usize;
+    Ok((received.len() >= frame_len).then_some(frame_len))
+}
+
+fn send_tls<W: Write>(tls: &mut W, header: AmsHeader) -> io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    AmsTcpHeader::from(header).write_to(&mut buffer)?;
+    tls.write_all(&buffer)?;
+    tls.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tls_client::TlsClient;
+    use crate::client::AdsClient;
+    use crate::error::AdsError;
+    use crate::proto::ams_address::AmsAddress;
+    use crate::proto::request::{ReadRequest, Request, WriteRequest};
+    use crate::proto::response::{ReadResponse, Response, WriteResponse};
+    use crate::server::test_util::{client_address, net_id};
+    use crate::server::virtual_plc::VirtualPlc;
+    use crate::tls::tests::TestPki;
+    use crate::tls::{client_config, server_config, TlsIdentity};
+    use std::time::Duration;
+
+    fn server() -> AdsServer {
+        let server = AdsServer::new(net_id());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        server
+    }
+
+    #[test]
+    fn ads_server_tls_test() {
+        let pki = TestPki::new();
+        let config = server_config(
+            pki.identity("localhost"),
+            Some(std::slice::from_ref(&pki.ca)),
+        )
+        .unwrap();
+        let handle = server().bind_tls("127.0.0.1:0", config).unwrap();
+
+        let config =
+            client_config(std::slice::from_ref(&pki.ca), Some(pki.identity("client"))).unwrap();
+        let mut client =
+            TlsClient::connect(handle.local_addr(), "localhost", &config, client_address())
+                .unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let plc = AmsAddress::new(net_id(), 851);
+        assert_eq!(
+            client
+                .request(
+                    &plc,
+                    Request::Write(WriteRequest::new(0x4020, 0, vec![7, 8]))
+                )
+                .unwrap(),
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        assert_eq!(
+            client
+                .request(&plc, Request::Read(ReadRequest::new(0x4020, 0, 2)))
+                .unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![7, 8]))
+        );
+        drop(client);
+        handle.shutdown();
+    }
+
+    #[test]
+    fn ads_server_tls_access_denied_test() {
+        let pki = TestPki::new();
+        let config = server_config(
+            pki.identity("localhost"),
+            Some(std::slice::from_ref(&pki.ca)),
+        )
+        .unwrap();
+        let handle = server().bind_tls("127.0.0.1:0", config).unwrap();
+
+        //Client certificate of another CA
+        let other = TestPki::new();
+        let config = client_config(
+            std::slice::from_ref(&pki.ca),
+            Some(other.identity("client")),
+        )
+        .unwrap();
+        let result =
+            TlsClient::connect(handle.local_addr(), "localhost", &config, client_address())
+                .and_then(|mut client| {
+                    client.set_read_timeout(Some(Duration::from_secs(5)))?;
+                    client.request(
+                        &AmsAddress::new(net_id(), 851),
+                        Request::Read(ReadRequest::new(0x4020, 0, 2)),
+                    )
+                });
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            Box::new(AdsError::ErrAccessDenied)
+        );
+
+        //Server certificate of an unknown CA
+        let config = client_config(std::slice::from_ref(&other.ca), None).unwrap();
+        let error = TlsClient::connect(handle.local_addr(), "localhost", &config, client_address())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+            Box::new(AdsError::ErrAccessDenied)
+        );
+        handle.shutdown();
+    }
+
+    #[test]
+    fn ads_server_tls_psk_test() {
+        let psk = TlsIdentity::psk("client", &[0x5A; 32]);
+        let handle = server()
+            .bind_tls("127.0.0.1:0", server_config(psk.clone(), None).unwrap())
+            .unwrap();
+        let plc = AmsAddress::new(net_id(), 851);
+
+        let config = client_config(&[], Some(psk)).unwrap();
+        let mut client =
+            TlsClient::connect(handle.local_addr(), "localhost", &config, client_address())
+                .unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert_eq!(
+            client
+                .request(&plc, Request::Read(ReadRequest::new(0x4020, 0, 2)))
+                .unwrap(),
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![0, 0]))
+        );
+        drop(client);
+
+        //Other key or identity
+        for psk in [
+            TlsIdentity::psk("client", &[0xA5; 32]),
+            TlsIdentity::psk("other", &[0x5A; 32]),
+        ] {
+            let config = client_config(&[], Some(psk)).unwrap();
+            let error =
+                TlsClient::connect(handle.local_addr(), "localhost", &config, client_address())
+                    .err()
+                    .unwrap();
+            assert_eq!(
+                error.into_inner().unwrap().downcast::<AdsError>().unwrap(),
+                Box::new(AdsError::ErrAccessDenied)
+            );
+        }
+        handle.shutdown();
+    }
+
+    #[test]
+    fn complete_frame_len_test() {
+        assert_eq!(complete_frame_len(&[0, 0, 2]).unwrap(), None);
+        assert_eq!(complete_frame_len(&[0, 0, 2, 0, 0, 0, 1]).unwrap(), None);
+        assert_eq!(
+            complete_frame_len(&[0, 0, 2, 0, 0, 0, 1, 2, 3]).unwrap(),
+            Some(8)
+        );
+        let error = complete_frame_len(&[0, 0, 0xFF, 0xFF, 0xFF, 0xFF]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::ams_header::AmsHeader;
+use crate::proto::state_flags::{NetProto, StateFlags};
+use crate::server::tcp_server::{AdsServer, NOTIFICATION_IDLE_INTERVAL};
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+///Max number of clients remembered by the UDP server, the client seen least recently is dropped
+pub const MAX_UDP_PEERS: usize = 1024;
+///Clients without requests and notifications for this time are dropped
+pub const UDP_PEER_TIMEOUT: Duration = Duration::from_secs(60);
+
+///Last response sent to a client. Requests sent again with the same invoke id are answered from here.
+struct LastResponse {
+    invoke_id: u32,
+    datagram: Vec<u8>,
+}
+
+struct UdpPeer {
+    last_seen: Instant,
+    last_response: Option<LastResponse>,
+}
+
+///Clients of the UDP server by the claimed AmsAddress and the socket address the requests came from
+#[derive(Default)]
+struct UdpPeers {
+    peers: HashMap<(AmsAddress, SocketAddr), UdpPeer>,
+}
+
+impl UdpPeers {
+    ///Peer which sent a request now
+    fn seen(&mut self, client: AmsAddress, addr: SocketAddr, now: Instant) -> &mut UdpPeer {
+        self.peers
+            .retain(|_, peer| now.saturating_duration_since(peer.last_seen) < UDP_PEER_TIMEOUT);
+        let key = (client, addr);
+        if !self.peers.contains_key(&key) && self.peers.len() >= MAX_UDP_PEERS {
+            let oldest = self
+                .peers
+                .iter()
+                .min_by_key(|(_, peer)| peer.last_seen)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.peers.remove(&oldest);
+            }
+        }
+        let peer = self.peers.entry(key).or_insert(UdpPeer {
+            last_seen: now,
+            last_response: None,
+        });
+        peer.last_seen = now;
+        peer
+    }
+
+    ///Socket address the client sent its last request from.
+    ///Sending a notification keeps the client from timing out.
+    fn notify(&mut self, client: &AmsAddress, now: Instant) -> Option<SocketAddr> {
+        let ((_, addr), peer) = self
+            .peers
+            .iter_mut()
+            .filter(|((address, _), _)| address == client)
+            .max_by_key(|(_, peer)| peer.last_seen)?;
+        peer.last_seen = now;
+        Some(*addr)
+    }
+}
+
+impl AdsServer {
+    ///Bind an UdpSocket and serve the devices. See [AdsServer::serve_udp]
+    pub fn bind_udp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<UdpServerHandle> {
+        self.serve_udp(UdpSocket::bind(addr)?)
+    }
+
+    ///Answer ADS requests received as UDP datagrams on a background thread.
+    ///TwinCAT devices use the port [ADS_UDP_PORT](crate::proto::ads_udp::ADS_UDP_PORT) for ADS over UDP.
+    ///
+    ///A request received again with the same invoke id as the last request of the client
+    ///(the response got lost) is answered with the last response without passing it to the device again.
+    ///Device notifications are sent to the socket address the client sent its last request from.
+    ///There is no connection, the devices are not informed about clients which are gone.
+    ///At most [MAX_UDP_PEERS] clients are remembered, clients are dropped after [UDP_PEER_TIMEOUT]
+    ///without requests and notifications.
+    pub fn serve_udp(&self, socket: UdpSocket) -> io::Result<UdpServerHandle> {
+        let local_addr = socket.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let server = self.clone();
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            let mut peers = UdpPeers::default();
+            let mut buffer = [0; 65536];
+            while !thread_stop.load(Ordering::SeqCst) {
+                let timeout = server.notification_timeout(NOTIFICATION_IDLE_INTERVAL);
+                let _ = socket.set_read_timeout(Some(timeout));
+                if let Ok((len, addr)) = socket.recv_from(&mut buffer) {
+                    if let Ok(header) = AmsHeader::from_datagram(&buffer[..len]) {
+                        let invoke_id = header.invoke_id();
+                        let peer =
+                            peers.seen(header.source_address().clone(), addr, Instant::now());
+                        match &peer.last_response {
+                            Some(last) if last.invoke_id == invoke_id => {
+                                let _ = socket.send_to(&last.datagram, addr);
+                            }
+                            _ => {
+                                if let Some(response) = server.handle(header) {
+                                    let datagram = response.to_datagram();
+                                    let _ = socket.send_to(&datagram, addr);
+                                    peer.last_response = Some(LastResponse {
+                                        invoke_id,
+                                        datagram,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let now = Instant::now();
+                if !server.notification_due(now) {
+                    continue;
+                }
+                for mut header in server.notifications(now) {
+                    if let Some(peer) = peers.notify(header.target_address(), now) {
+                        header.update_state_flags(StateFlags::new(false, true, NetProto::Udp));
+                        let _ = socket.send_to(&header.to_datagram(), peer);
+                    }
+                }
+            }
+        });
+
+        Ok(UdpServerHandle {
+            local_addr,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+///Handle of an [AdsServer] serving UDP. Shuts the server down when dropped.
+pub struct UdpServerHandle {
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl UdpServerHandle {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop_server();
+    }
+
+    fn stop_server(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for UdpServerHandle {
+    fn drop(&mut self) {
+        self.stop_server();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::udp_client::UdpClient;
+    use crate::client::AdsClient;
+    use crate::error::AdsError;
+    use crate::proto::request::{AddDeviceNotificationRequest, ReadRequest, Request, WriteRequest};
+    use crate::proto::response::{
+        AddDeviceNotificationResponse, AdsNotificationStream, ReadResponse, Response, WriteResponse,
+    };
+    use crate::server::notification_engine::NotificationEngine;
+    use crate::server::test_util::{client_address, net_id};
+    use crate::server::virtual_plc::VirtualPlc;
+    use std::convert::TryInto;
+    use std::time::Duration;
+
+    #[test]
+    fn udp_peers_test() {
+        let mut peers = UdpPeers::default();
+        let start = Instant::now();
+        let addr: SocketAddr = "127.0.0.1:1000".parse().unwrap();
+        let other_addr: SocketAddr = "127.0.0.1:1001".parse().unwrap();
+        peers.seen(client_address(), addr, start).last_response = Some(LastResponse {
+            invoke_id: 1,
+            datagram: vec![1],
+        });
+
+        //Same AmsAddress from another socket address
+        let later = start + Duration::from_secs(1);
+        assert!(peers
+            .seen(client_address(), other_addr, later)
+            .last_response
+            .is_none());
+        assert_eq!(peers.notify(&client_address(), later), Some(other_addr));
+        assert!(peers
+            .seen(client_address(), addr, later)
+            .last_response
+            .is_some());
+
+        //Timed out
+        let timeout = later + UDP_PEER_TIMEOUT;
+        let other = AmsAddress::new(net_id(), 30001);
+        peers.seen(other.clone(), addr, timeout);
+        assert_eq!(peers.notify(&client_address(), timeout), None);
+
+        //Least recently seen is dropped
+        for port in 0..MAX_UDP_PEERS as u16 {
+            peers.seen(
+                AmsAddress::new(net_id(), port),
+                addr,
+                timeout + Duration::from_millis(1),
+            );
+        }
+        assert_eq!(peers.peers.len(), MAX_UDP_PEERS);
+        assert_eq!(peers.notify(&other, timeout), None);
+    }
+
+    #[test]
+    fn ads_server_udp_test() {
+        let server = AdsServer::new(net_id());
+        server.add_device(851, VirtualPlc::new(4, 0, 0));
+        let handle = server.bind_udp("127.0.0.1:0").unwrap();
+
+        let mut client = UdpClient::connect(handle.local_addr(), client_address()).unwrap();
+        let plc = AmsAddress::new(net_id(), 851);
+        let response = client
+            .request(
+                &plc,
+                Request::Write(WriteRequest::new(0x4020, 0, vec![1, 2])),
+            )
+            .unwrap();
+        assert_eq!(
+            response,
+            Response::Write(WriteResponse::new(AdsError::ErrNoError))
+        );
+        let response = client
+            .request(&plc, Request::Read(ReadRequest::new(0x4020, 0, 2)))
+            .unwrap();
+        assert_eq!(
+            response,
+            Response::Read(ReadResponse::new(AdsError::ErrNoError, vec![1, 2]))
+        );
+        handle.shutdown();
+    }
+
+    #[test]
+    fn ads_server_udp_duplicate_and_notification_test() {
+        let server = AdsServer::new(net_id());
+        let engine = server.add_device(851, NotificationEngine::new(VirtualPlc::new(4, 0, 0)));
+        let handle = server.bind_udp("127.0.0.1:0").unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let request = AddDeviceNotificationRequest::builder(0x4020, 0)
+            .on_change(Duration::ZERO)
+            .length(1)
+            .build()
+            .unwrap();
+        let datagram = AmsHeader::new(
+            AmsAddress::new(net_id(), 851),
+            client_address(),
+            StateFlags::new(false, true, NetProto::Udp),
+            3,
+            Request::AddDeviceNotification(request),
+        )
+        .to_datagram();
+        //Sent twice, e.g. because the first response got lost
+        socket.send_to(&datagram, handle.local_addr()).unwrap();
+        socket.send_to(&datagram, handle.local_addr()).unwrap();
+
+        let mut buffer = [0; 2048];
+        let mut responses: Vec<AddDeviceNotificationResponse> = Vec::new();
+        let mut notification: Option<AdsNotificationStream> = None;
+        while responses.len() < 2 || notification.is_none() {
+            let len = socket.recv(&mut buffer).unwrap();
+            let mut header = AmsHeader::from_datagram(&buffer[..len]).unwrap();
+            assert!(!header.state_flags().is_tcp());
+            assert_eq!(header.target_address(), &client_address());
+            match header.response().unwrap() {
+                Response::AddDeviceNotification(r) => responses.push(r),
+                response => notification = Some(response.try_into().unwrap()),
+            }
+        }
+        assert_eq!(responses[0], responses[1]);
+        assert_eq!(responses[0].result, AdsError::ErrNoError);
+        assert_eq!(engine.lock().unwrap().len(), 1);
+        let notification = notification.unwrap();
+        assert_eq!(
+            notification.ads_stamp_headers[0].notification_samples[0].notification_handle,
+            responses[0].notification_handle
+        );
+        handle.shutdown();
+    }
+}
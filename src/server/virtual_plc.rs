@@ -0,0 +1,432 @@
+use crate::ads_services::system_services::{
+    ADSIGRP_IOIMAGE_RWIB, ADSIGRP_IOIMAGE_RWIX, ADSIGRP_IOIMAGE_RWOB, ADSIGRP_IOIMAGE_RWOX,
+    ADSIGRP_PLC_RWMB, ADSIGRP_PLC_RWMX,
+};
+use crate::error::AdsError;
+use crate::proto::ads_state::AdsState;
+use crate::proto::ams_address::AmsAddress;
+use crate::proto::request::{ReadRequest, ReadWriteRequest, WriteControlRequest, WriteRequest};
+use crate::proto::response::{
+    ReadDeviceInfoResponse, ReadResponse, ReadStateResponse, ReadWriteResponse,
+    WriteControlResponse, WriteResponse,
+};
+use crate::server::AdsDevice;
+
+///Memory areas of the virtual PLC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProcessImage {
+    ///%M
+    Memory,
+    ///%I
+    Inputs,
+    ///%Q
+    Outputs,
+}
+
+impl ProcessImage {
+    ///Index group for byte access
+    pub fn index_group(&self) -> u32 {
+        match self {
+            ProcessImage::Memory => ADSIGRP_PLC_RWMB.index_group,
+            ProcessImage::Inputs => ADSIGRP_IOIMAGE_RWIB.index_group,
+            ProcessImage::Outputs => ADSIGRP_IOIMAGE_RWOB.index_group,
+        }
+    }
+
+    ///Index group for bit access. The index offset is the bit offset.
+    pub fn bit_index_group(&self) -> u32 {
+        match self {
+            ProcessImage::Memory => ADSIGRP_PLC_RWMX.index_group,
+            ProcessImage::Inputs => ADSIGRP_IOIMAGE_RWIX.index_group,
+            ProcessImage::Outputs => ADSIGRP_IOIMAGE_RWOX.index_group,
+        }
+    }
+
+    ///Returns the process image and true for bit access
+    pub fn from_index_group(index_group: u32) -> Option<(ProcessImage, bool)> {
+        [
+            ProcessImage::Memory,
+            ProcessImage::Inputs,
+            ProcessImage::Outputs,
+        ]
+        .into_iter()
+        .find_map(|image| {
+            if image.index_group() == index_group {
+                Some((image, false))
+            } else if image.bit_index_group() == index_group {
+                Some((image, true))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+///Simulated PLC runtime for integration tests without a controller.
+///Serves the %M, %I and %Q process images with byte and bit access, answers device info and state requests
+///and follows the state transitions requested with WriteControl:
+///- Run -> Stop, Config
+///- Stop -> Run, Config
+///- Config -> Run
+///
+///The process images can only be accessed in Run and Stop.
+///Bit access reads and writes one byte (0 or 1) per bit.
+#[derive(Debug, Clone)]
+pub struct VirtualPlc {
+    memory: Vec<u8>,
+    inputs: Vec<u8>,
+    outputs: Vec<u8>,
+    device_name: String,
+    major_version: u8,
+    minor_version: u8,
+    version_build: u16,
+    ads_state: AdsState,
+    device_state: u16,
+}
+
+impl VirtualPlc {
+    ///Create a PLC in Run with the given process image sizes in bytes
+    pub fn new(memory_size: usize, input_size: usize, output_size: usize) -> Self {
+        VirtualPlc {
+            memory: vec![0; memory_size],
+            inputs: vec![0; input_size],
+            outputs: vec![0; output_size],
+            device_name: String::from("Virtual PLC"),
+            major_version: 3,
+            minor_version: 1,
+            version_build: 4024,
+            ads_state: AdsState::AdsStateRun,
+            device_state: 0,
+        }
+    }
+
+    ///Set the values returned for ReadDeviceInfo. The device name is cut after 16 bytes.
+    pub fn set_device_info(
+        &mut self,
+        device_name: &str,
+        major_version: u8,
+        minor_version: u8,
+        version_build: u16,
+    ) {
+        self.device_name = device_name.to_string();
+        self.major_version = major_version;
+        self.minor_version = minor_version;
+        self.version_build = version_build;
+    }
+
+    pub fn ads_state(&self) -> AdsState {
+        self.ads_state
+    }
+
+    pub fn device_state(&self) -> u16 {
+        self.device_state
+    }
+
+    ///Set the state without checking the transition
+    pub fn set_state(&mut self, ads_state: AdsState, device_state: u16) {
+        self.ads_state = ads_state;
+        self.device_state = device_state;
+    }
+
+    pub fn image(&self, image: ProcessImage) -> &[u8] {
+        match image {
+            ProcessImage::Memory => &self.memory,
+            ProcessImage::Inputs => &self.inputs,
+            ProcessImage::Outputs => &self.outputs,
+        }
+    }
+
+    pub fn image_mut(&mut self, image: ProcessImage) -> &mut [u8] {
+        match image {
+            ProcessImage::Memory => &mut self.memory,
+            ProcessImage::Inputs => &mut self.inputs,
+            ProcessImage::Outputs => &mut self.outputs,
+        }
+    }
+
+    ///Read from a process image by index group and offset
+    pub fn read_area(
+        &self,
+        index_group: u32,
+        index_offset: u32,
+        length: u32,
+    ) -> Result<Vec<u8>, AdsError> {
+        let (image, bit_access) = self.accessible_image(index_group)?;
+        let data = self.image(image);
+        if bit_access {
+            (0..length as u64)
+                .map(|n| {
+                    let (byte, bit) = VirtualPlc::bit_position(data, index_offset as u64 + n)?;
+                    Ok((data[byte] >> bit) & 1)
+                })
+                .collect()
+        } else {
+            let range = VirtualPlc::byte_range(data, index_offset, length)?;
+            Ok(data[range].to_vec())
+        }
+    }
+
+    ///Write to a process image by index group and offset
+    pub fn write_area(
+        &mut self,
+        index_group: u32,
+        index_offset: u32,
+        values: &[u8],
+    ) -> Result<(), AdsError> {
+        let (image, bit_access) = self.accessible_image(index_group)?;
+        let data = self.image_mut(image);
+        if bit_access {
+            let positions = (0..values.len() as u64)
+                .map(|n| VirtualPlc::bit_position(data, index_offset as u64 + n))
+                .collect::<Result<Vec<(usize, u32)>, AdsError>>()?;
+            for ((byte, bit), value) in positions.into_iter().zip(values) {
+                if *value != 0 {
+                    data[byte] |= 1 << bit;
+                } else {
+                    data[byte] &= !(1 << bit);
+                }
+            }
+        } else {
+            let range = VirtualPlc::byte_range(data, index_offset, values.len() as u32)?;
+            data[range].copy_from_slice(values);
+        }
+        Ok(())
+    }
+
+    fn accessible_image(&self, index_group: u32) -> Result<(ProcessImage, bool), AdsError> {
+        let image =
+            ProcessImage::from_index_group(index_group).ok_or(AdsError::AdsErrDeviceInvalidGrp)?;
+        match self.ads_state {
+            AdsState::AdsStateRun | AdsState::AdsStateStop => Ok(image),
+            _ => Err(AdsError::AdsErrDeviceNotReady),
+        }
+    }
+
+    fn byte_range(
+        data: &[u8],
+        index_offset: u32,
+        length: u32,
+    ) -> Result<std::ops::Range<usize>, AdsError> {
+        let start = index_offset as usize;
+        if start > data.len() || (start == data.len() && length > 0) {
+            return Err(AdsError::AdsErrDeviceInvalidOffset);
+        }
+        let end = start + length as usize;
+        if end > data.len() {
+            return Err(AdsError::AdsErrDeviceInvalidSize);
+        }
+        Ok(start..end)
+    }
+
+    fn bit_position(data: &[u8], bit_offset: u64) -> Result<(usize, u32), AdsError> {
+        let byte = (bit_offset / 8) as usize;
+        if byte >= data.len() {
+            return Err(AdsError::AdsErrDeviceInvalidOffset);
+        }
+        Ok((byte, (bit_offset % 8) as u32))
+    }
+
+    fn transition(&mut self, ads_state: AdsState) -> Result<(), AdsError> {
+        use AdsState::*;
+        match (self.ads_state, ads_state) {
+            (_, AdsStateRun) | (_, AdsStateConfig) => (),
+            (AdsStateRun, AdsStateStop) | (AdsStateStop, AdsStateStop) => (),
+            (_, AdsStateStop) => return Err(AdsError::AdsErrDeviceInvalidState),
+            _ => return Err(AdsError::AdsErrDeviceInvalidParm),
+        }
+        self.ads_state = ads_state;
+        Ok(())
+    }
+}
+
+impl Default for VirtualPlc {
+    ///64 KiB of memory, 4 KiB inputs and 4 KiB outputs
+    fn default() -> Self {
+        VirtualPlc::new(0x10000, 0x1000, 0x1000)
+    }
+}
+
+impl AdsDevice for VirtualPlc {
+    fn read_device_info(&mut self, _source: &AmsAddress) -> ReadDeviceInfoResponse {
+        ReadDeviceInfoResponse::new(
+            AdsError::ErrNoError,
+            self.major_version,
+            self.minor_version,
+            self.version_build,
+            ReadDeviceInfoResponse::create_device_name_buf(&self.device_name),
+        )
+    }
+
+    fn read_state(&mut self, _source: &AmsAddress) -> ReadStateResponse {
+        ReadStateResponse::new(AdsError::ErrNoError, self.ads_state, self.device_state)
+    }
+
+    fn read(&mut self, _source: &AmsAddress, request: &ReadRequest) -> ReadResponse {
+        match self.read_area(request.index_group, request.index_offset, request.length) {
+            Ok(data) => ReadResponse::new(AdsError::ErrNoError, data),
+            Err(e) => ReadResponse::new(e, Vec::new()),
+        }
+    }
+
+    fn write(&mut self, _source: &AmsAddress, request: &WriteRequest) -> WriteResponse {
+        match self.write_area(request.index_group, request.index_offset, &request.data) {
+            Ok(()) => WriteResponse::new(AdsError::ErrNoError),
+            Err(e) => WriteResponse::new(e),
+        }
+    }
+
+    ///Writes the data and reads back read_length bytes from the same offset
+    fn read_write(
+        &mut self,
+        _source: &AmsAddress,
+        request: &ReadWriteRequest,
+    ) -> ReadWriteResponse {
+        let result = self
+            .write_area(request.index_group, request.index_offset, &request.data)
+            .and_then(|_| {
+                self.read_area(
+                    request.index_group,
+                    request.index_offset,
+                    request.read_length,
+                )
+            });
+        match result {
+            Ok(data) => ReadWriteResponse::new(AdsError::ErrNoError, data),
+            Err(e) => ReadWriteResponse::new(e, Vec::new()),
+        }
+    }
+
+    fn write_control(
+        &mut self,
+        _source: &AmsAddress,
+        request: &WriteControlRequest,
+    ) -> WriteControlResponse {
+        match self.transition(request.ads_state) {
+            Ok(()) => {
+                self.device_state = request.device_state;
+                WriteControlResponse::new(AdsError::ErrNoError)
+            }
+            Err(e) => WriteControlResponse::new(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::test_util::client_address;
+
+    #[test]
+    fn process_image_index_group_test() {
+        assert_eq!(
+            ProcessImage::from_index_group(0x4020),
+            Some((ProcessImage::Memory, false))
+        );
+        assert_eq!(
+            ProcessImage::from_index_group(0xF021),
+            Some((ProcessImage::Inputs, true))
+        );
+        assert_eq!(
+            ProcessImage::from_index_group(0xF031),
+            Some((ProcessImage::Outputs, true))
+        );
+        assert_eq!(ProcessImage::from_index_group(0x4040), None);
+    }
+
+    #[test]
+    fn virtual_plc_read_write_test() {
+        let mut plc = VirtualPlc::new(8, 2, 2);
+        let response = plc.write(
+            &client_address(),
+            &WriteRequest::new(0x4020, 2, vec![1, 2, 3]),
+        );
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(plc.image(ProcessImage::Memory), [0, 0, 1, 2, 3, 0, 0, 0]);
+
+        let response = plc.read(&client_address(), &ReadRequest::new(0x4020, 3, 2));
+        assert_eq!(
+            response,
+            ReadResponse::new(AdsError::ErrNoError, vec![2, 3])
+        );
+
+        let response = plc.read(&client_address(), &ReadRequest::new(0xF030, 1, 2));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidSize);
+        let response = plc.read(&client_address(), &ReadRequest::new(0xF020, 2, 1));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidOffset);
+        let response = plc.read(&client_address(), &ReadRequest::new(0x4040, 0, 1));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidGrp);
+
+        let response = plc.read_write(
+            &client_address(),
+            &ReadWriteRequest::new(0xF030, 0, 2, vec![9]),
+        );
+        assert_eq!(
+            response,
+            ReadWriteResponse::new(AdsError::ErrNoError, vec![9, 0])
+        );
+    }
+
+    #[test]
+    fn virtual_plc_bit_access_test() {
+        let mut plc = VirtualPlc::new(2, 2, 2);
+        //%MX0.1, %MX0.2 and %MX1.0
+        plc.write(&client_address(), &WriteRequest::new(0x4021, 1, vec![1, 1]));
+        plc.write(&client_address(), &WriteRequest::new(0x4021, 8, vec![1]));
+        assert_eq!(plc.image(ProcessImage::Memory), [0b110, 1]);
+
+        plc.write(&client_address(), &WriteRequest::new(0x4021, 2, vec![0]));
+        assert_eq!(plc.image(ProcessImage::Memory), [0b010, 1]);
+
+        let response = plc.read(&client_address(), &ReadRequest::new(0x4021, 0, 3));
+        assert_eq!(response.data, vec![0, 1, 0]);
+
+        plc.image_mut(ProcessImage::Inputs)[1] = 0x80;
+        let response = plc.read(&client_address(), &ReadRequest::new(0xF021, 15, 1));
+        assert_eq!(response.data, vec![1]);
+
+        let response = plc.write(
+            &client_address(),
+            &WriteRequest::new(0xF031, 15, vec![1, 1]),
+        );
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidOffset);
+        assert_eq!(plc.image(ProcessImage::Outputs), [0, 0]);
+    }
+
+    #[test]
+    fn virtual_plc_device_info_test() {
+        let mut plc = VirtualPlc::default();
+        plc.set_device_info("TestPLC", 3, 1, 4026);
+        let response = plc.read_device_info(&client_address());
+        assert_eq!(response.get_device_name().unwrap(), "TestPLC");
+        assert_eq!(response.version_build, 4026);
+
+        let response = plc.read_state(&client_address());
+        assert_eq!(response.ads_state, AdsState::AdsStateRun);
+    }
+
+    #[test]
+    fn virtual_plc_write_control_test() {
+        let mut plc = VirtualPlc::new(2, 2, 2);
+        let request = |state| WriteControlRequest::new(state, 1, 0, Vec::new());
+
+        let response = plc.write_control(&client_address(), &request(AdsState::AdsStateStop));
+        assert_eq!(response.result, AdsError::ErrNoError);
+        assert_eq!(plc.ads_state(), AdsState::AdsStateStop);
+        assert_eq!(plc.device_state(), 1);
+
+        plc.write_control(&client_address(), &request(AdsState::AdsStateConfig));
+        assert_eq!(plc.ads_state(), AdsState::AdsStateConfig);
+        //No memory access in config mode
+        let response = plc.read(&client_address(), &ReadRequest::new(0x4020, 0, 1));
+        assert_eq!(response.result, AdsError::AdsErrDeviceNotReady);
+
+        let response = plc.write_control(&client_address(), &request(AdsState::AdsStateStop));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidState);
+        let response = plc.write_control(&client_address(), &request(AdsState::AdsStateSuspend));
+        assert_eq!(response.result, AdsError::AdsErrDeviceInvalidParm);
+        assert_eq!(plc.ads_state(), AdsState::AdsStateConfig);
+
+        plc.write_control(&client_address(), &request(AdsState::AdsStateRun));
+        assert_eq!(plc.ads_state(), AdsState::AdsStateRun);
+    }
+}
@@ -0,0 +1,279 @@
+use crate::error::AdsError;
+use openssl::error::ErrorStack;
+use openssl::pkey::{PKey, Private};
+use openssl::ssl::{
+    HandshakeError, SslAcceptor, SslConnector, SslContextBuilder, SslMethod, SslVerifyMode,
+    SslVersion,
+};
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::X509;
+use std::io;
+use std::net::TcpStream;
+
+///TCP port of Secure ADS (AMS/TCP over TLS)
+pub const ADS_SECURE_PORT: u16 = 8016;
+
+///TLS 1.2 cipher suites with a pre-shared key
+const PSK_CIPHERS: &str = "PSK";
+///Reason codes of OpenSSL for an alert received from the peer start here (1000 + alert)
+const SSL_AD_REASON_OFFSET: i32 = 1000;
+///Reason code of OpenSSL for a certificate of the peer which is not accepted
+const SSL_R_CERTIFICATE_VERIFY_FAILED: i32 = 134;
+
+///Identity of a Secure ADS client or server
+#[derive(Debug, Clone)]
+pub enum TlsIdentity {
+    ///Certificate chain (leaf first) and private key
+    Certificate {
+        cert_chain: Vec<X509>,
+        key: PKey<Private>,
+    },
+    ///Pre-shared key and the identity it belongs to (TLS-PSK).
+    ///Both sides use the same identity and key.
+    Psk { identity: String, key: Vec<u8> },
+}
+
+impl TlsIdentity {
+    pub fn certificate(cert_chain: Vec<X509>, key: PKey<Private>) -> Self {
+        TlsIdentity::Certificate { cert_chain, key }
+    }
+
+    pub fn psk(identity: &str, key: &[u8]) -> Self {
+        TlsIdentity::Psk {
+            identity: identity.to_string(),
+            key: key.to_vec(),
+        }
+    }
+
+    ///Certificate chain and private key from PEM (PKCS#8, PKCS#1 or SEC1 key)
+    pub fn from_pem(cert_chain: &str, key: &str) -> io::Result<Self> {
+        let cert_chain = certificates_from_pem(cert_chain)?;
+        let key = PKey::private_key_from_pem(key.as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(TlsIdentity::Certificate { cert_chain, key })
+    }
+}
+
+///All certificates of a PEM file
+pub fn certificates_from_pem(pem: &str) -> io::Result<Vec<X509>> {
+    let certificates = X509::stack_from_pem(pem.as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    if certificates.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no certificate in PEM",
+        ));
+    }
+    Ok(certificates)
+}
+
+fn tls_error(error: ErrorStack) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error)
+}
+
+///Trust only the certificates issued by `roots`
+fn set_roots(builder: &mut SslContextBuilder, roots: &[X509]) -> io::Result<()> {
+    let mut store = X509StoreBuilder::new().map_err(tls_error)?;
+    for root in roots {
+        store.add_cert(root.clone()).map_err(tls_error)?;
+    }
+    builder.set_cert_store(store.build());
+    Ok(())
+}
+
+fn set_certificate(
+    builder: &mut SslContextBuilder,
+    cert_chain: &[X509],
+    key: &PKey<Private>,
+) -> io::Result<()> {
+    let (leaf, chain) = cert_chain
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty certificate chain"))?;
+    builder.set_certificate(leaf).map_err(tls_error)?;
+    for cert in chain {
+        builder
+            .add_extra_chain_cert(cert.clone())
+            .map_err(tls_error)?;
+    }
+    builder.set_private_key(key).map_err(tls_error)?;
+    builder.check_private_key().map_err(tls_error)
+}
+
+///TLS 1.2 with the PSK cipher suites, there are no certificates to verify
+fn set_psk_ciphers(builder: &mut SslContextBuilder) -> io::Result<()> {
+    builder
+        .set_max_proto_version(Some(SslVersion::TLS1_2))
+        .map_err(tls_error)?;
+    builder.set_cipher_list(PSK_CIPHERS).map_err(tls_error)?;
+    builder.set_verify(SslVerifyMode::NONE);
+    Ok(())
+}
+
+///Client config trusting the server certificates issued by `roots`.
+///With a certificate identity the client authenticates with its certificate as well (required by TwinCAT with a shared CA).
+///With a PSK identity client and server authenticate with the key, `roots` are not used.
+pub fn client_config(roots: &[X509], identity: Option<TlsIdentity>) -> io::Result<SslConnector> {
+    let mut builder = SslConnector::builder(SslMethod::tls_client()).map_err(tls_error)?;
+    match identity {
+        Some(TlsIdentity::Certificate { cert_chain, key }) => {
+            set_roots(&mut builder, roots)?;
+            set_certificate(&mut builder, &cert_chain, &key)?;
+        }
+        Some(TlsIdentity::Psk { identity, key }) => {
+            set_psk_ciphers(&mut builder)?;
+            builder.set_psk_client_callback(move |_, _hint, identity_buffer, psk_buffer| {
+                //The identity is sent NUL terminated
+                if identity.len() >= identity_buffer.len() || key.len() > psk_buffer.len() {
+                    return Ok(0);
+                }
+                identity_buffer[..identity.len()].copy_from_slice(identity.as_bytes());
+                identity_buffer[identity.len()] = 0;
+                psk_buffer[..key.len()].copy_from_slice(&key);
+                Ok(key.len())
+            });
+        }
+        None => set_roots(&mut builder, roots)?,
+    }
+    Ok(builder.build())
+}
+
+///Server config with the identity of the server.
+///With `client_roots` only clients with a certificate issued by one of them are accepted.
+///With a PSK identity only clients with the same identity and key are accepted, `client_roots` must be None.
+pub fn server_config(
+    identity: TlsIdentity,
+    client_roots: Option<&[X509]>,
+) -> io::Result<SslAcceptor> {
+    let mut builder =
+        SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server()).map_err(tls_error)?;
+    match identity {
+        TlsIdentity::Certificate { cert_chain, key } => {
+            set_certificate(&mut builder, &cert_chain, &key)?;
+            if let Some(roots) = client_roots {
+                set_roots(&mut builder, roots)?;
+                builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+            }
+        }
+        TlsIdentity::Psk { identity, key } => {
+            if client_roots.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "client certificates are not used with a PSK identity",
+                ));
+            }
+            set_psk_ciphers(&mut builder)?;
+            builder.set_psk_server_callback(move |_, client_identity, psk_buffer| {
+                //A key of length 0 rejects the client
+                if client_identity != Some(identity.as_bytes()) || key.len() > psk_buffer.len() {
+                    return Ok(0);
+                }
+                psk_buffer[..key.len()].copy_from_slice(&key);
+                Ok(key.len())
+            });
+        }
+    }
+    Ok(builder.build())
+}
+
+///True for TLS errors caused by the peer not accepting us or us not accepting the peer
+fn is_rejected(error: &openssl::ssl::Error) -> bool {
+    error.ssl_error().is_some_and(|stack| {
+        stack.errors().iter().any(|e| {
+            e.reason_code() >= SSL_AD_REASON_OFFSET
+                || e.reason_code() == SSL_R_CERTIFICATE_VERIFY_FAILED
+        })
+    })
+}
+
+///Map an error of the TLS connection to the AdsError of Secure ADS.
+///Rejected peers (e.g. certificate or PSK not accepted) are ErrAccessDenied, other TLS errors ErrTlsSend.
+pub(crate) fn ads_tls_error(error: io::Error) -> io::Error {
+    let rejected = error
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<openssl::ssl::Error>())
+        .map(is_rejected);
+    match rejected {
+        Some(true) => io::Error::other(AdsError::ErrAccessDenied),
+        Some(false) => io::Error::other(AdsError::ErrTlsSend),
+        None => error,
+    }
+}
+
+///Map a failed handshake to ErrAccessDenied unless the connection failed
+pub(crate) fn handshake_error(error: HandshakeError<TcpStream>) -> io::Error {
+    match error {
+        HandshakeError::SetupFailure(e) => tls_error(e),
+        HandshakeError::Failure(mid) | HandshakeError::WouldBlock(mid) => {
+            let error = mid.into_error();
+            match error.io_error() {
+                Some(_) => error.into_io_error().expect("io error checked before"),
+                None => io::Error::other(AdsError::ErrAccessDenied),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+    ///CA and a leaf certificate for localhost signed by it
+    pub(crate) struct TestPki {
+        pub ca: X509,
+        ca_cert: rcgen::Certificate,
+        ca_key: KeyPair,
+    }
+
+    impl TestPki {
+        pub(crate) fn new() -> Self {
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params
+                .distinguished_name
+                .push(DnType::CommonName, "ADS test CA");
+            let ca_key = KeyPair::generate().unwrap();
+            let ca_cert = params.self_signed(&ca_key).unwrap();
+            TestPki {
+                ca: X509::from_der(ca_cert.der()).unwrap(),
+                ca_cert,
+                ca_key,
+            }
+        }
+
+        pub(crate) fn identity(&self, name: &str) -> TlsIdentity {
+            let key = KeyPair::generate().unwrap();
+            //The subject must differ from the CA, otherwise the certificate counts as self-signed
+            let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+            params.distinguished_name.push(DnType::CommonName, name);
+            let cert = params.signed_by(&key, &self.ca_cert, &self.ca_key).unwrap();
+            TlsIdentity::from_pem(&cert.pem(), &key.serialize_pem()).unwrap()
+        }
+    }
+
+    #[test]
+    fn tls_config_test() {
+        let pki = TestPki::new();
+        assert!(server_config(pki.identity("localhost"), None).is_ok());
+        assert!(server_config(
+            pki.identity("localhost"),
+            Some(std::slice::from_ref(&pki.ca))
+        )
+        .is_ok());
+        assert!(client_config(std::slice::from_ref(&pki.ca), Some(pki.identity("client"))).is_ok());
+
+        let psk = TlsIdentity::psk("client", &[1; 32]);
+        assert!(server_config(psk.clone(), None).is_ok());
+        assert_eq!(
+            server_config(psk.clone(), Some(std::slice::from_ref(&pki.ca)))
+                .err()
+                .unwrap()
+                .kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert!(client_config(&[], Some(psk)).is_ok());
+
+        assert!(TlsIdentity::from_pem("no pem", "no pem").is_err());
+        assert!(certificates_from_pem("").is_err());
+    }
+}